@@ -0,0 +1,876 @@
+//! Layered bridge configuration.
+//!
+//! Settings are resolved from, in increasing precedence:
+//! 1. built-in defaults,
+//! 2. the user file `~/.config/acplazybridge/config.toml`
+//!    (`$XDG_CONFIG_HOME/acplazybridge/config.toml` when set),
+//! 3. a project-local `.acplb.toml` in the session working directory,
+//! 4. `ACPLB_*` / `CODEX_*` environment variables,
+//! 5. explicit overrides supplied by the binary (command-line flags).
+//!
+//! Every field is optional so layers merge field by field. The project file
+//! comes from the repository being edited and is not trusted: it may only
+//! tune timeouts, queueing and the default mode (see `ProjectConfig`), and
+//! its `[permissions]` may narrow but never widen what the other layers
+//! allow. Permission
+//! environment variables (`ACPLB_APPROVAL_POLICY`, ...) keep being applied on
+//! top of the resolved overrides by `PermissionOverrides`.
+//!
+//! ```toml
+//! [runtime]
+//! idle_timeout_ms = 600000
+//! prompt_queue = "reject"
+//!
+//! [codex]
+//! command = "/opt/codex/bin/codex"
+//! args = ["-c", "model_verbosity=\"low\""]
+//!
+//! [claude]
+//! command = "/opt/claude/bin/claude"
+//!
+//! [gemini]
+//! command = "/opt/gemini/bin/gemini"
+//!
+//! [notify]
+//! path = "/tmp/codex-notify.jsonl"
+//! kind = "file"
+//!
+//! [permissions]
+//! network_access = false
+//!
+//! [permissions.modes.acceptEdits]
+//! approval_policy = "on-request"
+//! ```
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::permissions::{map_acp_to_codex, AcpPermissionMode, CodexTurnOverrides};
+use crate::runtime::{PromptQueuePolicy, RuntimeConfig};
+
+/// Project-local configuration file looked up in the session working directory.
+pub const PROJECT_CONFIG_FILE: &str = ".acplb.toml";
+
+const DEFAULT_IDLE_TIMEOUT_MS: u64 = 1_000_000;
+const DEFAULT_POLLING_INTERVAL_MS: u64 = 1000;
+const DEFAULT_CODEX_COMMAND: &str = "codex";
+const DEFAULT_CLAUDE_COMMAND: &str = "claude";
+const DEFAULT_GEMINI_COMMAND: &str = "gemini";
+
+/// Approval policies, from the one that asks most to the one that never asks.
+const APPROVAL_POLICIES: [&str; 4] = ["untrusted", "on-failure", "on-request", "never"];
+/// Sandbox modes, from the narrowest to the widest.
+const SANDBOX_MODES: [&str; 3] = ["read-only", "workspace-write", "danger-full-access"];
+const NOTIFY_KINDS: [&str; 2] = ["file", "fifo"];
+const NOTIFY_INJECT_MODES: [&str; 3] = ["auto", "never", "force"];
+
+/// Errors raised while loading or validating configuration.
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("failed to read config file {path}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("invalid TOML in {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("invalid configuration in {origin}: {message}")]
+    Invalid { origin: String, message: String },
+}
+
+/// `[runtime]` section, feeding `RuntimeConfig`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuntimeSection {
+    pub idle_timeout_ms: Option<u64>,
+    pub polling_interval_ms: Option<u64>,
+    pub evidence_path: Option<PathBuf>,
+    pub state_dir: Option<PathBuf>,
+    pub session_ttl_ms: Option<u64>,
+    pub max_sessions: Option<usize>,
+    pub prompt_queue: Option<PromptQueuePolicy>,
+    /// Permission mode id for new sessions (e.g. `acceptEdits`).
+    pub default_mode: Option<AcpPermissionMode>,
+}
+
+/// `[codex]` section: how the Codex CLI is launched.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CodexSection {
+    /// Codex executable (`CODEX_RUN` / `CODEX_CMD`).
+    pub command: Option<String>,
+    /// Extra arguments appended after the bridge's own `proto` overrides.
+    pub args: Option<Vec<String>>,
+}
+
+/// `[claude]` section: how the Claude Code CLI is launched.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClaudeSection {
+    /// Claude executable (`ACPLB_CLAUDE_CMD`).
+    pub command: Option<String>,
+    /// Extra arguments appended after the bridge's own flags.
+    pub args: Option<Vec<String>>,
+}
+
+/// `[gemini]` section: how the Gemini CLI is launched.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GeminiSection {
+    /// Gemini executable (`ACPLB_GEMINI_CMD`).
+    pub command: Option<String>,
+    /// Extra arguments appended after the bridge's own flags.
+    pub args: Option<Vec<String>>,
+}
+
+/// `[notify]` section: Codex turn-completion notifications.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotifySection {
+    /// Notify sink watched by the bridge (`ACPLB_NOTIFY_PATH`).
+    pub path: Option<PathBuf>,
+    /// `file` or `fifo` (`ACPLB_NOTIFY_KIND`).
+    pub kind: Option<String>,
+    /// `auto`, `never` or `force` forwarder injection (`ACPLB_NOTIFY_INJECT`).
+    pub inject: Option<String>,
+    /// Custom Codex `notify` command (`ACPLB_NOTIFY_CMD`).
+    pub command: Option<String>,
+}
+
+/// Codex permission fields that can be overridden.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PermissionSettings {
+    pub approval_policy: Option<String>,
+    pub sandbox_mode: Option<String>,
+    pub network_access: Option<bool>,
+    pub web_search: Option<bool>,
+}
+
+/// `[permissions]` section: settings for every mode, refined per mode under
+/// `[permissions.modes.<mode id>]`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PermissionsSection {
+    pub approval_policy: Option<String>,
+    pub sandbox_mode: Option<String>,
+    pub network_access: Option<bool>,
+    pub web_search: Option<bool>,
+    pub modes: BTreeMap<String, PermissionSettings>,
+}
+
+/// Bridge configuration; one instance per layer, merged with `overlay`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BridgeConfig {
+    pub runtime: RuntimeSection,
+    pub codex: CodexSection,
+    pub claude: ClaudeSection,
+    pub gemini: GeminiSection,
+    pub notify: NotifySection,
+    pub permissions: PermissionsSection,
+}
+
+impl BridgeConfig {
+    /// Parse and validate a TOML document; `origin` names it in errors.
+    pub fn from_toml_str(contents: &str, origin: &Path) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(contents).map_err(|source| ConfigError::Parse {
+            path: origin.to_path_buf(),
+            source,
+        })?;
+        config.validate(&origin.display().to_string())?;
+        Ok(config)
+    }
+
+    /// Read a configuration file.
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let contents = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_toml_str(&contents, path)
+    }
+
+    /// Read a configuration file if it exists.
+    pub fn from_optional_file(path: &Path) -> Result<Option<Self>, ConfigError> {
+        if path.is_file() {
+            Self::from_file(path).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Configuration layer taken from environment variables.
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let config = Self {
+            runtime: RuntimeSection {
+                idle_timeout_ms: env_parse("ACPLB_IDLE_TIMEOUT_MS")?,
+                polling_interval_ms: env_parse("ACPLB_POLLING_INTERVAL_MS")?,
+                evidence_path: env_value("ACPLB_EVIDENCE_PATH").map(PathBuf::from),
+                state_dir: env_value("ACPLB_STATE_DIR").map(PathBuf::from),
+                session_ttl_ms: env_parse("ACPLB_SESSION_TTL_MS")?,
+                max_sessions: env_parse("ACPLB_MAX_SESSIONS")?,
+                prompt_queue: env_parse("ACPLB_PROMPT_QUEUE")?,
+                default_mode: env_parse("ACPLB_DEFAULT_MODE")?,
+            },
+            codex: CodexSection {
+                command: env_value("CODEX_RUN").or_else(|| env_value("CODEX_CMD")),
+                args: None,
+            },
+            claude: ClaudeSection {
+                command: env_value("ACPLB_CLAUDE_CMD"),
+                args: None,
+            },
+            gemini: GeminiSection {
+                command: env_value("ACPLB_GEMINI_CMD"),
+                args: None,
+            },
+            notify: NotifySection {
+                path: env_value("ACPLB_NOTIFY_PATH").map(PathBuf::from),
+                kind: env_value("ACPLB_NOTIFY_KIND"),
+                inject: env_value("ACPLB_NOTIFY_INJECT"),
+                command: env_value("ACPLB_NOTIFY_CMD"),
+            },
+            permissions: PermissionsSection::default(),
+        };
+        config.validate("environment")?;
+        Ok(config)
+    }
+
+    /// Merge `other` on top of `self`; fields set in `other` win.
+    pub fn overlay(mut self, other: BridgeConfig) -> Self {
+        let runtime = other.runtime;
+        overlay_field(&mut self.runtime.idle_timeout_ms, runtime.idle_timeout_ms);
+        overlay_field(
+            &mut self.runtime.polling_interval_ms,
+            runtime.polling_interval_ms,
+        );
+        overlay_field(&mut self.runtime.evidence_path, runtime.evidence_path);
+        overlay_field(&mut self.runtime.state_dir, runtime.state_dir);
+        overlay_field(&mut self.runtime.session_ttl_ms, runtime.session_ttl_ms);
+        overlay_field(&mut self.runtime.max_sessions, runtime.max_sessions);
+        overlay_field(&mut self.runtime.prompt_queue, runtime.prompt_queue);
+        overlay_field(&mut self.runtime.default_mode, runtime.default_mode);
+
+        overlay_field(&mut self.codex.command, other.codex.command);
+        overlay_field(&mut self.codex.args, other.codex.args);
+        overlay_field(&mut self.claude.command, other.claude.command);
+        overlay_field(&mut self.claude.args, other.claude.args);
+        overlay_field(&mut self.gemini.command, other.gemini.command);
+        overlay_field(&mut self.gemini.args, other.gemini.args);
+
+        let notify = other.notify;
+        overlay_field(&mut self.notify.path, notify.path);
+        overlay_field(&mut self.notify.kind, notify.kind);
+        overlay_field(&mut self.notify.inject, notify.inject);
+        overlay_field(&mut self.notify.command, notify.command);
+
+        let permissions = other.permissions;
+        overlay_field(
+            &mut self.permissions.approval_policy,
+            permissions.approval_policy,
+        );
+        overlay_field(&mut self.permissions.sandbox_mode, permissions.sandbox_mode);
+        overlay_field(
+            &mut self.permissions.network_access,
+            permissions.network_access,
+        );
+        overlay_field(&mut self.permissions.web_search, permissions.web_search);
+        for (mode, settings) in permissions.modes {
+            let current = self.permissions.modes.entry(mode).or_default();
+            *current = std::mem::take(current).overlay(settings);
+        }
+
+        self
+    }
+
+    /// Check values that TOML typing alone cannot catch.
+    pub fn validate(&self, origin: &str) -> Result<(), ConfigError> {
+        let invalid = |message: String| ConfigError::Invalid {
+            origin: origin.to_string(),
+            message,
+        };
+
+        for (name, value) in [
+            ("runtime.idle_timeout_ms", self.runtime.idle_timeout_ms),
+            (
+                "runtime.polling_interval_ms",
+                self.runtime.polling_interval_ms,
+            ),
+            ("runtime.session_ttl_ms", self.runtime.session_ttl_ms),
+        ] {
+            if value == Some(0) {
+                return Err(invalid(format!("{} must be greater than zero", name)));
+            }
+        }
+        if self.runtime.max_sessions == Some(0) {
+            return Err(invalid(
+                "runtime.max_sessions must be greater than zero".into(),
+            ));
+        }
+        for (name, command) in [
+            ("codex.command", &self.codex.command),
+            ("claude.command", &self.claude.command),
+            ("gemini.command", &self.gemini.command),
+        ] {
+            if command
+                .as_deref()
+                .is_some_and(|command| command.trim().is_empty())
+            {
+                return Err(invalid(format!("{} must not be empty", name)));
+            }
+        }
+        check_choice("notify.kind", self.notify.kind.as_deref(), &NOTIFY_KINDS).map_err(invalid)?;
+        check_choice(
+            "notify.inject",
+            self.notify.inject.as_deref(),
+            &NOTIFY_INJECT_MODES,
+        )
+        .map_err(invalid)?;
+
+        let global = self.permissions.global();
+        global.validate("permissions").map_err(invalid)?;
+        for (mode, settings) in &self.permissions.modes {
+            if mode.parse::<AcpPermissionMode>().is_err() {
+                return Err(invalid(format!("unknown permission mode `{}`", mode)));
+            }
+            settings
+                .validate(&format!("permissions.modes.{}", mode))
+                .map_err(invalid)?;
+        }
+        Ok(())
+    }
+
+    /// Effective runtime configuration, falling back to built-in defaults.
+    pub fn runtime_config(&self) -> RuntimeConfig {
+        let runtime = &self.runtime;
+        RuntimeConfig {
+            idle_timeout_ms: runtime.idle_timeout_ms.unwrap_or(DEFAULT_IDLE_TIMEOUT_MS),
+            polling_interval_ms: runtime
+                .polling_interval_ms
+                .unwrap_or(DEFAULT_POLLING_INTERVAL_MS),
+            evidence_path: runtime.evidence_path.clone(),
+            state_dir: runtime.state_dir.clone(),
+            session_ttl_ms: runtime.session_ttl_ms,
+            max_sessions: runtime.max_sessions,
+            prompt_queue: runtime.prompt_queue.unwrap_or_default(),
+            default_mode: runtime.default_mode.unwrap_or(AcpPermissionMode::Default),
+        }
+    }
+
+    /// Codex executable to launch.
+    pub fn codex_command(&self) -> &str {
+        self.codex
+            .command
+            .as_deref()
+            .unwrap_or(DEFAULT_CODEX_COMMAND)
+    }
+
+    /// Claude Code executable to launch.
+    pub fn claude_command(&self) -> &str {
+        self.claude
+            .command
+            .as_deref()
+            .unwrap_or(DEFAULT_CLAUDE_COMMAND)
+    }
+
+    /// Gemini CLI executable to launch.
+    pub fn gemini_command(&self) -> &str {
+        self.gemini
+            .command
+            .as_deref()
+            .unwrap_or(DEFAULT_GEMINI_COMMAND)
+    }
+
+    /// Codex overrides for `mode`: the built-in mapping refined by
+    /// `[permissions]` and then `[permissions.modes.<mode>]`.
+    pub fn codex_overrides(&self, mode: AcpPermissionMode) -> CodexTurnOverrides {
+        let mut overrides = map_acp_to_codex(mode);
+        let global = self.permissions.global();
+        global.apply(&mut overrides);
+        if let Some(settings) = self
+            .permissions
+            .modes
+            .iter()
+            .find(|(key, _)| key.parse::<AcpPermissionMode>().ok() == Some(mode))
+            .map(|(_, settings)| settings)
+        {
+            settings.apply(&mut overrides);
+        }
+        overrides
+    }
+}
+
+impl PermissionsSection {
+    /// Settings applied to every mode.
+    fn global(&self) -> PermissionSettings {
+        PermissionSettings {
+            approval_policy: self.approval_policy.clone(),
+            sandbox_mode: self.sandbox_mode.clone(),
+            network_access: self.network_access,
+            web_search: self.web_search,
+        }
+    }
+}
+
+impl PermissionSettings {
+    fn overlay(mut self, other: PermissionSettings) -> Self {
+        overlay_field(&mut self.approval_policy, other.approval_policy);
+        overlay_field(&mut self.sandbox_mode, other.sandbox_mode);
+        overlay_field(&mut self.network_access, other.network_access);
+        overlay_field(&mut self.web_search, other.web_search);
+        self
+    }
+
+    fn validate(&self, section: &str) -> Result<(), String> {
+        check_choice(
+            &format!("{}.approval_policy", section),
+            self.approval_policy.as_deref(),
+            &APPROVAL_POLICIES,
+        )?;
+        check_choice(
+            &format!("{}.sandbox_mode", section),
+            self.sandbox_mode.as_deref(),
+            &SANDBOX_MODES,
+        )
+    }
+
+    fn apply(&self, overrides: &mut CodexTurnOverrides) {
+        if let Some(policy) = &self.approval_policy {
+            overrides.approval_policy = Cow::Owned(policy.clone());
+        }
+        if let Some(sandbox) = &self.sandbox_mode {
+            overrides.sandbox_mode = Cow::Owned(sandbox.clone());
+        }
+        if let Some(network) = self.network_access {
+            overrides.network_access = network;
+        }
+        if let Some(web_search) = self.web_search {
+            overrides.web_search = web_search;
+        }
+    }
+}
+
+/// Layer read from a project `.acplb.toml`.
+///
+/// Only settings that neither launch anything nor choose where the bridge
+/// writes are kept; `ConfigLoader` additionally rejects `permissions` that
+/// widen the user layers.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProjectConfig {
+    pub idle_timeout_ms: Option<u64>,
+    pub polling_interval_ms: Option<u64>,
+    pub session_ttl_ms: Option<u64>,
+    pub max_sessions: Option<usize>,
+    pub prompt_queue: Option<PromptQueuePolicy>,
+    pub default_mode: Option<AcpPermissionMode>,
+    pub permissions: PermissionsSection,
+}
+
+impl ProjectConfig {
+    /// Restrict a parsed layer; `origin` names it in errors.
+    pub fn from_layer(config: BridgeConfig, origin: &str) -> Result<Self, ConfigError> {
+        let forbidden = [
+            (
+                "runtime.evidence_path",
+                config.runtime.evidence_path.is_some(),
+            ),
+            ("runtime.state_dir", config.runtime.state_dir.is_some()),
+            ("codex.command", config.codex.command.is_some()),
+            ("codex.args", config.codex.args.is_some()),
+            ("claude.command", config.claude.command.is_some()),
+            ("claude.args", config.claude.args.is_some()),
+            ("gemini.command", config.gemini.command.is_some()),
+            ("gemini.args", config.gemini.args.is_some()),
+            ("notify", config.notify != NotifySection::default()),
+        ];
+        if let Some((name, _)) = forbidden.iter().find(|(_, set)| *set) {
+            return Err(ConfigError::Invalid {
+                origin: origin.to_string(),
+                message: format!(
+                    "{} cannot be set in a project {}; use the user config instead",
+                    name, PROJECT_CONFIG_FILE
+                ),
+            });
+        }
+        let runtime = config.runtime;
+        Ok(Self {
+            idle_timeout_ms: runtime.idle_timeout_ms,
+            polling_interval_ms: runtime.polling_interval_ms,
+            session_ttl_ms: runtime.session_ttl_ms,
+            max_sessions: runtime.max_sessions,
+            prompt_queue: runtime.prompt_queue,
+            default_mode: runtime.default_mode,
+            permissions: config.permissions,
+        })
+    }
+
+    /// Read a project file if it exists.
+    pub fn from_optional_file(path: &Path) -> Result<Option<Self>, ConfigError> {
+        BridgeConfig::from_optional_file(path)?
+            .map(|config| Self::from_layer(config, &path.display().to_string()))
+            .transpose()
+    }
+
+    /// The restricted settings as a layer for `BridgeConfig::overlay`.
+    pub fn into_layer(self) -> BridgeConfig {
+        BridgeConfig {
+            runtime: RuntimeSection {
+                idle_timeout_ms: self.idle_timeout_ms,
+                polling_interval_ms: self.polling_interval_ms,
+                session_ttl_ms: self.session_ttl_ms,
+                max_sessions: self.max_sessions,
+                prompt_queue: self.prompt_queue,
+                default_mode: self.default_mode,
+                ..RuntimeSection::default()
+            },
+            permissions: self.permissions,
+            ..BridgeConfig::default()
+        }
+    }
+}
+
+/// Resolves the configuration layers for the bridge binaries.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigLoader {
+    user_config: Option<PathBuf>,
+    overrides: BridgeConfig,
+}
+
+impl ConfigLoader {
+    /// Loader using the default user configuration location.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the user layer from `path` instead of the default location; the
+    /// file must exist.
+    pub fn with_user_config(mut self, path: impl Into<PathBuf>) -> Self {
+        self.user_config = Some(path.into());
+        self
+    }
+
+    /// Highest-precedence layer, typically built from command-line flags.
+    pub fn with_overrides(mut self, overrides: BridgeConfig) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Default user configuration path.
+    pub fn default_user_config_path() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_home.join("acplazybridge").join("config.toml"))
+    }
+
+    /// Resolve every layer; `project_dir` adds its `.acplb.toml` when present.
+    pub fn load(&self, project_dir: Option<&Path>) -> Result<BridgeConfig, ConfigError> {
+        let mut config = BridgeConfig::default();
+
+        match &self.user_config {
+            Some(path) => config = config.overlay(BridgeConfig::from_file(path)?),
+            None => {
+                if let Some(path) = Self::default_user_config_path() {
+                    if let Some(user) = BridgeConfig::from_optional_file(&path)? {
+                        config = config.overlay(user);
+                    }
+                }
+            }
+        }
+
+        if let Some(dir) = project_dir {
+            let path = dir.join(PROJECT_CONFIG_FILE);
+            if let Some(project) = ProjectConfig::from_optional_file(&path)? {
+                let layered = config.clone().overlay(project.into_layer());
+                check_not_widened(&config, &layered).map_err(|message| ConfigError::Invalid {
+                    origin: path.display().to_string(),
+                    message,
+                })?;
+                config = layered;
+            }
+        }
+
+        config = config
+            .overlay(BridgeConfig::from_env()?)
+            .overlay(self.overrides.clone());
+        config.validate("command line")?;
+        Ok(config)
+    }
+}
+
+/// Fail when `layered` grants more than `base` in any mode, or starts new
+/// sessions in a wider mode.
+fn check_not_widened(base: &BridgeConfig, layered: &BridgeConfig) -> Result<(), String> {
+    let default_mode = |config: &BridgeConfig| config.runtime_config().default_mode;
+    let mut pairs: Vec<(String, CodexTurnOverrides, CodexTurnOverrides)> = AcpPermissionMode::ALL
+        .into_iter()
+        .map(|mode| {
+            (
+                format!("mode {}", mode.id()),
+                base.codex_overrides(mode),
+                layered.codex_overrides(mode),
+            )
+        })
+        .collect();
+    pairs.push((
+        "runtime.default_mode".to_string(),
+        base.codex_overrides(default_mode(base)),
+        layered.codex_overrides(default_mode(layered)),
+    ));
+
+    let rank = |choices: &[&str], value: &str| choices.iter().position(|c| *c == value);
+    for (scope, before, after) in pairs {
+        let widened = if rank(&APPROVAL_POLICIES, &after.approval_policy)
+            > rank(&APPROVAL_POLICIES, &before.approval_policy)
+        {
+            Some("approval_policy")
+        } else if rank(&SANDBOX_MODES, &after.sandbox_mode)
+            > rank(&SANDBOX_MODES, &before.sandbox_mode)
+        {
+            Some("sandbox_mode")
+        } else if after.network_access && !before.network_access {
+            Some("network_access")
+        } else if after.web_search && !before.web_search {
+            Some("web_search")
+        } else {
+            None
+        };
+        if let Some(field) = widened {
+            return Err(format!(
+                "{} for {} is wider than the user configuration allows; a project file may only narrow permissions",
+                field, scope
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn overlay_field<T>(target: &mut Option<T>, value: Option<T>) {
+    if value.is_some() {
+        *target = value;
+    }
+}
+
+fn check_choice(name: &str, value: Option<&str>, allowed: &[&str]) -> Result<(), String> {
+    match value {
+        Some(value) if !allowed.contains(&value) => Err(format!(
+            "{} must be one of {}, got `{}`",
+            name,
+            allowed.join(", "),
+            value
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn env_value(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|value| !value.is_empty())
+}
+
+fn env_parse<T: FromStr>(key: &str) -> Result<Option<T>, ConfigError> {
+    env_value(key)
+        .map(|value| {
+            value.parse().map_err(|_| ConfigError::Invalid {
+                origin: "environment".to_string(),
+                message: format!("{} has an invalid value `{}`", key, value),
+            })
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(contents: &str) -> Result<BridgeConfig, ConfigError> {
+        BridgeConfig::from_toml_str(contents, Path::new("test.toml"))
+    }
+
+    #[test]
+    fn test_layers_merge_field_by_field() {
+        // ast-grep-ignore: rust-no-unwrap
+        let user = parse(
+            r#"
+            [runtime]
+            idle_timeout_ms = 5000
+            polling_interval_ms = 250
+
+            [codex]
+            command = "/usr/local/bin/codex"
+
+            [claude]
+            command = "/usr/local/bin/claude"
+
+            [gemini]
+            command = "/usr/local/bin/gemini"
+
+            [permissions.modes.acceptEdits]
+            network_access = true
+            "#,
+        )
+        .unwrap();
+        // ast-grep-ignore: rust-no-unwrap
+        let project = parse(
+            r#"
+            [runtime]
+            idle_timeout_ms = 9000
+
+            [permissions.modes.acceptEdits]
+            approval_policy = "on-request"
+            "#,
+        )
+        .unwrap();
+
+        let merged = BridgeConfig::default().overlay(user).overlay(project);
+        let runtime = merged.runtime_config();
+        assert_eq!(runtime.idle_timeout_ms, 9000);
+        assert_eq!(runtime.polling_interval_ms, 250);
+        assert_eq!(merged.codex_command(), "/usr/local/bin/codex");
+        assert_eq!(merged.claude_command(), "/usr/local/bin/claude");
+        assert_eq!(merged.gemini_command(), "/usr/local/bin/gemini");
+
+        let overrides = merged.codex_overrides(AcpPermissionMode::AcceptEdits);
+        assert_eq!(overrides.approval_policy, "on-request");
+        assert_eq!(overrides.sandbox_mode, "workspace-write");
+        assert!(overrides.network_access);
+    }
+
+    #[test]
+    fn test_defaults_match_builtin_values() {
+        let config = BridgeConfig::default();
+        let runtime = config.runtime_config();
+        assert_eq!(runtime.idle_timeout_ms, DEFAULT_IDLE_TIMEOUT_MS);
+        assert_eq!(runtime.polling_interval_ms, DEFAULT_POLLING_INTERVAL_MS);
+        assert_eq!(runtime.prompt_queue, PromptQueuePolicy::Serialize);
+        assert_eq!(config.codex_command(), "codex");
+        assert_eq!(config.claude_command(), "claude");
+        assert_eq!(config.gemini_command(), "gemini");
+        for mode in AcpPermissionMode::ALL {
+            let expected = map_acp_to_codex(mode);
+            let actual = config.codex_overrides(mode);
+            assert_eq!(actual.approval_policy, expected.approval_policy);
+            assert_eq!(actual.sandbox_mode, expected.sandbox_mode);
+            assert_eq!(actual.network_access, expected.network_access);
+            assert_eq!(actual.web_search, expected.web_search);
+        }
+    }
+
+    #[test]
+    fn test_loader_applies_project_file_and_overrides() {
+        // ast-grep-ignore: rust-no-unwrap
+        let dir = tempfile::tempdir().unwrap();
+        let user_path = dir.path().join("config.toml");
+        // ast-grep-ignore: rust-no-unwrap
+        std::fs::write(
+            &user_path,
+            "[runtime]\nsession_ttl_ms = 5000\nmax_sessions = 4\n\n[codex]\nargs = [\"--user\"]\n",
+        )
+        .unwrap();
+        let project = dir.path().join("project");
+        // ast-grep-ignore: rust-no-unwrap
+        std::fs::create_dir(&project).unwrap();
+        // ast-grep-ignore: rust-no-unwrap
+        std::fs::write(
+            project.join(PROJECT_CONFIG_FILE),
+            "[runtime]\nsession_ttl_ms = 9000\n",
+        )
+        .unwrap();
+
+        let overrides = BridgeConfig {
+            runtime: RuntimeSection {
+                max_sessions: Some(2),
+                ..RuntimeSection::default()
+            },
+            ..BridgeConfig::default()
+        };
+        let loader = ConfigLoader::new()
+            .with_user_config(&user_path)
+            .with_overrides(overrides);
+
+        // ast-grep-ignore: rust-no-unwrap
+        let global = loader.load(None).unwrap();
+        assert_eq!(global.runtime.session_ttl_ms, Some(5000));
+
+        // ast-grep-ignore: rust-no-unwrap
+        let scoped = loader.load(Some(&project)).unwrap();
+        assert_eq!(scoped.runtime.session_ttl_ms, Some(9000));
+        assert_eq!(scoped.runtime.max_sessions, Some(2));
+        assert_eq!(scoped.codex.args, Some(vec!["--user".to_string()]));
+
+        let missing = ConfigLoader::new().with_user_config(dir.path().join("missing.toml"));
+        assert!(matches!(missing.load(None), Err(ConfigError::Read { .. })));
+    }
+
+    #[test]
+    fn test_project_file_cannot_launch_commands_or_widen_permissions() {
+        // ast-grep-ignore: rust-no-unwrap
+        let dir = tempfile::tempdir().unwrap();
+        let user_path = dir.path().join("config.toml");
+        // ast-grep-ignore: rust-no-unwrap
+        std::fs::write(&user_path, "").unwrap();
+        let loader = ConfigLoader::new().with_user_config(&user_path);
+        let load_project = |contents: &str| {
+            // ast-grep-ignore: rust-no-unwrap
+            std::fs::write(dir.path().join(PROJECT_CONFIG_FILE), contents).unwrap();
+            loader.load(Some(dir.path()))
+        };
+
+        for contents in [
+            "[codex]\ncommand = \"./evil\"",
+            "[claude]\nargs = [\"--dangerously-skip-permissions\"]",
+            "[gemini]\ncommand = \"./evil\"",
+            "[notify]\ncommand = \"./evil\"",
+            "[runtime]\nevidence_path = \"/etc/passwd\"",
+            "[permissions]\nsandbox_mode = \"danger-full-access\"",
+            "[permissions.modes.acceptEdits]\nnetwork_access = true",
+            "[permissions.modes.default]\napproval_policy = \"never\"",
+            "[runtime]\ndefault_mode = \"yolo\"",
+        ] {
+            match load_project(contents) {
+                Err(ConfigError::Invalid { origin, .. }) => {
+                    assert!(origin.ends_with(PROJECT_CONFIG_FILE), "{}", origin)
+                }
+                other => panic!("accepted {:?}: {:?}", contents, other),
+            }
+        }
+
+        // Timeouts and narrower permissions are fine.
+        // ast-grep-ignore: rust-no-unwrap
+        let config = load_project(
+            "[runtime]\nidle_timeout_ms = 4000\n\n[permissions.modes.bypassPermissions]\nnetwork_access = false\nsandbox_mode = \"read-only\"",
+        )
+        .unwrap();
+        assert_eq!(config.runtime.idle_timeout_ms, Some(4000));
+        let bypass = config.codex_overrides(AcpPermissionMode::BypassPermissions);
+        assert!(!bypass.network_access);
+        assert_eq!(bypass.sandbox_mode, "read-only");
+    }
+
+    #[test]
+    fn test_invalid_values_are_rejected() {
+        let cases = [
+            "[runtime]\npolling_interval_ms = 0",
+            "[runtime]\nidle_timeout = 10",
+            "[notify]\nkind = \"socket\"",
+            "[permissions]\nsandbox_mode = \"everything\"",
+            "[permissions.modes.turbo]\nnetwork_access = true",
+            "[permissions.modes.plan]\napproval_policy = \"sometimes\"",
+            "[codex]\ncommand = \"\"",
+            "[claude]\ncommand = \" \"",
+            "[gemini]\ncommand = \"\"",
+        ];
+        for contents in cases {
+            assert!(parse(contents).is_err(), "accepted: {}", contents);
+        }
+    }
+}
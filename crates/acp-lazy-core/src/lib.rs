@@ -6,14 +6,33 @@
 //! - Process transport and stdio communication
 //! - Shared runtime orchestration built on the Agent Client Protocol
 //! - Permission mapping for Codex integration
+//! - Layered TOML/environment configuration
 //! - Connection management following Zed's patterns
 
+pub mod config;
 pub mod permissions;
 pub mod protocol;
 pub mod runtime;
 pub mod transport;
 
 pub mod logging {
+    use std::fs::OpenOptions;
+    use std::path::Path;
+    use std::sync::Mutex;
+
+    use tracing_subscriber::fmt::writer::BoxMakeWriter;
+    use tracing_subscriber::prelude::*;
+
+    /// Log line format.
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+    pub enum LogFormat {
+        /// Human-readable lines.
+        #[default]
+        Text,
+        /// One JSON object per line.
+        Json,
+    }
+
     /// Initialize tracing with environment-based filtering.
     ///
     /// Uses RUST_LOG environment variable to control log levels.
@@ -22,16 +41,30 @@ pub mod logging {
     /// Important: We configure the writer to stderr so stdout remains
     /// strictly reserved for JSON-RPC output.
     pub fn init() {
-        use tracing_subscriber::prelude::*;
-        let fmt_layer = tracing_subscriber::fmt::layer()
-            .with_target(false)
-            .with_writer(std::io::stderr);
+        // Writing to stderr cannot fail to open.
+        let _ = init_with(LogFormat::Text, None);
+    }
+
+    /// Initialize tracing like [`init`], writing `format` lines to `log_file`
+    /// (appended) instead of stderr when given.
+    pub fn init_with(format: LogFormat, log_file: Option<&Path>) -> std::io::Result<()> {
+        let writer = match log_file {
+            Some(path) => BoxMakeWriter::new(Mutex::new(
+                OpenOptions::new().create(true).append(true).open(path)?,
+            )),
+            None => BoxMakeWriter::new(std::io::stderr),
+        };
         let filter = tracing_subscriber::EnvFilter::try_from_default_env()
             .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
-        tracing_subscriber::registry()
-            .with(filter)
-            .with(fmt_layer)
-            .init();
+        let fmt_layer = tracing_subscriber::fmt::layer()
+            .with_target(false)
+            .with_writer(writer);
+        let fmt_layer = match format {
+            LogFormat::Text => fmt_layer.with_filter(filter).boxed(),
+            LogFormat::Json => fmt_layer.json().with_filter(filter).boxed(),
+        };
+        tracing_subscriber::registry().with(fmt_layer).init();
+        Ok(())
     }
 }
 
@@ -3,11 +3,13 @@
 //! This module handles the translation of ACP permission modes to Codex CLI
 //! parameters to ensure non-interactive operation suitable for IDE integration.
 
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::collections::HashMap;
 
 /// ACP permission modes that control agent capabilities.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum AcpPermissionMode {
     /// Default mode: read-only access, no network
     Default,
@@ -21,6 +23,50 @@ pub enum AcpPermissionMode {
     Yolo,
 }
 
+impl AcpPermissionMode {
+    /// Every mode, in the order advertised to clients.
+    pub const ALL: [AcpPermissionMode; 5] = [
+        Self::Default,
+        Self::Plan,
+        Self::AcceptEdits,
+        Self::BypassPermissions,
+        Self::Yolo,
+    ];
+
+    /// Canonical mode identifier used in `SessionModeId`.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::Plan => "plan",
+            Self::AcceptEdits => "acceptEdits",
+            Self::BypassPermissions => "bypassPermissions",
+            Self::Yolo => "yolo",
+        }
+    }
+
+    /// Human-readable mode name.
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Default => "Default",
+            Self::Plan => "Plan",
+            Self::AcceptEdits => "Accept Edits",
+            Self::BypassPermissions => "Bypass Permissions",
+            Self::Yolo => "YOLO",
+        }
+    }
+
+    /// Short description of what the mode allows.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Default => "Read-only workspace access; asks before anything else",
+            Self::Plan => "Plan changes without modifying files (read-only)",
+            Self::AcceptEdits => "Edit files in the workspace without network",
+            Self::BypassPermissions => "Edit files in the workspace with network access",
+            Self::Yolo => "Full system access with no sandbox or approvals (dangerous)",
+        }
+    }
+}
+
 impl std::str::FromStr for AcpPermissionMode {
     type Err = ();
 
@@ -49,6 +95,8 @@ pub struct CodexTurnOverrides {
     pub sandbox_mode: Cow<'static, str>,
     /// Whether network access is allowed
     pub network_access: bool,
+    /// Whether Codex may use its built-in web search tool
+    pub web_search: bool,
     /// Additional CLI arguments to pass
     pub extra_args: Vec<String>,
 }
@@ -59,6 +107,7 @@ impl Default for CodexTurnOverrides {
             approval_policy: Cow::Borrowed("never"),
             sandbox_mode: Cow::Borrowed("read-only"),
             network_access: false,
+            web_search: false,
             extra_args: Vec::new(),
         }
     }
@@ -80,6 +129,12 @@ impl CodexTurnOverrides {
             args.push("sandbox_workspace_write.network_access=true".to_string());
         }
 
+        // Leave Codex's own web search setting alone unless the mode enables it.
+        if self.web_search {
+            args.push("-c".to_string());
+            args.push("tools.web_search=true".to_string());
+        }
+
         // Add any extra arguments
         args.extend(self.extra_args.clone());
 
@@ -92,28 +147,42 @@ impl CodexTurnOverrides {
             approval_policy: Cow::Borrowed("never"),
             sandbox_mode: Cow::Borrowed("danger-full-access"),
             network_access: true,
+            web_search: true,
             extra_args: vec!["--dangerously-bypass-approvals-and-sandbox".to_string()],
         }
     }
 }
 
-/// Map ACP permission modes to non-interactive Codex overrides.
+/// Map ACP permission modes to Codex overrides.
 ///
-/// This ensures that the Codex CLI never prompts for user approval,
-/// which would block the IDE integration.
+/// Only the default mode lets Codex ask for approval (`on-request`); those
+/// requests are answered through `session/request_permission`. Every other
+/// mode runs without prompts inside its sandbox.
+///
+/// Web search cannot touch the workspace, so it is also enabled in plan mode
+/// where looking up documentation is part of the job.
 pub fn map_acp_to_codex(mode: AcpPermissionMode) -> CodexTurnOverrides {
     match mode {
-        AcpPermissionMode::Default | AcpPermissionMode::Plan => CodexTurnOverrides::default(),
+        AcpPermissionMode::Default => CodexTurnOverrides {
+            approval_policy: Cow::Borrowed("on-request"),
+            ..CodexTurnOverrides::default()
+        },
+        AcpPermissionMode::Plan => CodexTurnOverrides {
+            web_search: true,
+            ..CodexTurnOverrides::default()
+        },
         AcpPermissionMode::AcceptEdits => CodexTurnOverrides {
             approval_policy: Cow::Borrowed("never"),
             sandbox_mode: Cow::Borrowed("workspace-write"),
             network_access: false,
+            web_search: false,
             extra_args: Vec::new(),
         },
         AcpPermissionMode::BypassPermissions => CodexTurnOverrides {
             approval_policy: Cow::Borrowed("never"),
             sandbox_mode: Cow::Borrowed("workspace-write"),
             network_access: true,
+            web_search: true,
             extra_args: Vec::new(),
         },
         AcpPermissionMode::Yolo => CodexTurnOverrides::danger(),
@@ -130,6 +199,7 @@ pub fn map_acp_to_codex(mode: AcpPermissionMode) -> CodexTurnOverrides {
 /// - `ACPLB_APPROVAL_POLICY`: Override approval policy (never|on-request|on-failure|untrusted)
 /// - `ACPLB_SANDBOX_MODE`: Override sandbox mode (read-only|workspace-write|danger-full-access)
 /// - `ACPLB_NETWORK_ACCESS`: Override network access (true|false)
+/// - `ACPLB_WEB_SEARCH`: Override the web search tool toggle (true|false)
 ///
 /// # Example
 ///
@@ -184,6 +254,9 @@ impl PermissionOverrides {
         if let Some(network) = self.get("network_access") {
             overrides.network_access = network.parse().unwrap_or(false);
         }
+        if let Some(web_search) = self.get("web_search") {
+            overrides.web_search = web_search.parse().unwrap_or(false);
+        }
         overrides
     }
 }
@@ -217,22 +290,37 @@ mod tests {
         assert_eq!("invalid".parse::<AcpPermissionMode>().ok(), None);
     }
 
+    #[test]
+    fn test_mode_ids_round_trip() {
+        for mode in AcpPermissionMode::ALL {
+            assert_eq!(mode.id().parse::<AcpPermissionMode>().ok(), Some(mode));
+        }
+    }
+
     #[test]
     fn test_permission_mapping() {
         let default = map_acp_to_codex(AcpPermissionMode::Default);
-        assert_eq!(default.approval_policy, "never");
+        assert_eq!(default.approval_policy, "on-request");
         assert_eq!(default.sandbox_mode, "read-only");
         assert!(!default.network_access);
+        assert!(!default.web_search);
+
+        let plan = map_acp_to_codex(AcpPermissionMode::Plan);
+        assert_eq!(plan.approval_policy, "never");
+        assert_eq!(plan.sandbox_mode, "read-only");
+        assert!(plan.web_search);
 
         let edit = map_acp_to_codex(AcpPermissionMode::AcceptEdits);
         assert_eq!(edit.approval_policy, "never");
         assert_eq!(edit.sandbox_mode, "workspace-write");
         assert!(!edit.network_access);
+        assert!(!edit.web_search);
 
         let bypass = map_acp_to_codex(AcpPermissionMode::BypassPermissions);
         assert_eq!(bypass.approval_policy, "never");
         assert_eq!(bypass.sandbox_mode, "workspace-write");
         assert!(bypass.network_access);
+        assert!(bypass.web_search);
 
         let yolo = map_acp_to_codex(AcpPermissionMode::Yolo);
         assert_eq!(yolo.sandbox_mode, "danger-full-access");
@@ -246,6 +334,7 @@ mod tests {
             approval_policy: Cow::Borrowed("never"),
             sandbox_mode: Cow::Borrowed("workspace-write"),
             network_access: true,
+            web_search: false,
             extra_args: vec![],
         };
 
@@ -256,6 +345,15 @@ mod tests {
         assert!(args.contains(&"sandbox_workspace_write.network_access=true".to_string()));
     }
 
+    #[test]
+    fn test_web_search_arg_only_when_enabled() {
+        let off = map_acp_to_codex(AcpPermissionMode::AcceptEdits).to_cli_args();
+        assert!(!off.iter().any(|arg| arg.starts_with("tools.web_search")));
+
+        let on = map_acp_to_codex(AcpPermissionMode::Plan).to_cli_args();
+        assert!(on.contains(&"tools.web_search=true".to_string()));
+    }
+
     #[test]
     fn test_env_overrides_no_leak() {
         // Test that environment overrides work without memory leaks
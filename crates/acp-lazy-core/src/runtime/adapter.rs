@@ -4,28 +4,83 @@
 //! streaming, notify integration) to an implementation of `ProviderAdapter`.
 
 use agent_client_protocol::{
-    AgentCapabilities, CancelNotification, Error, PromptRequest, PromptResponse, SessionId,
-    SessionNotification,
+    AgentCapabilities, AuthMethod, AuthMethodId, CancelNotification, ClientCapabilities, Error,
+    ExtRequest, ExtResponse, PromptRequest, PromptResponse, SessionId, SessionNotification,
 };
 use async_trait::async_trait;
 use tokio::sync::mpsc;
+use tracing::{debug, warn};
 
-use super::{server::RuntimeConfig, session::SessionState};
+use super::{client::ClientHandle, server::RuntimeConfig, session::SessionState};
 
 /// Channel used by adapters to emit ACP session notifications.
 pub type SessionNotifier = Option<mpsc::UnboundedSender<SessionNotification>>;
 
+/// Send a session update to the runtime, logging when it cannot be delivered.
+pub fn forward_update(notifier: &SessionNotifier, session_key: &str, update: SessionNotification) {
+    debug!(
+        "Forwarding session update: session={}, update_type={:?}",
+        session_key,
+        std::mem::discriminant(&update.update)
+    );
+    if let Some(tx) = notifier.as_ref() {
+        if let Err(e) = tx.send(update) {
+            warn!("Failed to send update to notifier channel: {}", e);
+        }
+    } else {
+        warn!("No notifier channel available for session {}", session_key);
+    }
+}
+
+/// A model a provider can run a session with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProviderModel {
+    /// Identifier passed back in `session/set_model`.
+    pub id: String,
+    /// Human-readable name shown by the client.
+    pub name: String,
+    pub description: Option<String>,
+}
+
 /// Provider-specific behavior required by the shared runtime.
 #[async_trait(?Send)]
 pub trait ProviderAdapter: Send + Sync {
     /// Capabilities advertised during initialize.
     fn agent_capabilities(&self) -> AgentCapabilities;
 
+    /// Authentication methods advertised during initialize.
+    fn auth_methods(&self) -> Vec<AuthMethod> {
+        Vec::new()
+    }
+
+    /// Run or validate the login for one of `auth_methods`.
+    async fn authenticate(&self, _method_id: &AuthMethodId) -> Result<(), Error> {
+        Err(Error::method_not_found())
+    }
+
+    /// Models selectable through `session/set_model`; the first entry is the default.
+    fn available_models(&self) -> Vec<ProviderModel> {
+        Vec::new()
+    }
+
+    /// Receive the runtime's client handle for agent-to-client requests
+    /// (permission prompts, file system calls, extension notifications).
+    fn attach_client(&self, _client: ClientHandle) {}
+
+    /// Receive the capabilities the client advertised in `initialize`.
+    fn on_initialize(&self, _capabilities: &ClientCapabilities) {}
+
     /// Invoked after `RuntimeServer` records a new session.
     async fn on_session_created(&self, _session: &SessionState) -> Result<(), Error> {
         Ok(())
     }
 
+    /// Invoked after a session is closed or evicted; adapters release its
+    /// processes and notify sources.
+    async fn on_session_closed(&self, _session_id: &SessionId) -> Result<(), Error> {
+        Ok(())
+    }
+
     /// Process a prompt turn for the given session.
     async fn handle_prompt(
         &self,
@@ -48,4 +103,34 @@ pub trait ProviderAdapter: Send + Sync {
     ) -> Result<(), Error> {
         Ok(())
     }
+
+    /// Invoked when a session's model is updated; adapters apply it on the next turn.
+    async fn on_model_changed(
+        &self,
+        _session_id: &SessionId,
+        _session: &SessionState,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Model the provider reports actually running for the session, if known.
+    async fn active_model(&self, _session_id: &SessionId) -> Option<String> {
+        None
+    }
+
+    /// Number of provider processes currently alive, reported by `_acplb/status`.
+    async fn running_processes(&self) -> usize {
+        0
+    }
+
+    /// Extra extension methods handled by `ext_method`, named without the
+    /// leading underscore (e.g. `acplb/codex/foo`).
+    fn ext_methods(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Handle one of the methods listed in `ext_methods`.
+    async fn ext_method(&self, _request: ExtRequest) -> Result<ExtResponse, Error> {
+        Err(Error::method_not_found())
+    }
 }
@@ -0,0 +1,287 @@
+//! Typed handle for agent-to-client requests.
+//!
+//! `AgentSideConnection` is `!Send` and must live on the local task set that
+//! drives the JSON-RPC transport, while provider adapters stream Codex output
+//! on `Send` tasks. `ClientHandle` bridges the two: adapters enqueue typed
+//! requests on a channel and `serve_client_requests` forwards them to the
+//! connection, replying through a oneshot.
+//!
+//! `SessionNotification`s travel on their own channel and are delivered in
+//! order by `forward_session_notifications`, so every byte on stdout is
+//! written by the connection itself. `ClientHandle::session_notification`
+//! instead waits until the connection has taken the update, for replies that
+//! must not overtake it (the `session/load` history replay).
+
+use std::rc::Rc;
+
+use agent_client_protocol::{
+    Client, CreateTerminalRequest, CreateTerminalResponse, Error, ExtNotification,
+    KillTerminalCommandRequest, KillTerminalCommandResponse, ReadTextFileRequest,
+    ReadTextFileResponse, ReleaseTerminalRequest, ReleaseTerminalResponse,
+    RequestPermissionRequest, RequestPermissionResponse, SessionNotification,
+    TerminalOutputRequest, TerminalOutputResponse, WaitForTerminalExitRequest,
+    WaitForTerminalExitResponse, WriteTextFileRequest, WriteTextFileResponse,
+};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, warn};
+
+/// Requests that adapters may issue to the connected ACP client.
+#[derive(Debug)]
+pub enum ClientRequest {
+    /// `session/request_permission`
+    RequestPermission {
+        request: Box<RequestPermissionRequest>,
+        reply: oneshot::Sender<Result<RequestPermissionResponse, Error>>,
+    },
+    /// `fs/read_text_file`
+    ReadTextFile {
+        request: ReadTextFileRequest,
+        reply: oneshot::Sender<Result<ReadTextFileResponse, Error>>,
+    },
+    /// `fs/write_text_file`
+    WriteTextFile {
+        request: WriteTextFileRequest,
+        reply: oneshot::Sender<Result<WriteTextFileResponse, Error>>,
+    },
+    /// `terminal/create`
+    CreateTerminal {
+        request: CreateTerminalRequest,
+        reply: oneshot::Sender<Result<CreateTerminalResponse, Error>>,
+    },
+    /// `terminal/output`
+    TerminalOutput {
+        request: TerminalOutputRequest,
+        reply: oneshot::Sender<Result<TerminalOutputResponse, Error>>,
+    },
+    /// `terminal/wait_for_exit`
+    WaitForTerminalExit {
+        request: WaitForTerminalExitRequest,
+        reply: oneshot::Sender<Result<WaitForTerminalExitResponse, Error>>,
+    },
+    /// `terminal/kill`
+    KillTerminalCommand {
+        request: KillTerminalCommandRequest,
+        reply: oneshot::Sender<Result<KillTerminalCommandResponse, Error>>,
+    },
+    /// `terminal/release`
+    ReleaseTerminal {
+        request: ReleaseTerminalRequest,
+        reply: oneshot::Sender<Result<ReleaseTerminalResponse, Error>>,
+    },
+    /// `session/update`, acknowledged once queued on the connection.
+    SessionNotification {
+        notification: Box<SessionNotification>,
+        reply: oneshot::Sender<Result<(), Error>>,
+    },
+    /// Extension notification (`_<method>`); delivery is fire-and-forget.
+    ExtNotification { notification: ExtNotification },
+}
+
+/// Cloneable, `Send` handle used by adapters to call into the ACP client.
+#[derive(Debug, Clone)]
+pub struct ClientHandle {
+    tx: mpsc::UnboundedSender<ClientRequest>,
+}
+
+impl ClientHandle {
+    /// Create a handle together with the receiver consumed by `serve_client_requests`.
+    pub fn channel() -> (Self, mpsc::UnboundedReceiver<ClientRequest>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Self { tx }, rx)
+    }
+
+    /// Ask the client to approve a tool call.
+    pub async fn request_permission(
+        &self,
+        request: RequestPermissionRequest,
+    ) -> Result<RequestPermissionResponse, Error> {
+        self.call(|reply| ClientRequest::RequestPermission {
+            request: Box::new(request),
+            reply,
+        })
+        .await
+    }
+
+    /// Read a text file through the client, including unsaved editor changes.
+    pub async fn read_text_file(
+        &self,
+        request: ReadTextFileRequest,
+    ) -> Result<ReadTextFileResponse, Error> {
+        self.call(|reply| ClientRequest::ReadTextFile { request, reply })
+            .await
+    }
+
+    /// Write a text file through the client.
+    pub async fn write_text_file(
+        &self,
+        request: WriteTextFileRequest,
+    ) -> Result<WriteTextFileResponse, Error> {
+        self.call(|reply| ClientRequest::WriteTextFile { request, reply })
+            .await
+    }
+
+    /// Start a command in a client terminal.
+    pub async fn create_terminal(
+        &self,
+        request: CreateTerminalRequest,
+    ) -> Result<CreateTerminalResponse, Error> {
+        self.call(|reply| ClientRequest::CreateTerminal { request, reply })
+            .await
+    }
+
+    /// Current output and exit status of a client terminal.
+    pub async fn terminal_output(
+        &self,
+        request: TerminalOutputRequest,
+    ) -> Result<TerminalOutputResponse, Error> {
+        self.call(|reply| ClientRequest::TerminalOutput { request, reply })
+            .await
+    }
+
+    /// Wait for a client terminal's command to exit.
+    pub async fn wait_for_terminal_exit(
+        &self,
+        request: WaitForTerminalExitRequest,
+    ) -> Result<WaitForTerminalExitResponse, Error> {
+        self.call(|reply| ClientRequest::WaitForTerminalExit { request, reply })
+            .await
+    }
+
+    /// Kill a client terminal's command, keeping the terminal open.
+    pub async fn kill_terminal_command(
+        &self,
+        request: KillTerminalCommandRequest,
+    ) -> Result<KillTerminalCommandResponse, Error> {
+        self.call(|reply| ClientRequest::KillTerminalCommand { request, reply })
+            .await
+    }
+
+    /// Release a client terminal; tool calls embedding it keep its output.
+    pub async fn release_terminal(
+        &self,
+        request: ReleaseTerminalRequest,
+    ) -> Result<ReleaseTerminalResponse, Error> {
+        self.call(|reply| ClientRequest::ReleaseTerminal { request, reply })
+            .await
+    }
+
+    /// Send a session update, returning once the connection has queued it
+    /// ahead of any later response.
+    pub async fn session_notification(
+        &self,
+        notification: SessionNotification,
+    ) -> Result<(), Error> {
+        self.call(|reply| ClientRequest::SessionNotification {
+            notification: Box::new(notification),
+            reply,
+        })
+        .await
+    }
+
+    /// Send an extension notification; `method` is given without the leading `_`.
+    pub fn ext_notification(&self, notification: ExtNotification) -> Result<(), Error> {
+        self.tx
+            .send(ClientRequest::ExtNotification { notification })
+            .map_err(|_| connection_closed())
+    }
+
+    async fn call<T>(
+        &self,
+        request: impl FnOnce(oneshot::Sender<Result<T, Error>>) -> ClientRequest,
+    ) -> Result<T, Error> {
+        let (reply, response) = oneshot::channel();
+        self.tx
+            .send(request(reply))
+            .map_err(|_| connection_closed())?;
+        response.await.map_err(|_| connection_closed())?
+    }
+}
+
+/// Forward queued client requests to `client` until every handle is dropped.
+///
+/// Each request runs on its own local task so a slow permission prompt does
+/// not hold up other sessions. Must be polled inside a `tokio::task::LocalSet`.
+pub async fn serve_client_requests<C>(client: Rc<C>, mut rx: mpsc::UnboundedReceiver<ClientRequest>)
+where
+    C: Client + 'static,
+{
+    while let Some(request) = rx.recv().await {
+        let client = client.clone();
+        tokio::task::spawn_local(async move {
+            match request {
+                ClientRequest::RequestPermission { request, reply } => {
+                    let result = client.request_permission(*request).await;
+                    let _ = reply.send(result);
+                }
+                ClientRequest::ReadTextFile { request, reply } => {
+                    let _ = reply.send(client.read_text_file(request).await);
+                }
+                ClientRequest::WriteTextFile { request, reply } => {
+                    let _ = reply.send(client.write_text_file(request).await);
+                }
+                ClientRequest::CreateTerminal { request, reply } => {
+                    let _ = reply.send(client.create_terminal(request).await);
+                }
+                ClientRequest::TerminalOutput { request, reply } => {
+                    let _ = reply.send(client.terminal_output(request).await);
+                }
+                ClientRequest::WaitForTerminalExit { request, reply } => {
+                    let _ = reply.send(client.wait_for_terminal_exit(request).await);
+                }
+                ClientRequest::KillTerminalCommand { request, reply } => {
+                    let _ = reply.send(client.kill_terminal_command(request).await);
+                }
+                ClientRequest::ReleaseTerminal { request, reply } => {
+                    let _ = reply.send(client.release_terminal(request).await);
+                }
+                ClientRequest::SessionNotification {
+                    notification,
+                    reply,
+                } => {
+                    let _ = reply.send(client.session_notification(*notification).await);
+                }
+                ClientRequest::ExtNotification { notification } => {
+                    let method = notification.method.clone();
+                    if let Err(err) = client.ext_notification(notification).await {
+                        debug!(
+                            target: "acp_lazy_core::runtime",
+                            "ext notification {} failed: {:?}",
+                            method,
+                            err
+                        );
+                    }
+                }
+            }
+        });
+    }
+    debug!(target: "acp_lazy_core::runtime", "client request channel closed");
+}
+
+/// Deliver `session/update` notifications to `client` until every notifier
+/// is dropped.
+///
+/// Notifications are sent one at a time so the client sees them in the order
+/// adapters produced them.
+pub async fn forward_session_notifications<C>(
+    client: Rc<C>,
+    mut rx: mpsc::UnboundedReceiver<SessionNotification>,
+) where
+    C: Client + 'static,
+{
+    while let Some(notification) = rx.recv().await {
+        let session_id = notification.session_id.clone();
+        if let Err(err) = client.session_notification(notification).await {
+            warn!(
+                target: "acp_lazy_core::runtime",
+                session_id = %session_id.0,
+                "failed to send session notification: {:?}",
+                err
+            );
+        }
+    }
+    debug!(target: "acp_lazy_core::runtime", "session notification channel closed");
+}
+
+fn connection_closed() -> Error {
+    Error::internal_error().with_data("client connection closed")
+}
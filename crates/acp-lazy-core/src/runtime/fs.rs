@@ -0,0 +1,109 @@
+//! Client-side file system access.
+//!
+//! Editors advertise `fs.readTextFile` and `fs.writeTextFile` in
+//! `initialize`. Reading through the client returns the editor buffer,
+//! including unsaved edits, and writing lets the editor track the change.
+//! `ClientFs` routes calls through the client when the capability is present
+//! and falls back to the local disk otherwise.
+
+use std::path::Path;
+
+use agent_client_protocol::{
+    Error, FileSystemCapability, ReadTextFileRequest, SessionId, WriteTextFileRequest,
+};
+
+use crate::runtime::client::ClientHandle;
+
+/// File system service handed to adapters by `RuntimeServer::client_fs`.
+#[derive(Debug, Clone, Default)]
+pub struct ClientFs {
+    client: Option<ClientHandle>,
+    capabilities: FileSystemCapability,
+}
+
+impl ClientFs {
+    pub fn new(client: Option<ClientHandle>, capabilities: FileSystemCapability) -> Self {
+        Self {
+            client,
+            capabilities,
+        }
+    }
+
+    /// True when reads go through the client (and so see unsaved buffers).
+    pub fn reads_from_client(&self) -> bool {
+        self.client.is_some() && self.capabilities.read_text_file
+    }
+
+    /// True when writes go through the client.
+    pub fn writes_to_client(&self) -> bool {
+        self.client.is_some() && self.capabilities.write_text_file
+    }
+
+    /// Read the whole text file at the absolute `path`.
+    pub async fn read_text_file(
+        &self,
+        session_id: &SessionId,
+        path: &Path,
+    ) -> Result<String, Error> {
+        ensure_absolute(path)?;
+        match &self.client {
+            Some(client) if self.capabilities.read_text_file => {
+                let response = client
+                    .read_text_file(ReadTextFileRequest {
+                        session_id: session_id.clone(),
+                        path: path.to_path_buf(),
+                        line: None,
+                        limit: None,
+                        meta: None,
+                    })
+                    .await?;
+                Ok(response.content)
+            }
+            _ => tokio::fs::read_to_string(path)
+                .await
+                .map_err(|err| io_error(path, err)),
+        }
+    }
+
+    /// Replace the contents of the text file at the absolute `path`.
+    pub async fn write_text_file(
+        &self,
+        session_id: &SessionId,
+        path: &Path,
+        content: String,
+    ) -> Result<(), Error> {
+        ensure_absolute(path)?;
+        match &self.client {
+            Some(client) if self.capabilities.write_text_file => {
+                client
+                    .write_text_file(WriteTextFileRequest {
+                        session_id: session_id.clone(),
+                        path: path.to_path_buf(),
+                        content,
+                        meta: None,
+                    })
+                    .await?;
+                Ok(())
+            }
+            _ => tokio::fs::write(path, content)
+                .await
+                .map_err(|err| io_error(path, err)),
+        }
+    }
+}
+
+fn ensure_absolute(path: &Path) -> Result<(), Error> {
+    if path.is_absolute() {
+        Ok(())
+    } else {
+        Err(Error::invalid_params().with_data(format!("path must be absolute: {}", path.display())))
+    }
+}
+
+fn io_error(path: &Path, err: std::io::Error) -> Error {
+    if err.kind() == std::io::ErrorKind::NotFound {
+        Error::resource_not_found(Some(path.display().to_string()))
+    } else {
+        Error::internal_error().with_data(format!("{}: {}", path.display(), err))
+    }
+}
@@ -4,9 +4,30 @@
 //! management, and provider adapter traits used by ACPLazyBridge agent servers.
 
 pub mod adapter;
+pub mod client;
+pub mod fs;
+pub mod persistence;
 pub mod server;
 pub mod session;
+pub mod stdio;
+pub mod terminal;
+pub mod turn;
 
-pub use adapter::{ProviderAdapter, SessionNotifier};
-pub use server::{RuntimeConfig, RuntimeServer};
-pub use session::{SessionState, SessionStore};
+pub use adapter::{forward_update, ProviderAdapter, ProviderModel, SessionNotifier};
+pub use client::{
+    forward_session_notifications, serve_client_requests, ClientHandle, ClientRequest,
+};
+pub use fs::ClientFs;
+pub use persistence::{JsonSessionPersistence, PersistedSession, SessionPersistence};
+pub use server::{
+    PromptQueuePolicy, RuntimeConfig, RuntimeServer, CONFIG_METHOD, SESSION_CLOSE_METHOD,
+    SESSION_INFO_METHOD, STATUS_METHOD,
+};
+pub use session::{PromptTicket, SessionState, SessionStats, SessionStore};
+pub use stdio::serve_stdio;
+pub use terminal::{ClientTerminal, ClientTerminals};
+pub use turn::{
+    file_uri_to_path, finish_turn, prompt_text, resource_mention, send_prompt, spawn_turn_process,
+    stream_turn_output, Conversation, ConversationStore, TurnAdapter, TurnCli, TurnCommand,
+    TurnContext, TurnEnd, TurnHandle, TurnMapper, TurnRegistry,
+};
@@ -0,0 +1,158 @@
+//! Persisted session storage used by `session/load`.
+//!
+//! `RuntimeServer` keeps live sessions in the in-memory `SessionStore`; a
+//! `SessionPersistence` implementation additionally records each session's
+//! state and the `SessionNotification`s sent to the client so a later process
+//! can rehydrate the session and replay its history. The provider CLI's own
+//! conversation is not part of the snapshot, so a loaded session continues
+//! with a model that has not seen the replayed turns.
+
+use std::path::{Path, PathBuf};
+
+use agent_client_protocol::{SessionId, SessionNotification};
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use super::session::SessionState;
+
+/// Snapshot of a session as stored on disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersistedSession {
+    pub state: SessionState,
+    #[serde(default)]
+    pub history: Vec<SessionNotification>,
+}
+
+/// Backend for persisting sessions across runtime restarts.
+#[async_trait]
+pub trait SessionPersistence: Send + Sync {
+    /// Create or update the stored state, preserving any recorded history.
+    async fn save_state(&self, state: &SessionState) -> Result<()>;
+
+    /// Append notifications to the session's history.
+    async fn append_history(
+        &self,
+        session_id: &SessionId,
+        notifications: &[SessionNotification],
+    ) -> Result<()>;
+
+    /// Load a previously stored session, if any.
+    async fn load(&self, session_id: &SessionId) -> Result<Option<PersistedSession>>;
+}
+
+/// Default persistence backend: one JSON file per session under `dir`.
+pub struct JsonSessionPersistence {
+    dir: PathBuf,
+    // Serializes read-modify-write cycles on session files.
+    write_lock: Mutex<()>,
+}
+
+impl JsonSessionPersistence {
+    /// Store sessions under `dir`, creating it on first write.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    /// Directory holding the session files.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// File for `session_id`. Bytes outside `[A-Za-z0-9_-]` are
+    /// percent-encoded, so distinct ids (`a.b`, `a_b`) never share a file.
+    fn session_path(&self, session_id: &SessionId) -> PathBuf {
+        let mut file_name = String::with_capacity(session_id.0.len());
+        for byte in session_id.0.bytes() {
+            if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' {
+                file_name.push(char::from(byte));
+            } else {
+                file_name.push_str(&format!("%{:02X}", byte));
+            }
+        }
+        self.dir.join(format!("{}.json", file_name))
+    }
+
+    async fn read(&self, session_id: &SessionId) -> Result<Option<PersistedSession>> {
+        let path = self.session_path(session_id);
+        let session: PersistedSession = match tokio::fs::read(&path).await {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .with_context(|| format!("corrupt session file {}", path.display()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("failed to read session file {}", path.display()))
+            }
+        };
+        // Case-insensitive file systems can still fold two ids onto one file.
+        if session.state.session_id != *session_id {
+            bail!(
+                "session file {} belongs to session {}, not {}",
+                path.display(),
+                session.state.session_id.0,
+                session_id.0
+            );
+        }
+        Ok(Some(session))
+    }
+
+    async fn write(&self, session: &PersistedSession) -> Result<()> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .with_context(|| format!("failed to create state dir {}", self.dir.display()))?;
+
+        let path = self.session_path(&session.state.session_id);
+        let tmp_path = path.with_extension("json.tmp");
+        let bytes = serde_json::to_vec(session)?;
+        tokio::fs::write(&tmp_path, bytes)
+            .await
+            .with_context(|| format!("failed to write session file {}", tmp_path.display()))?;
+        tokio::fs::rename(&tmp_path, &path)
+            .await
+            .with_context(|| format!("failed to replace session file {}", path.display()))
+    }
+}
+
+#[async_trait]
+impl SessionPersistence for JsonSessionPersistence {
+    async fn save_state(&self, state: &SessionState) -> Result<()> {
+        let _guard = self.write_lock.lock().await;
+        let history = self
+            .read(&state.session_id)
+            .await?
+            .map(|existing| existing.history)
+            .unwrap_or_default();
+        self.write(&PersistedSession {
+            state: state.clone(),
+            history,
+        })
+        .await
+    }
+
+    async fn append_history(
+        &self,
+        session_id: &SessionId,
+        notifications: &[SessionNotification],
+    ) -> Result<()> {
+        if notifications.is_empty() {
+            return Ok(());
+        }
+
+        let _guard = self.write_lock.lock().await;
+        let mut session = self
+            .read(session_id)
+            .await?
+            .with_context(|| format!("no persisted state for session {}", session_id.0))?;
+        session.history.extend_from_slice(notifications);
+        self.write(&session).await
+    }
+
+    async fn load(&self, session_id: &SessionId) -> Result<Option<PersistedSession>> {
+        self.read(session_id).await
+    }
+}
@@ -5,29 +5,89 @@
 //! `ProviderAdapter` implementation.
 
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use agent_client_protocol::AgentCapabilities;
 use agent_client_protocol::{
-    AuthenticateRequest, AuthenticateResponse, CancelNotification, Error, ExtNotification,
-    ExtRequest, ExtResponse, InitializeRequest, InitializeResponse, LoadSessionRequest,
-    LoadSessionResponse, NewSessionRequest, NewSessionResponse, PromptRequest, PromptResponse,
-    SessionId, SetSessionModeRequest, SetSessionModeResponse, VERSION,
+    Agent, AuthenticateRequest, AuthenticateResponse, CancelNotification, ClientCapabilities,
+    Error, ExtNotification, ExtRequest, ExtResponse, InitializeRequest, InitializeResponse,
+    LoadSessionRequest, LoadSessionResponse, NewSessionRequest, NewSessionResponse, PromptRequest,
+    PromptResponse, RawValue, SessionId, SessionMode, SessionModeId, SessionModeState,
+    SessionNotification, SessionUpdate, SetSessionModeRequest, SetSessionModeResponse, StopReason,
+    VERSION,
 };
 #[cfg(feature = "unstable")]
-use agent_client_protocol::{SetSessionModelRequest, SetSessionModelResponse};
+use agent_client_protocol::{
+    ModelId, ModelInfo, SessionModelState, SetSessionModelRequest, SetSessionModelResponse,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use tokio::fs::OpenOptions;
 use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 use tracing::{debug, info, warn};
 use uuid::Uuid;
 
+use crate::config::BridgeConfig;
 use crate::permissions::AcpPermissionMode;
 use crate::runtime::adapter::{ProviderAdapter, SessionNotifier};
-use crate::runtime::session::{SessionState, SessionStore};
+use crate::runtime::client::ClientHandle;
+use crate::runtime::fs::ClientFs;
+use crate::runtime::persistence::{JsonSessionPersistence, SessionPersistence};
+use crate::runtime::session::{PromptTicket, SessionState, SessionStore};
+use crate::runtime::terminal::ClientTerminals;
+
+/// Extension method closing a session (sent by clients as `_acplb/session/close`).
+pub const SESSION_CLOSE_METHOD: &str = "acplb/session/close";
+/// Extension method reporting live sessions, running processes and uptime.
+pub const STATUS_METHOD: &str = "acplb/status";
+/// Extension method describing one session: cwd, mode, model, turns and usage.
+pub const SESSION_INFO_METHOD: &str = "acplb/session/info";
+/// Extension method returning the effective `RuntimeConfig`.
+pub const CONFIG_METHOD: &str = "acplb/config";
+
+/// Bridge extension methods handled by the runtime itself.
+const RUNTIME_EXT_METHODS: [&str; 4] = [
+    SESSION_CLOSE_METHOD,
+    STATUS_METHOD,
+    SESSION_INFO_METHOD,
+    CONFIG_METHOD,
+];
+
+/// Upper bound on how often the idle-session sweeper runs.
+const MAX_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How `RuntimeServer` handles a prompt sent while another prompt for the
+/// same session is still running.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptQueuePolicy {
+    /// Queue the prompt and run it after the earlier ones finish.
+    #[default]
+    #[serde(alias = "queue")]
+    Serialize,
+    /// Fail the prompt with `invalid_request`.
+    Reject,
+}
+
+impl FromStr for PromptQueuePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "serialize" | "queue" => Ok(Self::Serialize),
+            "reject" => Ok(Self::Reject),
+            other => Err(format!("unknown prompt queue policy: {}", other)),
+        }
+    }
+}
 
 /// Configuration options for the runtime server.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct RuntimeConfig {
     /// Idle timeout in milliseconds.
     pub idle_timeout_ms: u64,
@@ -35,20 +95,32 @@ pub struct RuntimeConfig {
     pub polling_interval_ms: u64,
     /// Optional evidence file path for runtime events.
     pub evidence_path: Option<PathBuf>,
+    /// Directory for persisted sessions; enables `session/load` when set.
+    pub state_dir: Option<PathBuf>,
+    /// Evict sessions with no prompt activity for this many milliseconds.
+    pub session_ttl_ms: Option<u64>,
+    /// Maximum number of live sessions; the least recently active are evicted first.
+    pub max_sessions: Option<usize>,
+    /// Handling of overlapping prompts within one session.
+    pub prompt_queue: PromptQueuePolicy,
+    /// Permission mode assigned to new sessions.
+    pub default_mode: AcpPermissionMode,
 }
 
 impl Default for RuntimeConfig {
+    /// Built-in defaults with `ACPLB_*` environment overrides; binaries use
+    /// `ConfigLoader` to also apply configuration files.
     fn default() -> Self {
-        Self {
-            idle_timeout_ms: std::env::var("ACPLB_IDLE_TIMEOUT_MS")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(1_000_000), // Increased from 1.2s to 1000s for API calls
-            polling_interval_ms: std::env::var("ACPLB_POLLING_INTERVAL_MS")
-                .ok()
-                .and_then(|s| s.parse().ok())
-                .unwrap_or(1000),
-            evidence_path: std::env::var("ACPLB_EVIDENCE_PATH").ok().map(PathBuf::from),
+        match BridgeConfig::from_env() {
+            Ok(config) => config.runtime_config(),
+            Err(err) => {
+                warn!(
+                    target: "acp_lazy_core::runtime",
+                    "ignoring environment configuration: {}",
+                    err
+                );
+                BridgeConfig::default().runtime_config()
+            }
         }
     }
 }
@@ -60,6 +132,11 @@ pub struct RuntimeServer {
     config: RuntimeConfig,
     provider: Arc<dyn ProviderAdapter>,
     notifier: SessionNotifier,
+    persistence: Option<Arc<dyn SessionPersistence>>,
+    client: Option<ClientHandle>,
+    /// Capabilities from the last `initialize`; empty until then.
+    client_capabilities: Arc<RwLock<ClientCapabilities>>,
+    started_at: Instant,
 }
 
 impl RuntimeServer {
@@ -69,14 +146,65 @@ impl RuntimeServer {
         config: RuntimeConfig,
         notifier: SessionNotifier,
     ) -> Self {
+        let persistence = config
+            .state_dir
+            .clone()
+            .map(|dir| Arc::new(JsonSessionPersistence::new(dir)) as Arc<dyn SessionPersistence>);
         Self {
             session_store: SessionStore::default(),
             config,
             provider,
             notifier,
+            persistence,
+            client: None,
+            client_capabilities: Arc::default(),
+            started_at: Instant::now(),
         }
     }
 
+    /// Replace the session persistence backend (`None` disables `session/load`).
+    pub fn with_persistence(mut self, persistence: Option<Arc<dyn SessionPersistence>>) -> Self {
+        self.persistence = persistence;
+        self
+    }
+
+    /// Replace the channel session notifications are sent on.
+    pub fn with_notifier(mut self, notifier: SessionNotifier) -> Self {
+        self.notifier = notifier;
+        self
+    }
+
+    /// Attach the handle used to call into the ACP client; it is shared with
+    /// the provider through `ProviderAdapter::attach_client`.
+    pub fn with_client(mut self, client: ClientHandle) -> Self {
+        self.provider.attach_client(client.clone());
+        self.client = Some(client);
+        self
+    }
+
+    /// Handle for agent-to-client requests, if a client is attached.
+    pub fn client(&self) -> Option<&ClientHandle> {
+        self.client.as_ref()
+    }
+
+    /// Capabilities the client advertised in `initialize`.
+    pub fn client_capabilities(&self) -> ClientCapabilities {
+        self.client_capabilities
+            .read()
+            .map(|caps| caps.clone())
+            .unwrap_or_default()
+    }
+
+    /// File system service honoring the client's `fs` capabilities.
+    pub fn client_fs(&self) -> ClientFs {
+        ClientFs::new(self.client.clone(), self.client_capabilities().fs)
+    }
+
+    /// Terminal service honoring the client's `terminal` capability.
+    pub fn client_terminals(&self) -> ClientTerminals {
+        ClientTerminals::new(self.client.clone(), self.client_capabilities().terminal)
+    }
+
     /// Convenience constructor using default configuration values.
     pub fn with_defaults(provider: Arc<dyn ProviderAdapter>, notifier: SessionNotifier) -> Self {
         Self::new(provider, RuntimeConfig::default(), notifier)
@@ -92,13 +220,18 @@ impl RuntimeServer {
         self.session_store.get(session_id).await
     }
 
-    pub async fn initialize(&self, _req: InitializeRequest) -> Result<InitializeResponse, Error> {
+    pub async fn initialize(&self, req: InitializeRequest) -> Result<InitializeResponse, Error> {
         info!(target: "acp_lazy_core::runtime", "initialize request received");
 
+        self.provider.on_initialize(&req.client_capabilities);
+        if let Ok(mut caps) = self.client_capabilities.write() {
+            *caps = req.client_capabilities.clone();
+        }
+
         let response = InitializeResponse {
             protocol_version: VERSION,
             agent_capabilities: self.merge_capabilities(),
-            auth_methods: Vec::new(),
+            auth_methods: self.provider.auth_methods(),
             meta: None,
         };
 
@@ -107,6 +240,7 @@ impl RuntimeServer {
             None,
             serde_json::json!({
                 "protocolVersion": response.protocol_version,
+                "clientCapabilities": req.client_capabilities,
             }),
         )
         .await;
@@ -116,9 +250,34 @@ impl RuntimeServer {
 
     pub async fn authenticate(
         &self,
-        _req: AuthenticateRequest,
+        req: AuthenticateRequest,
     ) -> Result<AuthenticateResponse, Error> {
-        Err(Error::method_not_found())
+        let methods = self.provider.auth_methods();
+        if methods.is_empty() {
+            return Err(Error::method_not_found());
+        }
+        if !methods.iter().any(|method| method.id == req.method_id) {
+            return Err(Error::invalid_params().with_data("unknown auth method"));
+        }
+
+        info!(
+            target: "acp_lazy_core::runtime",
+            method = %req.method_id.0,
+            "authenticate request received"
+        );
+        let result = self.provider.authenticate(&req.method_id).await;
+
+        self.record_event(
+            "authenticate",
+            None,
+            serde_json::json!({
+                "method": req.method_id.0.as_ref(),
+                "ok": result.is_ok(),
+            }),
+        )
+        .await;
+
+        result.map(|()| AuthenticateResponse { meta: None })
     }
 
     pub async fn new_session(&self, req: NewSessionRequest) -> Result<NewSessionResponse, Error> {
@@ -128,17 +287,22 @@ impl RuntimeServer {
         let state = SessionState::new(
             session_id.clone(),
             req.cwd.clone(),
-            AcpPermissionMode::Default,
-        );
+            self.config.default_mode,
+        )
+        .with_mcp_servers(req.mcp_servers.clone())
+        .with_client_capabilities(self.client_capabilities());
         info!(
             target: "acp_lazy_core::runtime",
             session_id = %state.session_id.0,
             cwd = %state.working_dir.display(),
+            mcp_servers = state.mcp_servers.len(),
             "creating session"
         );
 
         self.session_store.insert(state.clone()).await;
         self.provider.on_session_created(&state).await?;
+        self.persist_state(&state).await;
+        self.sweep_sessions().await;
 
         self.record_event(
             "session_created",
@@ -149,29 +313,89 @@ impl RuntimeServer {
         )
         .await;
 
+        let modes = Some(session_mode_state(state.permission_mode));
+
         #[cfg(feature = "unstable")]
         let response = NewSessionResponse {
             session_id,
-            modes: None,
-            models: None,
+            modes,
+            models: self.session_model_state(&state),
             meta: None,
         };
 
         #[cfg(not(feature = "unstable"))]
         let response = NewSessionResponse {
             session_id,
-            modes: None,
+            modes,
             meta: None,
         };
 
         Ok(response)
     }
 
+    /// Rehydrate a persisted session and replay its history to the client.
+    ///
+    /// Only the transcript is restored: the provider starts a fresh CLI
+    /// conversation that has not seen the replayed turns. The response says
+    /// so with `contextRestored: false` in its `_meta`.
     pub async fn load_session(
         &self,
-        _req: LoadSessionRequest,
+        req: LoadSessionRequest,
     ) -> Result<LoadSessionResponse, Error> {
-        Err(Error::method_not_found())
+        let Some(persistence) = self.persistence.as_ref() else {
+            return Err(Error::method_not_found());
+        };
+        ensure_absolute(req.cwd.as_path())?;
+
+        let persisted = persistence
+            .load(&req.session_id)
+            .await
+            .map_err(|err| Error::internal_error().with_data(err.to_string()))?
+            .ok_or_else(|| Error::invalid_params().with_data("unknown session id"))?;
+
+        let mut state = persisted.state;
+        state.working_dir = req.cwd.clone();
+        state.mcp_servers = req.mcp_servers.clone();
+        state.client_capabilities = self.client_capabilities();
+        info!(
+            target: "acp_lazy_core::runtime",
+            session_id = %state.session_id.0,
+            cwd = %state.working_dir.display(),
+            history = persisted.history.len(),
+            "loading session"
+        );
+
+        self.session_store.insert(state.clone()).await;
+        self.provider.on_session_created(&state).await?;
+        self.persist_state(&state).await;
+        self.sweep_sessions().await;
+
+        // Replay the conversation before responding, as required by session/load.
+        self.replay_history(&persisted.history).await;
+
+        self.record_event(
+            "session_loaded",
+            Some(&state.session_id),
+            serde_json::json!({
+                "cwd": state.working_dir.display().to_string(),
+                "replayed": persisted.history.len(),
+            }),
+        )
+        .await;
+
+        let mut response = LoadSessionResponse {
+            modes: Some(session_mode_state(state.permission_mode)),
+            ..Default::default()
+        };
+        response.meta = Some(serde_json::json!({ "contextRestored": false }));
+
+        #[cfg(feature = "unstable")]
+        let response = LoadSessionResponse {
+            models: self.session_model_state(&state),
+            ..response
+        };
+
+        Ok(response)
     }
 
     pub async fn set_session_mode(
@@ -200,6 +424,23 @@ impl RuntimeServer {
             self.provider
                 .on_permission_mode_changed(&req.session_id, &updated)
                 .await?;
+            self.persist_state(&updated).await;
+
+            if let Some(tx) = self.notifier.as_ref() {
+                let notification = SessionNotification {
+                    session_id: req.session_id.clone(),
+                    update: SessionUpdate::CurrentModeUpdate {
+                        current_mode_id: SessionModeId(Arc::from(updated.permission_mode.id())),
+                    },
+                    meta: None,
+                };
+                if tx.send(notification).is_err() {
+                    warn!(
+                        target: "acp_lazy_core::runtime",
+                        "notifier closed before current mode update was sent"
+                    );
+                }
+            }
 
             self.record_event(
                 "session_mode_changed",
@@ -215,12 +456,73 @@ impl RuntimeServer {
     }
 
     pub async fn prompt(&self, req: PromptRequest) -> Result<PromptResponse, Error> {
+        let session_id = req.session_id.clone();
+        let ticket = self
+            .session_store
+            .begin_prompt(&session_id)
+            .await
+            .ok_or_else(|| Error::invalid_params().with_data("unknown session id"))?;
+
+        let result = self.run_queued_prompt(&ticket, req).await;
+        self.session_store.end_prompt(&session_id).await;
+        result
+    }
+
+    /// Apply the prompt queue policy, then run the prompt once it reaches the
+    /// front of the session's queue.
+    async fn run_queued_prompt(
+        &self,
+        ticket: &PromptTicket,
+        req: PromptRequest,
+    ) -> Result<PromptResponse, Error> {
+        let session_id = &ticket.state.session_id;
+        if ticket.ahead > 0 {
+            if self.config.prompt_queue == PromptQueuePolicy::Reject {
+                warn!(
+                    target: "acp_lazy_core::runtime",
+                    session_id = %session_id.0,
+                    "rejecting overlapping prompt"
+                );
+                self.record_event("prompt_rejected", Some(session_id), serde_json::json!({}))
+                    .await;
+                return Err(Error::invalid_request()
+                    .with_data("a prompt is already running for this session"));
+            }
+            info!(
+                target: "acp_lazy_core::runtime",
+                session_id = %session_id.0,
+                ahead = ticket.ahead,
+                "prompt queued"
+            );
+        }
+
+        let _turn = ticket.wait_turn().await;
+        if self.session_store.is_cancelled(session_id, ticket).await {
+            debug!(
+                target: "acp_lazy_core::runtime",
+                session_id = %session_id.0,
+                "dropping prompt cancelled while queued"
+            );
+            return Ok(PromptResponse {
+                stop_reason: StopReason::Cancelled,
+                meta: None,
+            });
+        }
+
+        // Mode or model may have changed while the prompt was queued.
         let session = self
             .session_store
-            .get(&req.session_id)
+            .get(session_id)
             .await
             .ok_or_else(|| Error::invalid_params().with_data("unknown session id"))?;
+        self.run_prompt(session, req).await
+    }
 
+    async fn run_prompt(
+        &self,
+        session: SessionState,
+        req: PromptRequest,
+    ) -> Result<PromptResponse, Error> {
         info!(
             target: "acp_lazy_core::runtime",
             session_id = %session.session_id.0,
@@ -237,11 +539,17 @@ impl RuntimeServer {
         )
         .await;
 
-        match self
+        let (notifier, recorder) = self.recording_notifier(&session.session_id, &req);
+        let result = self
             .provider
-            .handle_prompt(session.clone(), req, self.notifier.clone(), &self.config)
-            .await
-        {
+            .handle_prompt(session.clone(), req, notifier, &self.config)
+            .await;
+        if let Some(recorder) = recorder {
+            self.persist_history(&session.session_id, recorder).await;
+        }
+        self.sync_active_model(&session).await;
+
+        match result {
             Ok(response) => {
                 info!(
                     target: "acp_lazy_core::runtime",
@@ -250,11 +558,21 @@ impl RuntimeServer {
                     "prompt completed"
                 );
 
+                // Adapters report token usage under `meta.usage`; keep it for cost tracking.
+                let usage = response
+                    .meta
+                    .as_ref()
+                    .and_then(|meta| meta.get("usage"))
+                    .cloned();
+                self.session_store
+                    .record_turn(&session.session_id, usage.clone())
+                    .await;
                 self.record_event(
                     "prompt_completed",
                     Some(&session.session_id),
                     serde_json::json!({
                         "stopReason": response.stop_reason,
+                        "usage": usage,
                     }),
                 )
                 .await;
@@ -294,6 +612,9 @@ impl RuntimeServer {
             session_id = %notification.session_id.0,
             "cancellation requested"
         );
+        self.session_store
+            .cancel_queued(&notification.session_id)
+            .await;
 
         self.record_event(
             "prompt_cancelled",
@@ -308,27 +629,418 @@ impl RuntimeServer {
     #[cfg(feature = "unstable")]
     pub async fn set_session_model(
         &self,
-        _req: SetSessionModelRequest,
+        req: SetSessionModelRequest,
     ) -> Result<SetSessionModelResponse, Error> {
-        Err(Error::method_not_found())
+        let models = self.provider.available_models();
+        if models.is_empty() {
+            return Err(Error::method_not_found());
+        }
+        if !models
+            .iter()
+            .any(|model| model.id.as_str() == req.model_id.0.as_ref())
+        {
+            return Err(Error::invalid_params().with_data("unsupported model"));
+        }
+
+        let updated = self
+            .session_store
+            .update_model(&req.session_id, Some(req.model_id.0.to_string()))
+            .await
+            .ok_or_else(|| Error::invalid_params().with_data("unknown session id"))?;
+
+        info!(
+            target: "acp_lazy_core::runtime",
+            session_id = %req.session_id.0,
+            model = %req.model_id.0,
+            "session model updated"
+        );
+
+        self.provider
+            .on_model_changed(&req.session_id, &updated)
+            .await?;
+        self.persist_state(&updated).await;
+
+        self.record_event(
+            "session_model_changed",
+            Some(&req.session_id),
+            serde_json::json!({
+                "model": req.model_id.0.as_ref(),
+            }),
+        )
+        .await;
+
+        Ok(SetSessionModelResponse { meta: None })
     }
 
-    pub async fn ext_method(&self, _req: ExtRequest) -> Result<ExtResponse, Error> {
-        Err(Error::method_not_found())
+    pub async fn ext_method(&self, req: ExtRequest) -> Result<ExtResponse, Error> {
+        match req.method.as_ref() {
+            SESSION_CLOSE_METHOD => {
+                let params: SessionParams = parse_ext_params(&req)?;
+                self.close_session(&params.session_id).await?;
+                ext_response(serde_json::json!({}))
+            }
+            STATUS_METHOD => ext_response(self.status().await),
+            SESSION_INFO_METHOD => {
+                let params: SessionParams = parse_ext_params(&req)?;
+                ext_response(self.session_info(&params.session_id).await?)
+            }
+            CONFIG_METHOD => ext_response(
+                serde_json::to_value(&self.config)
+                    .map_err(|err| Error::internal_error().with_data(err.to_string()))?,
+            ),
+            method
+                if self
+                    .provider
+                    .ext_methods()
+                    .iter()
+                    .any(|name| name == method) =>
+            {
+                self.provider.ext_method(req).await
+            }
+            _ => Err(Error::method_not_found()),
+        }
     }
 
     pub async fn ext_notification(&self, _notification: ExtNotification) -> Result<(), Error> {
         Ok(())
     }
 
+    /// Bridge-wide status reported by `_acplb/status`.
+    async fn status(&self) -> serde_json::Value {
+        let mut sessions: Vec<String> = self
+            .session_store
+            .session_ids()
+            .await
+            .into_iter()
+            .map(|id| id.0.to_string())
+            .collect();
+        sessions.sort();
+
+        serde_json::json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "uptimeMs": self.started_at.elapsed().as_millis() as u64,
+            "sessionCount": sessions.len(),
+            "sessions": sessions,
+            "runningProcesses": self.provider.running_processes().await,
+        })
+    }
+
+    /// Per-session details reported by `_acplb/session/info`.
+    async fn session_info(&self, session_id: &SessionId) -> Result<serde_json::Value, Error> {
+        let (state, stats) = self
+            .session_store
+            .stats(session_id)
+            .await
+            .ok_or_else(|| Error::invalid_params().with_data("unknown session id"))?;
+        let model = self.provider.active_model(session_id).await.or(state.model);
+
+        Ok(serde_json::json!({
+            "sessionId": state.session_id.0,
+            "cwd": state.working_dir.display().to_string(),
+            "mode": state.permission_mode.id(),
+            "model": model,
+            "mcpServers": state.mcp_servers.len(),
+            "turns": stats.turns,
+            "usage": stats.last_usage,
+            "activePrompts": stats.active_prompts,
+            "idleMs": stats.idle_for.as_millis() as u64,
+        }))
+    }
+
+    /// Stop tracking a session and release its provider resources.
+    ///
+    /// Persisted state is kept, so a closed session can still be restored with
+    /// `session/load`.
+    pub async fn close_session(&self, session_id: &SessionId) -> Result<(), Error> {
+        if !self.evict_session(session_id, "closed").await {
+            return Err(Error::invalid_params().with_data("unknown session id"));
+        }
+        Ok(())
+    }
+
+    /// Evict idle sessions past `session_ttl_ms` and enforce `max_sessions`,
+    /// returning the evicted ids.
+    pub async fn sweep_sessions(&self) -> Vec<SessionId> {
+        let ttl = self.config.session_ttl_ms.map(Duration::from_millis);
+        let candidates = self
+            .session_store
+            .eviction_candidates(ttl, self.config.max_sessions)
+            .await;
+
+        let mut evicted = Vec::with_capacity(candidates.len());
+        for session_id in candidates {
+            if self.evict_session(&session_id, "evicted").await {
+                evicted.push(session_id);
+            }
+        }
+        evicted
+    }
+
+    /// Periodically run `sweep_sessions` until the runtime is dropped.
+    ///
+    /// Returns immediately when neither a session TTL nor a session cap is
+    /// configured. Provider hooks are not `Send`, so spawn this with
+    /// `tokio::task::spawn_local`.
+    pub async fn run_session_sweeper(self) {
+        if self.config.session_ttl_ms.is_none() && self.config.max_sessions.is_none() {
+            return;
+        }
+        let period = self
+            .config
+            .session_ttl_ms
+            .map(|ttl| {
+                Duration::from_millis(ttl / 4).clamp(Duration::from_secs(1), MAX_SWEEP_INTERVAL)
+            })
+            .unwrap_or(MAX_SWEEP_INTERVAL);
+
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            self.sweep_sessions().await;
+        }
+    }
+
+    async fn evict_session(&self, session_id: &SessionId, reason: &str) -> bool {
+        if self.session_store.remove(session_id).await.is_none() {
+            return false;
+        }
+
+        info!(
+            target: "acp_lazy_core::runtime",
+            session_id = %session_id.0,
+            reason = reason,
+            "session closed"
+        );
+        if let Err(err) = self.provider.on_session_closed(session_id).await {
+            warn!(
+                target: "acp_lazy_core::runtime",
+                session_id = %session_id.0,
+                "provider failed to release session: {}",
+                err.message
+            );
+        }
+
+        self.record_event(
+            "session_closed",
+            Some(session_id),
+            serde_json::json!({
+                "reason": reason,
+            }),
+        )
+        .await;
+        true
+    }
+
+    /// Record the model the provider reports running, so later `models`
+    /// responses reflect what is actually in use.
+    async fn sync_active_model(&self, session: &SessionState) {
+        let Some(active) = self.provider.active_model(&session.session_id).await else {
+            return;
+        };
+        if session.model.as_deref() == Some(active.as_str()) {
+            return;
+        }
+
+        debug!(
+            target: "acp_lazy_core::runtime",
+            session_id = %session.session_id.0,
+            model = %active,
+            "provider reported active model"
+        );
+        if let Some(updated) = self
+            .session_store
+            .update_model(&session.session_id, Some(active))
+            .await
+        {
+            self.persist_state(&updated).await;
+        }
+    }
+
+    /// Build the model state advertised for a session, if the provider lists models.
+    #[cfg(feature = "unstable")]
+    fn session_model_state(&self, state: &SessionState) -> Option<SessionModelState> {
+        let models = self.provider.available_models();
+        let default = models.first()?.id.clone();
+        let current = state.model.clone().unwrap_or(default);
+
+        let mut available_models: Vec<ModelInfo> = models
+            .into_iter()
+            .map(|model| ModelInfo {
+                model_id: ModelId(Arc::from(model.id)),
+                name: model.name,
+                description: model.description,
+                meta: None,
+            })
+            .collect();
+        // A provider-reported model may be outside the advertised list.
+        if !available_models
+            .iter()
+            .any(|model| model.model_id.0.as_ref() == current)
+        {
+            available_models.push(ModelInfo {
+                model_id: ModelId(Arc::from(current.as_str())),
+                name: current.clone(),
+                description: None,
+                meta: None,
+            });
+        }
+
+        Some(SessionModelState {
+            current_model_id: ModelId(Arc::from(current)),
+            available_models,
+            meta: None,
+        })
+    }
+
+    /// Send persisted `history` to the client.
+    ///
+    /// With a client attached each update is handed to the connection before
+    /// the next, so the `session/load` response cannot overtake the replay;
+    /// the notifier channel gives no such guarantee and is only the fallback.
+    async fn replay_history(&self, history: &[SessionNotification]) {
+        if let Some(client) = self.client.as_ref() {
+            for notification in history {
+                if let Err(err) = client.session_notification(notification.clone()).await {
+                    warn!(
+                        target: "acp_lazy_core::runtime",
+                        "failed to replay session history: {:?}",
+                        err
+                    );
+                    break;
+                }
+            }
+        } else if let Some(tx) = self.notifier.as_ref() {
+            for notification in history {
+                if tx.send(notification.clone()).is_err() {
+                    warn!(
+                        target: "acp_lazy_core::runtime",
+                        "notifier closed while replaying session history"
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Wrap the notifier so notifications sent during a prompt are also
+    /// recorded for persistence. The user's prompt is recorded up front so
+    /// replay shows both sides of the conversation.
+    fn recording_notifier(
+        &self,
+        session_id: &SessionId,
+        req: &PromptRequest,
+    ) -> (
+        SessionNotifier,
+        Option<JoinHandle<Vec<SessionNotification>>>,
+    ) {
+        if self.persistence.is_none() {
+            return (self.notifier.clone(), None);
+        }
+
+        let mut history: Vec<SessionNotification> = req
+            .prompt
+            .iter()
+            .map(|block| SessionNotification {
+                session_id: session_id.clone(),
+                update: SessionUpdate::UserMessageChunk {
+                    content: block.clone(),
+                },
+                meta: None,
+            })
+            .collect();
+
+        let (tx, mut rx) = mpsc::unbounded_channel::<SessionNotification>();
+        let downstream = self.notifier.clone();
+        let recorder = tokio::spawn(async move {
+            while let Some(notification) = rx.recv().await {
+                if let Some(downstream) = downstream.as_ref() {
+                    let _ = downstream.send(notification.clone());
+                }
+                history.push(notification);
+            }
+            history
+        });
+
+        (Some(tx), Some(recorder))
+    }
+
+    async fn persist_history(
+        &self,
+        session_id: &SessionId,
+        recorder: JoinHandle<Vec<SessionNotification>>,
+    ) {
+        let Some(persistence) = self.persistence.as_ref() else {
+            return;
+        };
+        // The recorder finishes once the adapter drops its notifier clones.
+        let history = match recorder.await {
+            Ok(history) => history,
+            Err(err) => {
+                warn!(
+                    target: "acp_lazy_core::runtime",
+                    "session history recorder failed: {}",
+                    err
+                );
+                return;
+            }
+        };
+        if let Err(err) = persistence.append_history(session_id, &history).await {
+            warn!(
+                target: "acp_lazy_core::runtime",
+                session_id = %session_id.0,
+                "failed to persist session history: {}",
+                err
+            );
+        }
+    }
+
+    async fn persist_state(&self, state: &SessionState) {
+        if let Some(persistence) = self.persistence.as_ref() {
+            if let Err(err) = persistence.save_state(state).await {
+                warn!(
+                    target: "acp_lazy_core::runtime",
+                    session_id = %state.session_id.0,
+                    "failed to persist session state: {}",
+                    err
+                );
+            }
+        }
+    }
+
     fn merge_capabilities(&self) -> AgentCapabilities {
         let provider_caps = self.provider.agent_capabilities();
         AgentCapabilities {
-            load_session: provider_caps.load_session,
+            load_session: provider_caps.load_session || self.persistence.is_some(),
             prompt_capabilities: provider_caps.prompt_capabilities,
             mcp_capabilities: provider_caps.mcp_capabilities,
-            meta: provider_caps.meta,
+            meta: Some(self.capabilities_meta(provider_caps.meta)),
+        }
+    }
+
+    /// Advertise the bridge extension methods under `_meta.acplb.extMethods`,
+    /// using the underscore-prefixed names clients send.
+    fn capabilities_meta(&self, provider_meta: Option<serde_json::Value>) -> serde_json::Value {
+        let methods: Vec<String> = RUNTIME_EXT_METHODS
+            .iter()
+            .map(|method| method.to_string())
+            .chain(self.provider.ext_methods())
+            .map(|method| format!("_{}", method))
+            .collect();
+
+        let mut meta = provider_meta.unwrap_or_else(|| serde_json::json!({}));
+        if let Some(object) = meta.as_object_mut() {
+            object.insert(
+                "acplb".to_string(),
+                serde_json::json!({ "extMethods": methods }),
+            );
+        } else {
+            warn!(
+                target: "acp_lazy_core::runtime",
+                "provider capability meta is not an object; extension methods not advertised"
+            );
         }
+        meta
     }
 
     async fn record_event(
@@ -374,6 +1086,93 @@ impl RuntimeServer {
     }
 }
 
+/// Serve ACP directly from the runtime, so adapters built on the shared
+/// runtime need no `Agent` impl of their own.
+#[async_trait(?Send)]
+impl Agent for RuntimeServer {
+    async fn initialize(&self, args: InitializeRequest) -> Result<InitializeResponse, Error> {
+        RuntimeServer::initialize(self, args).await
+    }
+
+    async fn authenticate(&self, args: AuthenticateRequest) -> Result<AuthenticateResponse, Error> {
+        RuntimeServer::authenticate(self, args).await
+    }
+
+    async fn new_session(&self, args: NewSessionRequest) -> Result<NewSessionResponse, Error> {
+        RuntimeServer::new_session(self, args).await
+    }
+
+    async fn load_session(&self, args: LoadSessionRequest) -> Result<LoadSessionResponse, Error> {
+        RuntimeServer::load_session(self, args).await
+    }
+
+    async fn prompt(&self, args: PromptRequest) -> Result<PromptResponse, Error> {
+        RuntimeServer::prompt(self, args).await
+    }
+
+    async fn cancel(&self, notification: CancelNotification) -> Result<(), Error> {
+        RuntimeServer::cancel(self, notification).await
+    }
+
+    async fn set_session_mode(
+        &self,
+        args: SetSessionModeRequest,
+    ) -> Result<SetSessionModeResponse, Error> {
+        RuntimeServer::set_session_mode(self, args).await
+    }
+
+    #[cfg(feature = "unstable")]
+    async fn set_session_model(
+        &self,
+        args: SetSessionModelRequest,
+    ) -> Result<SetSessionModelResponse, Error> {
+        RuntimeServer::set_session_model(self, args).await
+    }
+
+    async fn ext_method(&self, args: ExtRequest) -> Result<ExtResponse, Error> {
+        RuntimeServer::ext_method(self, args).await
+    }
+
+    async fn ext_notification(&self, notification: ExtNotification) -> Result<(), Error> {
+        RuntimeServer::ext_notification(self, notification).await
+    }
+}
+
+/// Parameters of the session-scoped `_acplb/session/*` extension methods.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SessionParams {
+    session_id: SessionId,
+}
+
+fn parse_ext_params<T: for<'de> Deserialize<'de>>(req: &ExtRequest) -> Result<T, Error> {
+    serde_json::from_str(req.params.get())
+        .map_err(|err| Error::invalid_params().with_data(err.to_string()))
+}
+
+fn ext_response(value: serde_json::Value) -> Result<ExtResponse, Error> {
+    RawValue::from_string(value.to_string())
+        .map(Arc::from)
+        .map_err(|err| Error::internal_error().with_data(err.to_string()))
+}
+
+/// Build the mode state advertised for a session, listing every permission mode.
+fn session_mode_state(current: AcpPermissionMode) -> SessionModeState {
+    SessionModeState {
+        current_mode_id: SessionModeId(Arc::from(current.id())),
+        available_modes: AcpPermissionMode::ALL
+            .iter()
+            .map(|mode| SessionMode {
+                id: SessionModeId(Arc::from(mode.id())),
+                name: mode.display_name().to_string(),
+                description: Some(mode.description().to_string()),
+                meta: None,
+            })
+            .collect(),
+        meta: None,
+    }
+}
+
 /// Validate that a working directory is absolute.
 fn ensure_absolute(path: &Path) -> Result<(), Error> {
     if path.is_absolute() {
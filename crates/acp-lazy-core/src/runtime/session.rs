@@ -1,22 +1,40 @@
-//! Session state definitions for the shared runtime.
+//! Session state and bookkeeping for the shared runtime.
 //!
-//! These structures will track permission modes, working directories, notify
-//! sources, and child processes once the runtime is implemented.
+//! `SessionState` is the per-session metadata handed to adapters (cwd,
+//! permission mode, MCP servers, model, client capabilities). `SessionStore`
+//! tracks the live sessions around it: prompts are admitted with
+//! `begin_prompt` and run one at a time through `PromptTicket::wait_turn`,
+//! `cancel_queued` bumps a cancel epoch so prompts queued before a cancel are
+//! dropped, and `eviction_candidates` picks idle sessions to close once they
+//! outlive the TTL or exceed the session cap.
 
-use agent_client_protocol::SessionId;
+use agent_client_protocol::{ClientCapabilities, McpServer, SessionId};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, OwnedMutexGuard, RwLock};
 
 use crate::permissions::AcpPermissionMode;
 
 /// Session metadata tracked by the runtime.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SessionState {
     pub session_id: SessionId,
     pub working_dir: PathBuf,
     pub permission_mode: AcpPermissionMode,
+    /// MCP servers supplied by the client in `session/new` or `session/load`.
+    #[serde(default)]
+    pub mcp_servers: Vec<McpServer>,
+    /// Model selected via `session/set_model` or reported by the provider.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Capabilities the client advertised in `initialize`; not persisted
+    /// because a loading client advertises its own.
+    #[serde(skip)]
+    pub client_capabilities: ClientCapabilities,
 }
 
 impl SessionState {
@@ -30,14 +48,87 @@ impl SessionState {
             session_id,
             working_dir,
             permission_mode,
+            mcp_servers: Vec::new(),
+            model: None,
+            client_capabilities: ClientCapabilities::default(),
         }
     }
+
+    /// Attach the client's MCP server configuration.
+    pub fn with_mcp_servers(mut self, mcp_servers: Vec<McpServer>) -> Self {
+        self.mcp_servers = mcp_servers;
+        self
+    }
+
+    /// Attach the capabilities the client advertised in `initialize`.
+    pub fn with_client_capabilities(mut self, capabilities: ClientCapabilities) -> Self {
+        self.client_capabilities = capabilities;
+        self
+    }
+}
+
+/// Live session plus the bookkeeping used for idle eviction.
+struct TrackedSession {
+    state: SessionState,
+    last_active: Instant,
+    /// Prompts currently running or queued; busy sessions are never evicted.
+    active_prompts: usize,
+    /// Held by the running prompt so queued prompts run one at a time.
+    turn_lock: Arc<Mutex<()>>,
+    /// Bumped on cancel so prompts queued before it are dropped.
+    cancel_epoch: u64,
+    /// Completed prompt turns.
+    turns: u64,
+    /// Token usage reported by the provider for the latest turn.
+    last_usage: Option<serde_json::Value>,
+}
+
+impl TrackedSession {
+    fn new(state: SessionState) -> Self {
+        Self {
+            state,
+            last_active: Instant::now(),
+            active_prompts: 0,
+            turn_lock: Arc::new(Mutex::new(())),
+            cancel_epoch: 0,
+            turns: 0,
+            last_usage: None,
+        }
+    }
+}
+
+/// Runtime activity of a live session, reported by `_acplb/session/info`.
+#[derive(Debug, Clone)]
+pub struct SessionStats {
+    pub turns: u64,
+    pub last_usage: Option<serde_json::Value>,
+    pub active_prompts: usize,
+    pub idle_for: Duration,
+}
+
+/// A prompt admitted by `SessionStore::begin_prompt`.
+///
+/// Every ticket must be released with `SessionStore::end_prompt`.
+pub struct PromptTicket {
+    /// Session state when the prompt arrived.
+    pub state: SessionState,
+    /// Prompts already running or queued for the session.
+    pub ahead: usize,
+    cancel_epoch: u64,
+    turn_lock: Arc<Mutex<()>>,
+}
+
+impl PromptTicket {
+    /// Wait until every prompt admitted before this one has finished.
+    pub async fn wait_turn(&self) -> OwnedMutexGuard<()> {
+        self.turn_lock.clone().lock_owned().await
+    }
 }
 
 /// Shared session store wrapper used by the runtime.
 #[derive(Clone, Default)]
 pub struct SessionStore {
-    inner: Arc<RwLock<HashMap<SessionId, SessionState>>>,
+    inner: Arc<RwLock<HashMap<SessionId, TrackedSession>>>,
 }
 
 impl SessionStore {
@@ -57,14 +148,36 @@ impl SessionStore {
     }
 
     pub async fn insert(&self, state: SessionState) -> Option<SessionState> {
+        let mut guard = self.inner.write().await;
+        // Re-inserting a live session keeps its prompt queue intact.
+        if let Some(tracked) = guard.get_mut(&state.session_id) {
+            tracked.last_active = Instant::now();
+            return Some(std::mem::replace(&mut tracked.state, state));
+        }
+        guard.insert(state.session_id.clone(), TrackedSession::new(state));
+        None
+    }
+
+    /// Ids of all tracked sessions.
+    pub async fn session_ids(&self) -> Vec<SessionId> {
+        self.inner.read().await.keys().cloned().collect()
+    }
+
+    pub async fn get(&self, session_id: &SessionId) -> Option<SessionState> {
         self.inner
-            .write()
+            .read()
             .await
-            .insert(state.session_id.clone(), state)
+            .get(session_id)
+            .map(|tracked| tracked.state.clone())
     }
 
-    pub async fn get(&self, session_id: &SessionId) -> Option<SessionState> {
-        self.inner.read().await.get(session_id).cloned()
+    /// Stop tracking a session, returning its last known state.
+    pub async fn remove(&self, session_id: &SessionId) -> Option<SessionState> {
+        self.inner
+            .write()
+            .await
+            .remove(session_id)
+            .map(|tracked| tracked.state)
     }
 
     pub async fn update_permission(
@@ -73,10 +186,121 @@ impl SessionStore {
         mode: AcpPermissionMode,
     ) -> Option<SessionState> {
         let mut guard = self.inner.write().await;
-        if let Some(state) = guard.get_mut(session_id) {
-            state.permission_mode = mode;
-            return Some(state.clone());
+        if let Some(tracked) = guard.get_mut(session_id) {
+            tracked.state.permission_mode = mode;
+            return Some(tracked.state.clone());
         }
         None
     }
+
+    pub async fn update_model(
+        &self,
+        session_id: &SessionId,
+        model: Option<String>,
+    ) -> Option<SessionState> {
+        let mut guard = self.inner.write().await;
+        if let Some(tracked) = guard.get_mut(session_id) {
+            tracked.state.model = model;
+            return Some(tracked.state.clone());
+        }
+        None
+    }
+
+    /// Admit a prompt into the session's queue, if the session is tracked.
+    pub async fn begin_prompt(&self, session_id: &SessionId) -> Option<PromptTicket> {
+        let mut guard = self.inner.write().await;
+        let tracked = guard.get_mut(session_id)?;
+        let ticket = PromptTicket {
+            state: tracked.state.clone(),
+            ahead: tracked.active_prompts,
+            cancel_epoch: tracked.cancel_epoch,
+            turn_lock: tracked.turn_lock.clone(),
+        };
+        tracked.active_prompts += 1;
+        tracked.last_active = Instant::now();
+        Some(ticket)
+    }
+
+    /// Drop every prompt admitted so far that has not started running.
+    pub async fn cancel_queued(&self, session_id: &SessionId) {
+        if let Some(tracked) = self.inner.write().await.get_mut(session_id) {
+            tracked.cancel_epoch += 1;
+        }
+    }
+
+    /// Whether `cancel_queued` ran for the session after `ticket` was admitted.
+    /// Closed sessions count as cancelled.
+    pub async fn is_cancelled(&self, session_id: &SessionId, ticket: &PromptTicket) -> bool {
+        self.inner
+            .read()
+            .await
+            .get(session_id)
+            .is_none_or(|tracked| tracked.cancel_epoch != ticket.cancel_epoch)
+    }
+
+    /// Mark a prompt admitted with `begin_prompt` as finished.
+    pub async fn end_prompt(&self, session_id: &SessionId) {
+        if let Some(tracked) = self.inner.write().await.get_mut(session_id) {
+            tracked.active_prompts = tracked.active_prompts.saturating_sub(1);
+            tracked.last_active = Instant::now();
+        }
+    }
+
+    /// Count a completed turn and remember the usage the provider reported.
+    pub async fn record_turn(&self, session_id: &SessionId, usage: Option<serde_json::Value>) {
+        if let Some(tracked) = self.inner.write().await.get_mut(session_id) {
+            tracked.turns += 1;
+            if usage.is_some() {
+                tracked.last_usage = usage;
+            }
+        }
+    }
+
+    /// Session state together with its runtime activity.
+    pub async fn stats(&self, session_id: &SessionId) -> Option<(SessionState, SessionStats)> {
+        let guard = self.inner.read().await;
+        let tracked = guard.get(session_id)?;
+        Some((
+            tracked.state.clone(),
+            SessionStats {
+                turns: tracked.turns,
+                last_usage: tracked.last_usage.clone(),
+                active_prompts: tracked.active_prompts,
+                idle_for: tracked.last_active.elapsed(),
+            },
+        ))
+    }
+
+    /// Sessions to evict: idle ones older than `ttl`, then the least recently
+    /// active idle sessions until at most `max_sessions` remain.
+    pub async fn eviction_candidates(
+        &self,
+        ttl: Option<Duration>,
+        max_sessions: Option<usize>,
+    ) -> Vec<SessionId> {
+        let guard = self.inner.read().await;
+        let now = Instant::now();
+
+        let mut idle: Vec<(&SessionId, Instant)> = guard
+            .iter()
+            .filter(|(_, tracked)| tracked.active_prompts == 0)
+            .map(|(id, tracked)| (id, tracked.last_active))
+            .collect();
+        idle.sort_by_key(|(_, last_active)| *last_active);
+
+        let expired = idle
+            .iter()
+            .take_while(|(_, last_active)| {
+                ttl.is_some_and(|ttl| now.duration_since(*last_active) >= ttl)
+            })
+            .count();
+        let over_limit = max_sessions
+            .map(|max| guard.len().saturating_sub(max))
+            .unwrap_or(0);
+
+        idle.into_iter()
+            .take(expired.max(over_limit))
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
 }
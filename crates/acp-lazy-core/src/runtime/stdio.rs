@@ -0,0 +1,43 @@
+//! Serving a `RuntimeServer` over stdio.
+//!
+//! `AgentSideConnection` is `!Send`, so the connection and the tasks feeding
+//! it (client requests, session notifications, the idle-session sweeper) run
+//! on one `LocalSet`. Every stdout write goes through the connection.
+
+use std::rc::Rc;
+
+use agent_client_protocol::AgentSideConnection;
+use tokio::sync::mpsc;
+use tokio::task::LocalSet;
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+use crate::runtime::client::{forward_session_notifications, serve_client_requests, ClientHandle};
+use crate::runtime::server::RuntimeServer;
+
+/// Serve `runtime` as an ACP agent on stdin/stdout until the client disconnects.
+///
+/// Attaches the notifier and client handle; call it from a current-thread
+/// tokio runtime.
+pub async fn serve_stdio(runtime: RuntimeServer) -> anyhow::Result<()> {
+    let stdout = tokio::io::stdout().compat_write();
+    let stdin = tokio::io::stdin().compat();
+
+    LocalSet::new()
+        .run_until(async move {
+            let (notify_tx, notify_rx) = mpsc::unbounded_channel();
+            let (client, client_rx) = ClientHandle::channel();
+            let runtime = runtime.with_notifier(Some(notify_tx)).with_client(client);
+
+            let (conn, io_task) = AgentSideConnection::new(runtime.clone(), stdout, stdin, |fut| {
+                tokio::task::spawn_local(fut);
+            });
+
+            let conn = Rc::new(conn);
+            tokio::task::spawn_local(serve_client_requests(conn.clone(), client_rx));
+            tokio::task::spawn_local(forward_session_notifications(conn, notify_rx));
+            tokio::task::spawn_local(runtime.run_session_sweeper());
+
+            io_task.await
+        })
+        .await
+}
@@ -0,0 +1,121 @@
+//! Client terminals (`terminal/*`).
+//!
+//! When the client advertises the `terminal` capability, adapters can run
+//! commands in editor terminals and embed them in tool calls through
+//! `ToolCallContent::Terminal`. `ClientTerminals` creates them; each
+//! `ClientTerminal` wraps the follow-up `terminal/output`,
+//! `terminal/wait_for_exit`, `terminal/kill` and `terminal/release` calls.
+
+use agent_client_protocol::{
+    CreateTerminalRequest, Error, KillTerminalCommandRequest, ReleaseTerminalRequest, SessionId,
+    TerminalExitStatus, TerminalId, TerminalOutputRequest, TerminalOutputResponse, ToolCallContent,
+    WaitForTerminalExitRequest,
+};
+
+use crate::runtime::client::ClientHandle;
+
+/// Terminal service handed to adapters by `RuntimeServer::client_terminals`.
+#[derive(Debug, Clone, Default)]
+pub struct ClientTerminals {
+    client: Option<ClientHandle>,
+    supported: bool,
+}
+
+impl ClientTerminals {
+    /// `supported` is the client's `terminal` capability.
+    pub fn new(client: Option<ClientHandle>, supported: bool) -> Self {
+        Self { client, supported }
+    }
+
+    /// True when a client is attached and advertised terminal support.
+    pub fn is_supported(&self) -> bool {
+        self.client.is_some() && self.supported
+    }
+
+    /// Start `request.command` in a new client terminal.
+    pub async fn create(&self, request: CreateTerminalRequest) -> Result<ClientTerminal, Error> {
+        let client = match &self.client {
+            Some(client) if self.supported => client,
+            _ => {
+                return Err(
+                    Error::method_not_found().with_data("client does not support terminal/create")
+                )
+            }
+        };
+        let session_id = request.session_id.clone();
+        let response = client.create_terminal(request).await?;
+        Ok(ClientTerminal {
+            client: client.clone(),
+            session_id,
+            id: response.terminal_id,
+        })
+    }
+}
+
+/// A terminal created in the client; call `release` once it is no longer needed.
+#[derive(Debug)]
+pub struct ClientTerminal {
+    client: ClientHandle,
+    session_id: SessionId,
+    id: TerminalId,
+}
+
+impl ClientTerminal {
+    pub fn id(&self) -> &TerminalId {
+        &self.id
+    }
+
+    /// Tool call content that displays this terminal.
+    pub fn content(&self) -> ToolCallContent {
+        ToolCallContent::Terminal {
+            terminal_id: self.id.clone(),
+        }
+    }
+
+    /// Output captured so far, and the exit status once the command finished.
+    pub async fn output(&self) -> Result<TerminalOutputResponse, Error> {
+        self.client
+            .terminal_output(TerminalOutputRequest {
+                session_id: self.session_id.clone(),
+                terminal_id: self.id.clone(),
+                meta: None,
+            })
+            .await
+    }
+
+    pub async fn wait_for_exit(&self) -> Result<TerminalExitStatus, Error> {
+        let response = self
+            .client
+            .wait_for_terminal_exit(WaitForTerminalExitRequest {
+                session_id: self.session_id.clone(),
+                terminal_id: self.id.clone(),
+                meta: None,
+            })
+            .await?;
+        Ok(response.exit_status)
+    }
+
+    /// Kill the command; the terminal stays valid until `release`.
+    pub async fn kill(&self) -> Result<(), Error> {
+        self.client
+            .kill_terminal_command(KillTerminalCommandRequest {
+                session_id: self.session_id.clone(),
+                terminal_id: self.id.clone(),
+                meta: None,
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Release the terminal. Tool calls that embed it keep showing its output.
+    pub async fn release(self) -> Result<(), Error> {
+        self.client
+            .release_terminal(ReleaseTerminalRequest {
+                session_id: self.session_id,
+                terminal_id: self.id,
+                meta: None,
+            })
+            .await?;
+        Ok(())
+    }
+}
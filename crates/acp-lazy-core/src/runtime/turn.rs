@@ -0,0 +1,583 @@
+//! One CLI process per prompt.
+//!
+//! Providers such as Claude Code run non-interactively: the prompt goes to
+//! stdin, events come back as JSON lines on stdout and the process exits
+//! when the turn is over. `TurnRegistry` tracks the running turn of each
+//! session so `session/cancel` can stop it; `spawn_turn_process`,
+//! `prompt_text`, `send_prompt` and `stream_turn_output` drive the process
+//! and `finish_turn` settles the `session/prompt` response.
+//! `ConversationStore` remembers the provider session each turn resumes.
+//!
+//! `TurnAdapter` puts these together as a `ProviderAdapter`; a provider
+//! crate only implements `TurnCli` (command line and capabilities) and
+//! `TurnMapper` (stdout events to session updates).
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use agent_client_protocol::{
+    AgentCapabilities, CancelNotification, ContentBlock, EmbeddedResourceResource, Error,
+    PromptRequest, PromptResponse, SessionId, SessionNotification, StopReason,
+};
+use async_trait::async_trait;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::Notify;
+use tokio::time::{self, Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::config::{BridgeConfig, ConfigError, ConfigLoader};
+use crate::runtime::adapter::{forward_update, ProviderAdapter, ProviderModel, SessionNotifier};
+use crate::runtime::server::{RuntimeConfig, RuntimeServer};
+use crate::runtime::session::SessionState;
+use crate::transport::ProcessTransport;
+
+/// How long a CLI that closed stdout may take to exit before it is killed;
+/// Claude and Gemini write their session files after the last event.
+const EXIT_GRACE: Duration = Duration::from_secs(5);
+
+/// Cancellation handle for one running turn.
+#[derive(Debug, Default)]
+pub struct TurnHandle {
+    cancelled: AtomicBool,
+    cancel_notify: Notify,
+}
+
+impl TurnHandle {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.cancel_notify.notify_one();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+/// Running turns keyed by session.
+#[derive(Debug, Default)]
+pub struct TurnRegistry {
+    turns: Mutex<HashMap<String, Arc<TurnHandle>>>,
+}
+
+impl TurnRegistry {
+    /// Register a new turn for `session_id`, replacing any previous one.
+    pub fn begin(&self, session_id: &SessionId) -> Arc<TurnHandle> {
+        let turn = Arc::new(TurnHandle::default());
+        if let Ok(mut turns) = self.turns.lock() {
+            turns.insert(session_id.0.to_string(), turn.clone());
+        }
+        turn
+    }
+
+    /// Forget `turn` unless a newer turn already replaced it.
+    pub fn end(&self, session_id: &SessionId, turn: &Arc<TurnHandle>) {
+        if let Ok(mut turns) = self.turns.lock() {
+            if turns
+                .get(session_id.0.as_ref())
+                .is_some_and(|current| Arc::ptr_eq(current, turn))
+            {
+                turns.remove(session_id.0.as_ref());
+            }
+        }
+    }
+
+    /// Cancel the session's running turn; returns false when none is running.
+    pub fn cancel(&self, session_id: &SessionId) -> bool {
+        let turn = self
+            .turns
+            .lock()
+            .ok()
+            .and_then(|turns| turns.get(session_id.0.as_ref()).cloned());
+        match turn {
+            Some(turn) => {
+                turn.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Number of turns currently running.
+    pub fn running(&self) -> usize {
+        self.turns
+            .lock()
+            .map(|turns| turns.len())
+            .unwrap_or_default()
+    }
+
+    /// Stream `process` as the session's running turn (see
+    /// `stream_turn_output`) and unregister it afterwards. A cancel that
+    /// arrives after stdout closed still reports `TurnEnd::Cancelled`.
+    pub async fn run<F>(
+        &self,
+        session_id: &SessionId,
+        process: &mut ProcessTransport,
+        idle_timeout: Duration,
+        on_line: F,
+    ) -> Result<(TurnEnd, ExitStatus), Error>
+    where
+        F: FnMut(&str),
+    {
+        let turn = self.begin(session_id);
+        let streamed = stream_turn_output(process, &turn, idle_timeout, on_line).await;
+        self.end(session_id, &turn);
+        let (end, status) = streamed?;
+        if turn.is_cancelled() {
+            return Ok((TurnEnd::Cancelled, status));
+        }
+        Ok((end, status))
+    }
+}
+
+/// Provider conversation backing an ACP session.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Conversation {
+    /// Provider session id the next turn resumes.
+    pub resume_id: Option<String>,
+    /// Model the provider reported running.
+    pub model: Option<String>,
+}
+
+/// Conversations keyed by ACP session.
+#[derive(Debug, Default)]
+pub struct ConversationStore {
+    conversations: Mutex<HashMap<String, Conversation>>,
+}
+
+impl ConversationStore {
+    /// The session's conversation; empty before its first turn.
+    pub fn get(&self, session_key: &str) -> Conversation {
+        self.conversations
+            .lock()
+            .ok()
+            .and_then(|map| map.get(session_key).cloned())
+            .unwrap_or_default()
+    }
+
+    pub fn store(&self, session_key: &str, conversation: Conversation) {
+        if let Ok(mut map) = self.conversations.lock() {
+            map.insert(session_key.to_string(), conversation);
+        }
+    }
+
+    pub fn remove(&self, session_id: &SessionId) {
+        if let Ok(mut map) = self.conversations.lock() {
+            map.remove(session_id.0.as_ref());
+        }
+    }
+}
+
+/// Why `stream_turn_output` stopped reading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TurnEnd {
+    /// The process closed stdout.
+    Exited,
+    /// The turn was cancelled; the process was killed.
+    Cancelled,
+    /// No output within the idle timeout; the process was killed.
+    IdleTimeout,
+}
+
+/// Response to `session/prompt` once the turn's process is gone.
+///
+/// Cancellation wins over anything the provider reported, then the
+/// provider's own `result`. A turn stopped for idling without a result ends
+/// normally, as in the Codex adapter, since ACP has no idle-timeout stop
+/// reason. `None` means the process exited without a result; each adapter
+/// decides what that means.
+pub fn finish_turn(
+    end: TurnEnd,
+    result: Option<Result<PromptResponse, Error>>,
+) -> Option<Result<PromptResponse, Error>> {
+    let stop_reason = match (end, result) {
+        (TurnEnd::Cancelled, _) => StopReason::Cancelled,
+        (_, Some(result)) => return Some(result),
+        (TurnEnd::IdleTimeout, None) => StopReason::EndTurn,
+        (TurnEnd::Exited, None) => return None,
+    };
+    Some(Ok(PromptResponse {
+        stop_reason,
+        meta: None,
+    }))
+}
+
+/// Spawn a turn's CLI in the session's working directory and log its stderr.
+pub async fn spawn_turn_process(
+    command: &str,
+    args: &[String],
+    working_dir: &Path,
+    name: &str,
+) -> Result<ProcessTransport, Error> {
+    let mut process = ProcessTransport::spawn(command, args, None, working_dir.to_str())
+        .await
+        .map_err(|e| Error::internal_error().with_data(e.to_string()))?;
+    if let Err(e) = process.monitor_stderr() {
+        warn!("Failed to monitor {} stderr: {}", name, e);
+    }
+    Ok(process)
+}
+
+/// Flatten an ACP prompt into the text a CLI reads from stdin.
+///
+/// Embedded text resources become `<context ref="...">` blocks and links
+/// become `@path` mentions (or markdown links for non-file URIs); images and
+/// audio are rejected.
+pub fn prompt_text(request: &PromptRequest) -> Result<String, Error> {
+    let mut parts = Vec::new();
+    for block in &request.prompt {
+        match block {
+            ContentBlock::Text(text) => parts.push(text.text.clone()),
+            ContentBlock::Resource(resource) => match &resource.resource {
+                EmbeddedResourceResource::TextResourceContents(contents) => parts.push(format!(
+                    "<context ref=\"{}\">\n{}\n</context>",
+                    contents.uri, contents.text
+                )),
+                EmbeddedResourceResource::BlobResourceContents(contents) => {
+                    parts.push(resource_mention(&contents.uri, &contents.uri))
+                }
+            },
+            ContentBlock::ResourceLink(link) => parts.push(resource_mention(&link.uri, &link.name)),
+            other => {
+                return Err(Error::invalid_params()
+                    .with_data(format!("unsupported content block in prompt: {:?}", other)));
+            }
+        }
+    }
+
+    if parts.is_empty() {
+        return Err(
+            Error::invalid_params().with_data("prompt must contain at least one content block")
+        );
+    }
+    Ok(parts.join("\n\n"))
+}
+
+/// Render a resource reference: `@path` for local files, a markdown link otherwise.
+pub fn resource_mention(uri: &str, name: &str) -> String {
+    match file_uri_to_path(uri) {
+        Some(path) => format!("@{}", path),
+        None => format!("[{}]({})", name, uri),
+    }
+}
+
+/// Local path of a `file://` URI, percent-decoded; `None` for other schemes.
+pub fn file_uri_to_path(uri: &str) -> Option<String> {
+    let path = uri.strip_prefix("file://")?;
+    Some(percent_decode(path))
+}
+
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(byte) = input
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Write the whole prompt to stdin and close it so the CLI starts the turn.
+pub async fn send_prompt(process: &mut ProcessTransport, prompt: &str) -> Result<(), Error> {
+    let written = async {
+        let stdin = process.stdin();
+        stdin.write_all(prompt.as_bytes()).await?;
+        stdin.flush().await
+    }
+    .await;
+    process.close_stdin();
+    if let Err(e) = written {
+        if let Err(kill_err) = process.kill().await {
+            warn!("Failed to kill CLI process: {}", kill_err);
+        }
+        return Err(Error::internal_error().with_data(format!("failed to send prompt: {}", e)));
+    }
+    Ok(())
+}
+
+/// Feed stdout lines to `on_line` until the process exits, the turn is
+/// cancelled or it stays silent for `idle_timeout`, then reap the process.
+///
+/// A cancelled or stalled CLI is killed at once; one that closed stdout gets
+/// `EXIT_GRACE` to finish up and is only killed if it overruns.
+pub async fn stream_turn_output<F>(
+    process: &mut ProcessTransport,
+    turn: &TurnHandle,
+    idle_timeout: Duration,
+    mut on_line: F,
+) -> Result<(TurnEnd, ExitStatus), Error>
+where
+    F: FnMut(&str),
+{
+    let stdout = process
+        .take_stdout()
+        .ok_or_else(|| Error::internal_error().with_data("missing stdout"))?;
+    let mut lines = BufReader::new(stdout).lines();
+    let idle_timer = time::sleep(idle_timeout);
+    tokio::pin!(idle_timer);
+
+    let end = loop {
+        tokio::select! {
+            _ = turn.cancel_notify.notified() => break TurnEnd::Cancelled,
+            line = lines.next_line() => match line {
+                Ok(Some(line)) => {
+                    on_line(&line);
+                    idle_timer.as_mut().reset(Instant::now() + idle_timeout);
+                }
+                Ok(None) => break TurnEnd::Exited,
+                Err(e) => {
+                    warn!("Failed to read CLI output: {}", e);
+                    break TurnEnd::Exited;
+                }
+            },
+            _ = &mut idle_timer => {
+                debug!("CLI turn produced no output for {:?}; stopping it", idle_timeout);
+                break TurnEnd::IdleTimeout;
+            }
+        }
+    };
+
+    let status = match end {
+        TurnEnd::Exited => match time::timeout(EXIT_GRACE, process.wait()).await {
+            Ok(status) => status,
+            Err(_) => {
+                warn!(
+                    "CLI still running {:?} after closing stdout; killing it",
+                    EXIT_GRACE
+                );
+                kill_process(process).await;
+                process.wait().await
+            }
+        },
+        TurnEnd::Cancelled | TurnEnd::IdleTimeout => {
+            kill_process(process).await;
+            process.wait().await
+        }
+    }
+    .map_err(|e| Error::internal_error().with_data(e.to_string()))?;
+    Ok((end, status))
+}
+
+async fn kill_process(process: &mut ProcessTransport) {
+    if process.is_running() {
+        if let Err(e) = process.kill().await {
+            warn!("Failed to kill CLI process: {}", e);
+        }
+    }
+}
+
+/// A provider CLI that runs one process per prompt.
+pub trait TurnCli: Send + Sync + 'static {
+    /// Provider name used in logs and errors (e.g. "Claude").
+    fn name(&self) -> &str;
+
+    /// Capabilities advertised during initialize.
+    fn agent_capabilities(&self) -> AgentCapabilities;
+
+    /// Models selectable through `session/set_model`; the first entry is the default.
+    fn available_models(&self) -> Vec<ProviderModel> {
+        Vec::new()
+    }
+
+    /// Command line and stdin for one turn.
+    fn turn_command(&self, turn: TurnContext<'_>) -> Result<TurnCommand, Error>;
+
+    /// Mapper for one turn's stdout.
+    fn mapper(&self, session_id: SessionId) -> Box<dyn TurnMapper + '_>;
+
+    /// Response when the process exits without reporting a result.
+    fn exited_without_result(&self, status: ExitStatus) -> Result<PromptResponse, Error> {
+        Err(Error::internal_error().with_data(format!(
+            "{} exited without a result ({})",
+            self.name(),
+            status
+        )))
+    }
+}
+
+/// What `TurnCli::turn_command` builds a turn from.
+pub struct TurnContext<'a> {
+    pub session: &'a SessionState,
+    /// Configuration resolved for the session cwd.
+    pub bridge: &'a BridgeConfig,
+    /// The provider session the previous turn left behind.
+    pub conversation: &'a Conversation,
+    /// The prompt rendered by `prompt_text`.
+    pub prompt: String,
+}
+
+/// Process to spawn for one turn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TurnCommand {
+    pub program: String,
+    pub args: Vec<String>,
+    /// Written to stdin, which is then closed; keeps the prompt out of argv.
+    pub stdin: String,
+}
+
+/// Translates one turn's stdout into session updates.
+pub trait TurnMapper {
+    /// Map one stdout line; malformed lines are logged and skipped.
+    fn process_line(&mut self, line: &str) -> Vec<SessionNotification>;
+
+    /// Provider session id the next turn resumes.
+    fn resume_id(&self) -> Option<&str>;
+
+    /// Model the provider reported running.
+    fn model(&self) -> Option<&str> {
+        None
+    }
+
+    /// Response built from the provider's final result event, once seen.
+    fn response(&self) -> Option<Result<PromptResponse, Error>>;
+}
+
+/// `ProviderAdapter` for a `TurnCli`.
+///
+/// Nothing stays running between turns: each prompt spawns the CLI, streams
+/// its output through the mapper and records the provider session so the
+/// next turn resumes it.
+pub struct TurnAdapter<C> {
+    cli: C,
+    /// Provider session ids and the model each session last reported.
+    conversations: ConversationStore,
+    turns: TurnRegistry,
+    /// Re-resolved per turn so a project `.acplb.toml` in the session cwd applies.
+    config_loader: ConfigLoader,
+}
+
+impl<C: TurnCli> TurnAdapter<C> {
+    pub fn new(cli: C, config_loader: ConfigLoader) -> Self {
+        Self {
+            cli,
+            conversations: ConversationStore::default(),
+            turns: TurnRegistry::default(),
+            config_loader,
+        }
+    }
+
+    /// Runtime serving `cli`.
+    ///
+    /// The user file and environment are validated here so mistakes surface
+    /// at startup; project `.acplb.toml` files are applied per turn.
+    pub fn runtime(cli: C, config_loader: ConfigLoader) -> Result<RuntimeServer, ConfigError> {
+        let config = config_loader.load(None)?.runtime_config();
+        let adapter: Arc<dyn ProviderAdapter> = Arc::new(Self::new(cli, config_loader));
+        Ok(RuntimeServer::new(adapter, config, None))
+    }
+
+    async fn run_turn(
+        &self,
+        session: &SessionState,
+        request: &PromptRequest,
+        notifier: &SessionNotifier,
+        config: &RuntimeConfig,
+        session_key: &str,
+    ) -> Result<PromptResponse, Error> {
+        let name = self.cli.name();
+        let bridge = self
+            .config_loader
+            .load(Some(&session.working_dir))
+            .map_err(|err| Error::internal_error().with_data(err.to_string()))?;
+        let conversation = self.conversations.get(session_key);
+        let command = self.cli.turn_command(TurnContext {
+            session,
+            bridge: &bridge,
+            conversation: &conversation,
+            prompt: prompt_text(request)?,
+        })?;
+
+        let mut process =
+            spawn_turn_process(&command.program, &command.args, &session.working_dir, name).await?;
+        send_prompt(&mut process, &command.stdin).await?;
+
+        let mut mapper = self.cli.mapper(session.session_id.clone());
+        let idle_timeout = Duration::from_millis(config.idle_timeout_ms.max(1));
+        let (end, status) = self
+            .turns
+            .run(&session.session_id, &mut process, idle_timeout, |line| {
+                for update in mapper.process_line(line) {
+                    forward_update(notifier, session_key, update);
+                }
+            })
+            .await?;
+
+        if let Some(id) = mapper.resume_id() {
+            self.conversations.store(
+                session_key,
+                Conversation {
+                    resume_id: Some(id.to_string()),
+                    model: mapper.model().map(str::to_string).or(conversation.model),
+                },
+            );
+        }
+
+        finish_turn(end, mapper.response())
+            .unwrap_or_else(|| self.cli.exited_without_result(status))
+    }
+}
+
+#[async_trait(?Send)]
+impl<C: TurnCli> ProviderAdapter for TurnAdapter<C> {
+    fn agent_capabilities(&self) -> AgentCapabilities {
+        self.cli.agent_capabilities()
+    }
+
+    fn available_models(&self) -> Vec<ProviderModel> {
+        self.cli.available_models()
+    }
+
+    async fn handle_prompt(
+        &self,
+        session: SessionState,
+        request: PromptRequest,
+        notifier: SessionNotifier,
+        config: &RuntimeConfig,
+    ) -> Result<PromptResponse, Error> {
+        let session_key = session.session_id.0.to_string();
+        let result = self
+            .run_turn(&session, &request, &notifier, config, &session_key)
+            .await;
+        if let Err(err) = &result {
+            warn!(
+                "{} turn failed for session {}: {}",
+                self.cli.name(),
+                session_key,
+                err.message
+            );
+        }
+        result
+    }
+
+    async fn handle_cancel(&self, notification: CancelNotification) -> Result<(), Error> {
+        self.turns.cancel(&notification.session_id);
+        Ok(())
+    }
+
+    async fn on_session_closed(&self, session_id: &SessionId) -> Result<(), Error> {
+        self.turns.cancel(session_id);
+        self.conversations.remove(session_id);
+        Ok(())
+    }
+
+    async fn active_model(&self, session_id: &SessionId) -> Option<String> {
+        self.conversations.get(&session_id.0).model
+    }
+
+    async fn running_processes(&self) -> usize {
+        self.turns.running()
+    }
+}
@@ -137,6 +137,11 @@ impl ProcessTransport {
         }
     }
 
+    /// Close stdin so the child sees end of input; `stdin()` panics afterwards.
+    pub fn close_stdin(&mut self) {
+        self.stdin.take();
+    }
+
     /// Get mutable reference to stdout for reading.
     pub fn stdout(&mut self) -> Option<&mut ChildStdout> {
         self.stdout.as_mut()
@@ -4,21 +4,43 @@
 //! `RuntimeServer` abstraction and ensure the runtime maintains the protocol
 //! guarantees captured in the specification and plan.
 
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use acp_lazy_core::permissions::AcpPermissionMode;
 use acp_lazy_core::runtime::{
-    ProviderAdapter, RuntimeConfig, RuntimeServer, SessionNotifier, SessionState,
+    file_uri_to_path, finish_turn, forward_session_notifications, prompt_text, resource_mention,
+    send_prompt, serve_client_requests, stream_turn_output, ClientHandle, JsonSessionPersistence,
+    PromptQueuePolicy, ProviderAdapter, ProviderModel, RuntimeConfig, RuntimeServer,
+    SessionNotifier, SessionPersistence, SessionState, TurnEnd, TurnRegistry, CONFIG_METHOD,
+    SESSION_CLOSE_METHOD, SESSION_INFO_METHOD, STATUS_METHOD,
 };
+use acp_lazy_core::transport::ProcessTransport;
 use agent_client_protocol::{
-    AgentCapabilities, AuthMethodId, AuthenticateRequest, CancelNotification, ClientCapabilities,
-    ContentBlock, Error, ErrorCode, ExtNotification, ExtRequest, InitializeRequest,
-    LoadSessionRequest, McpServer, NewSessionRequest, PromptRequest, PromptResponse, RawValue,
-    SessionId, SessionModeId, SetSessionModeRequest, StopReason, VERSION,
+    Agent, AgentCapabilities, AgentSideConnection, AuthMethod, AuthMethodId, AuthenticateRequest,
+    CancelNotification, Client, ClientCapabilities, ClientSideConnection, ContentBlock,
+    CreateTerminalRequest, CreateTerminalResponse, EmbeddedResource, EmbeddedResourceResource,
+    Error, ErrorCode, ExtNotification, ExtRequest, FileSystemCapability, InitializeRequest,
+    KillTerminalCommandRequest, KillTerminalCommandResponse, LoadSessionRequest, McpServer,
+    NewSessionRequest, PermissionOption, PermissionOptionId, PermissionOptionKind, PromptRequest,
+    PromptResponse, RawValue, ReadTextFileRequest, ReadTextFileResponse, ReleaseTerminalRequest,
+    ReleaseTerminalResponse, RequestPermissionOutcome, RequestPermissionRequest,
+    RequestPermissionResponse, ResourceLink, SessionId, SessionModeId, SessionNotification,
+    SessionUpdate, SetSessionModeRequest, StopReason, StreamMessageContent, StreamMessageDirection,
+    TerminalExitStatus, TerminalId, TerminalOutputRequest, TerminalOutputResponse,
+    TextResourceContents, ToolCallContent, ToolCallId, ToolCallUpdate, ToolCallUpdateFields,
+    WaitForTerminalExitRequest, WaitForTerminalExitResponse, WriteTextFileRequest,
+    WriteTextFileResponse, VERSION,
 };
 use anyhow::{bail, Context, Result};
 use async_trait::async_trait;
+use tokio::sync::{Mutex, Notify};
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
 
 fn runtime() -> RuntimeServer {
     let adapter: std::sync::Arc<dyn ProviderAdapter> = std::sync::Arc::new(TestAdapter);
@@ -115,6 +137,51 @@ async fn new_session_creates_entry_with_default_permission() -> Result<()> {
         .context("session should exist")?;
     assert_eq!(stored.working_dir, cwd);
     assert_eq!(stored.permission_mode, AcpPermissionMode::Default);
+
+    let modes = response
+        .modes
+        .context("new_session should advertise modes")?;
+    assert_eq!(modes.current_mode_id.0.as_ref(), "default");
+    let mode_ids: Vec<&str> = modes
+        .available_modes
+        .iter()
+        .map(|mode| mode.id.0.as_ref())
+        .collect();
+    assert_eq!(
+        mode_ids,
+        vec![
+            "default",
+            "plan",
+            "acceptEdits",
+            "bypassPermissions",
+            "yolo"
+        ]
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn new_session_stores_client_mcp_servers() -> Result<()> {
+    let runtime = runtime();
+    let mut request = new_session_request(std::env::current_dir()?);
+    request.mcp_servers = vec![McpServer::Stdio {
+        name: "repo".to_string(),
+        command: PathBuf::from("/usr/bin/mcp-repo"),
+        args: vec!["--stdio".to_string()],
+        env: Vec::new(),
+    }];
+
+    let response = runtime.new_session(request).await?;
+
+    let stored = runtime
+        .session_state(&response.session_id)
+        .await
+        .context("session should exist")?;
+    assert_eq!(stored.mcp_servers.len(), 1);
+    assert!(matches!(
+        &stored.mcp_servers[0],
+        McpServer::Stdio { name, .. } if name == "repo"
+    ));
     Ok(())
 }
 
@@ -151,6 +218,91 @@ async fn authenticate_is_not_supported() -> Result<()> {
     Ok(())
 }
 
+/// Adapter advertising one auth method that only accepts a logged-in state.
+struct AuthAdapter {
+    logged_in: bool,
+}
+
+#[async_trait(?Send)]
+impl ProviderAdapter for AuthAdapter {
+    fn agent_capabilities(&self) -> AgentCapabilities {
+        AgentCapabilities::default()
+    }
+
+    fn auth_methods(&self) -> Vec<AuthMethod> {
+        vec![AuthMethod {
+            id: AuthMethodId(Arc::from("login")),
+            name: "Login".to_string(),
+            description: None,
+            meta: None,
+        }]
+    }
+
+    async fn authenticate(&self, _method_id: &AuthMethodId) -> Result<(), Error> {
+        if self.logged_in {
+            Ok(())
+        } else {
+            Err(Error::auth_required())
+        }
+    }
+
+    async fn handle_prompt(
+        &self,
+        _session: SessionState,
+        _request: PromptRequest,
+        _notifier: SessionNotifier,
+        _config: &RuntimeConfig,
+    ) -> Result<PromptResponse, Error> {
+        Err(Error::auth_required())
+    }
+}
+
+#[tokio::test]
+async fn authenticate_delegates_to_advertised_methods() -> Result<()> {
+    let runtime = RuntimeServer::with_defaults(Arc::new(AuthAdapter { logged_in: true }), None);
+    let response = runtime
+        .initialize(InitializeRequest {
+            protocol_version: VERSION,
+            client_capabilities: ClientCapabilities::default(),
+            meta: None,
+        })
+        .await?;
+    assert_eq!(response.auth_methods.len(), 1);
+
+    runtime
+        .authenticate(AuthenticateRequest {
+            method_id: AuthMethodId(Arc::from("login")),
+            meta: None,
+        })
+        .await?;
+
+    let error = match runtime
+        .authenticate(AuthenticateRequest {
+            method_id: AuthMethodId(Arc::from("other")),
+            meta: None,
+        })
+        .await
+    {
+        Ok(_) => bail!("unknown auth methods should be rejected"),
+        Err(err) => err,
+    };
+    assert_error_code(error, ErrorCode::INVALID_PARAMS);
+
+    let logged_out = RuntimeServer::with_defaults(Arc::new(AuthAdapter { logged_in: false }), None);
+    let error = match logged_out
+        .authenticate(AuthenticateRequest {
+            method_id: AuthMethodId(Arc::from("login")),
+            meta: None,
+        })
+        .await
+    {
+        Ok(_) => bail!("failed logins should surface auth_required"),
+        Err(err) => err,
+    };
+    assert_error_code(error, ErrorCode::AUTH_REQUIRED);
+    Ok(())
+}
+
 #[tokio::test]
 async fn load_session_is_not_supported() -> Result<()> {
     let runtime = runtime();
@@ -169,9 +321,384 @@ async fn load_session_is_not_supported() -> Result<()> {
     Ok(())
 }
 
+/// Adapter that answers every prompt with a single agent message chunk.
+struct EchoAdapter;
+
+#[async_trait(?Send)]
+impl ProviderAdapter for EchoAdapter {
+    fn agent_capabilities(&self) -> AgentCapabilities {
+        AgentCapabilities::default()
+    }
+
+    async fn handle_prompt(
+        &self,
+        session: SessionState,
+        _request: PromptRequest,
+        notifier: SessionNotifier,
+        _config: &RuntimeConfig,
+    ) -> Result<PromptResponse, Error> {
+        if let Some(tx) = notifier {
+            let _ = tx.send(SessionNotification {
+                session_id: session.session_id.clone(),
+                update: SessionUpdate::AgentMessageChunk {
+                    content: ContentBlock::from("pong"),
+                },
+                meta: None,
+            });
+        }
+        Ok(PromptResponse {
+            stop_reason: StopReason::EndTurn,
+            meta: None,
+        })
+    }
+}
+
+fn persistent_runtime(state_dir: PathBuf, notifier: SessionNotifier) -> RuntimeServer {
+    let config = RuntimeConfig {
+        state_dir: Some(state_dir),
+        ..RuntimeConfig::default()
+    };
+    RuntimeServer::new(Arc::new(EchoAdapter), config, notifier)
+}
+
+fn chunk_texts(notifications: &[SessionNotification]) -> Vec<String> {
+    notifications
+        .iter()
+        .filter_map(|notification| match &notification.update {
+            SessionUpdate::UserMessageChunk {
+                content: ContentBlock::Text(text),
+            } => Some(format!("user: {}", text.text)),
+            SessionUpdate::AgentMessageChunk {
+                content: ContentBlock::Text(text),
+            } => Some(format!("agent: {}", text.text)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn load_session_replays_persisted_history() -> Result<()> {
+    let state_dir = tempfile::tempdir()?;
+    let cwd = std::env::current_dir()?;
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let first = persistent_runtime(state_dir.path().to_path_buf(), Some(tx));
+    let init = first
+        .initialize(InitializeRequest {
+            protocol_version: VERSION,
+            client_capabilities: ClientCapabilities::default(),
+            meta: None,
+        })
+        .await?;
+    assert!(init.agent_capabilities.load_session);
+
+    let created = first.new_session(new_session_request(cwd.clone())).await?;
+    first
+        .set_session_mode(SetSessionModeRequest {
+            session_id: created.session_id.clone(),
+            mode_id: SessionModeId(Arc::from("plan")),
+            meta: None,
+        })
+        .await?;
+    first
+        .prompt(PromptRequest {
+            session_id: created.session_id.clone(),
+            prompt: vec![ContentBlock::from("ping")],
+            meta: None,
+        })
+        .await?;
+
+    let mut live = Vec::new();
+    while let Ok(notification) = rx.try_recv() {
+        live.push(notification);
+    }
+    assert_eq!(chunk_texts(&live), vec!["agent: pong".to_string()]);
+
+    // A fresh runtime (e.g. after an editor restart) rehydrates from disk.
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let second = persistent_runtime(state_dir.path().to_path_buf(), Some(tx));
+    let loaded = second
+        .load_session(LoadSessionRequest {
+            mcp_servers: Vec::new(),
+            cwd,
+            session_id: created.session_id.clone(),
+            meta: None,
+        })
+        .await?;
+    // Only the transcript comes back; the provider starts a fresh conversation.
+    assert_eq!(
+        loaded.meta,
+        Some(serde_json::json!({ "contextRestored": false }))
+    );
+
+    let mut replayed = Vec::new();
+    while let Ok(notification) = rx.try_recv() {
+        replayed.push(notification);
+    }
+    assert_eq!(
+        chunk_texts(&replayed),
+        vec!["user: ping".to_string(), "agent: pong".to_string()]
+    );
+
+    let stored = second
+        .session_state(&created.session_id)
+        .await
+        .context("loaded session should be tracked")?;
+    assert_eq!(stored.permission_mode, AcpPermissionMode::Plan);
+    Ok(())
+}
+
+#[tokio::test]
+async fn persisted_session_files_do_not_collide() -> Result<()> {
+    let state_dir = tempfile::tempdir()?;
+    let persistence = JsonSessionPersistence::new(state_dir.path());
+    let cwd = std::env::current_dir()?;
+
+    let ids = ["a.b", "a_b", "a/b", "a%2Eb"].map(session_id);
+    for id in &ids {
+        persistence
+            .save_state(&SessionState::new(
+                id.clone(),
+                cwd.clone(),
+                AcpPermissionMode::Default,
+            ))
+            .await?;
+    }
+    persistence
+        .append_history(
+            &ids[0],
+            &[SessionNotification {
+                session_id: ids[0].clone(),
+                update: SessionUpdate::AgentMessageChunk {
+                    content: ContentBlock::from("only a.b"),
+                },
+                meta: None,
+            }],
+        )
+        .await?;
+
+    for (index, id) in ids.iter().enumerate() {
+        let loaded = persistence
+            .load(id)
+            .await?
+            .context("saved session should load")?;
+        assert_eq!(loaded.state.session_id, *id);
+        assert_eq!(loaded.history.len(), usize::from(index == 0));
+    }
+    assert_eq!(std::fs::read_dir(state_dir.path())?.count(), ids.len());
+    Ok(())
+}
+
+#[tokio::test]
+async fn load_session_rejects_unknown_persisted_session() -> Result<()> {
+    let state_dir = tempfile::tempdir()?;
+    let runtime = persistent_runtime(state_dir.path().to_path_buf(), None);
+
+    let error = match runtime
+        .load_session(LoadSessionRequest {
+            mcp_servers: Vec::new(),
+            cwd: std::env::current_dir()?,
+            session_id: session_id("session-missing"),
+            meta: None,
+        })
+        .await
+    {
+        Ok(_) => bail!("unknown sessions should not load"),
+        Err(err) => err,
+    };
+    assert_error_code(error, ErrorCode::INVALID_PARAMS);
+    Ok(())
+}
+
+#[tokio::test]
+async fn load_session_replays_history_before_responding() -> Result<()> {
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async {
+            let state_dir = tempfile::tempdir()?;
+            let cwd = std::env::current_dir()?;
+            let first = persistent_runtime(state_dir.path().to_path_buf(), None);
+            let created = first.new_session(new_session_request(cwd.clone())).await?;
+            first
+                .prompt(PromptRequest {
+                    session_id: created.session_id.clone(),
+                    prompt: vec![ContentBlock::from("ping")],
+                    meta: None,
+                })
+                .await?;
+
+            // Serve a fresh runtime over an in-memory ACP connection pair.
+            let (notify_tx, notify_rx) = tokio::sync::mpsc::unbounded_channel();
+            let (client, client_rx) = ClientHandle::channel();
+            let second = persistent_runtime(state_dir.path().to_path_buf(), Some(notify_tx))
+                .with_client(client);
+            let (agent_io, client_io) = tokio::io::duplex(64 * 1024);
+            let (agent_read, agent_write) = tokio::io::split(agent_io);
+            let (client_read, client_write) = tokio::io::split(client_io);
+            let (agent_conn, agent_task) = AgentSideConnection::new(
+                second,
+                agent_write.compat_write(),
+                agent_read.compat(),
+                |fut| {
+                    tokio::task::spawn_local(fut);
+                },
+            );
+            let agent_conn = Rc::new(agent_conn);
+            tokio::task::spawn_local(serve_client_requests(agent_conn.clone(), client_rx));
+            tokio::task::spawn_local(forward_session_notifications(agent_conn, notify_rx));
+            tokio::task::spawn_local(agent_task);
+            let (client_conn, client_task) = ClientSideConnection::new(
+                RecordingClient::default(),
+                client_write.compat_write(),
+                client_read.compat(),
+                |fut| {
+                    tokio::task::spawn_local(fut);
+                },
+            );
+            tokio::task::spawn_local(client_task);
+
+            let mut wire = client_conn.subscribe();
+            client_conn
+                .load_session(LoadSessionRequest {
+                    mcp_servers: Vec::new(),
+                    cwd,
+                    session_id: created.session_id.clone(),
+                    meta: None,
+                })
+                .await?;
+
+            // Both replayed updates reached the client before the response.
+            let mut seen = Vec::new();
+            loop {
+                let message = wire.recv().await?;
+                if message.direction != StreamMessageDirection::Incoming {
+                    continue;
+                }
+                match message.message {
+                    StreamMessageContent::Notification { method, .. } => {
+                        seen.push(method.to_string())
+                    }
+                    StreamMessageContent::Response { .. } => break,
+                    StreamMessageContent::Request { method, .. } => {
+                        bail!("unexpected request {}", method)
+                    }
+                }
+            }
+            assert_eq!(seen, vec!["session/update", "session/update"]);
+            Ok(())
+        })
+        .await
+}
+
+/// Adapter offering two models and reporting a fixed active model after each turn.
+struct ModelAdapter;
+
+#[async_trait(?Send)]
+impl ProviderAdapter for ModelAdapter {
+    fn agent_capabilities(&self) -> AgentCapabilities {
+        AgentCapabilities::default()
+    }
+
+    fn available_models(&self) -> Vec<ProviderModel> {
+        ["fast", "smart"]
+            .into_iter()
+            .map(|id| ProviderModel {
+                id: id.to_string(),
+                name: id.to_string(),
+                description: None,
+            })
+            .collect()
+    }
+
+    async fn handle_prompt(
+        &self,
+        _session: SessionState,
+        _request: PromptRequest,
+        _notifier: SessionNotifier,
+        _config: &RuntimeConfig,
+    ) -> Result<PromptResponse, Error> {
+        Ok(PromptResponse {
+            stop_reason: StopReason::EndTurn,
+            meta: None,
+        })
+    }
+
+    async fn active_model(&self, _session_id: &SessionId) -> Option<String> {
+        Some("smart".to_string())
+    }
+}
+
+#[tokio::test]
+async fn prompt_records_provider_reported_model() -> Result<()> {
+    let runtime = RuntimeServer::with_defaults(Arc::new(ModelAdapter), None);
+    let created = runtime
+        .new_session(new_session_request(std::env::current_dir()?))
+        .await?;
+
+    runtime
+        .prompt(PromptRequest {
+            session_id: created.session_id.clone(),
+            prompt: vec![ContentBlock::from("hi")],
+            meta: None,
+        })
+        .await?;
+
+    let stored = runtime
+        .session_state(&created.session_id)
+        .await
+        .context("session should exist")?;
+    assert_eq!(stored.model.as_deref(), Some("smart"));
+    Ok(())
+}
+
+#[cfg(feature = "unstable")]
+#[tokio::test]
+async fn set_session_model_tracks_selected_model() -> Result<()> {
+    use agent_client_protocol::{ModelId, SetSessionModelRequest};
+
+    let runtime = RuntimeServer::with_defaults(Arc::new(ModelAdapter), None);
+    let created = runtime
+        .new_session(new_session_request(std::env::current_dir()?))
+        .await?;
+    let models = created
+        .models
+        .context("new_session should advertise models")?;
+    assert_eq!(models.current_model_id.0.as_ref(), "fast");
+    assert_eq!(models.available_models.len(), 2);
+
+    runtime
+        .set_session_model(SetSessionModelRequest {
+            session_id: created.session_id.clone(),
+            model_id: ModelId(Arc::from("smart")),
+            meta: None,
+        })
+        .await?;
+    let stored = runtime
+        .session_state(&created.session_id)
+        .await
+        .context("session should exist")?;
+    assert_eq!(stored.model.as_deref(), Some("smart"));
+
+    let error = match runtime
+        .set_session_model(SetSessionModelRequest {
+            session_id: created.session_id.clone(),
+            model_id: ModelId(Arc::from("unknown")),
+            meta: None,
+        })
+        .await
+    {
+        Ok(_) => bail!("unlisted models should be rejected"),
+        Err(err) => err,
+    };
+    assert_error_code(error, ErrorCode::INVALID_PARAMS);
+    Ok(())
+}
+
 #[tokio::test]
 async fn set_session_mode_updates_permission_mode() -> Result<()> {
-    let runtime = runtime();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let adapter: Arc<dyn ProviderAdapter> = Arc::new(TestAdapter);
+    let runtime = RuntimeServer::with_defaults(adapter, Some(tx));
     let cwd = std::env::current_dir()?;
     let created = runtime.new_session(new_session_request(cwd)).await?;
 
@@ -188,6 +715,14 @@ async fn set_session_mode_updates_permission_mode() -> Result<()> {
         .await
         .context("session should exist")?;
     assert_eq!(stored.permission_mode, AcpPermissionMode::BypassPermissions);
+
+    let notification = rx.try_recv().context("mode change should be announced")?;
+    match notification.update {
+        SessionUpdate::CurrentModeUpdate { current_mode_id } => {
+            assert_eq!(current_mode_id.0.as_ref(), "bypassPermissions");
+        }
+        other => bail!("unexpected update: {:?}", other),
+    }
     Ok(())
 }
 
@@ -231,3 +766,1093 @@ async fn ext_notification_is_gracefully_ignored() -> Result<()> {
         .await?;
     Ok(())
 }
+
+/// Adapter recording which sessions the runtime closed.
+#[derive(Default)]
+struct ClosingAdapter {
+    closed: Mutex<Vec<SessionId>>,
+}
+
+#[async_trait(?Send)]
+impl ProviderAdapter for ClosingAdapter {
+    fn agent_capabilities(&self) -> AgentCapabilities {
+        AgentCapabilities::default()
+    }
+
+    async fn on_session_closed(&self, session_id: &SessionId) -> Result<(), Error> {
+        self.closed.lock().await.push(session_id.clone());
+        Ok(())
+    }
+
+    async fn handle_prompt(
+        &self,
+        _session: SessionState,
+        _request: PromptRequest,
+        _notifier: SessionNotifier,
+        _config: &RuntimeConfig,
+    ) -> Result<PromptResponse, Error> {
+        Ok(PromptResponse {
+            stop_reason: StopReason::EndTurn,
+            meta: None,
+        })
+    }
+}
+
+fn closing_runtime(
+    adapter: Arc<ClosingAdapter>,
+    session_ttl_ms: Option<u64>,
+    max_sessions: Option<usize>,
+) -> RuntimeServer {
+    let config = RuntimeConfig {
+        evidence_path: None,
+        state_dir: None,
+        session_ttl_ms,
+        max_sessions,
+        ..RuntimeConfig::default()
+    };
+    RuntimeServer::new(adapter, config, None)
+}
+
+#[tokio::test]
+async fn session_close_ext_method_releases_session() -> Result<()> {
+    let adapter = Arc::new(ClosingAdapter::default());
+    let runtime = closing_runtime(adapter.clone(), None, None);
+    let created = runtime
+        .new_session(new_session_request(std::env::current_dir()?))
+        .await?;
+
+    runtime
+        .ext_method(ExtRequest {
+            method: Arc::from(SESSION_CLOSE_METHOD),
+            params: raw_json(serde_json::json!({ "sessionId": created.session_id.0 }))?,
+        })
+        .await?;
+    assert_eq!(runtime.session_count().await, 0);
+    assert_eq!(
+        *adapter.closed.lock().await,
+        vec![created.session_id.clone()]
+    );
+
+    let error = match runtime.close_session(&created.session_id).await {
+        Ok(()) => bail!("closing an unknown session should fail"),
+        Err(err) => err,
+    };
+    assert_error_code(error, ErrorCode::INVALID_PARAMS);
+
+    let error = match runtime
+        .prompt(PromptRequest {
+            session_id: created.session_id,
+            prompt: vec![ContentBlock::from("hi")],
+            meta: None,
+        })
+        .await
+    {
+        Ok(_) => bail!("closed sessions should reject prompts"),
+        Err(err) => err,
+    };
+    assert_error_code(error, ErrorCode::INVALID_PARAMS);
+    Ok(())
+}
+
+#[tokio::test]
+async fn new_session_evicts_least_recently_active_over_limit() -> Result<()> {
+    let adapter = Arc::new(ClosingAdapter::default());
+    let runtime = closing_runtime(adapter.clone(), None, Some(2));
+    let cwd = std::env::current_dir()?;
+
+    let first = runtime
+        .new_session(new_session_request(cwd.clone()))
+        .await?;
+    let second = runtime
+        .new_session(new_session_request(cwd.clone()))
+        .await?;
+    // Prompting refreshes the first session, leaving the second as the oldest.
+    runtime
+        .prompt(PromptRequest {
+            session_id: first.session_id.clone(),
+            prompt: vec![ContentBlock::from("hi")],
+            meta: None,
+        })
+        .await?;
+    let third = runtime.new_session(new_session_request(cwd)).await?;
+
+    assert_eq!(runtime.session_count().await, 2);
+    assert_eq!(*adapter.closed.lock().await, vec![second.session_id]);
+    assert!(runtime.session_state(&first.session_id).await.is_some());
+    assert!(runtime.session_state(&third.session_id).await.is_some());
+    Ok(())
+}
+
+#[tokio::test]
+async fn sweep_evicts_sessions_idle_past_ttl() -> Result<()> {
+    let adapter = Arc::new(ClosingAdapter::default());
+    let runtime = closing_runtime(adapter.clone(), Some(50), None);
+    let created = runtime
+        .new_session(new_session_request(std::env::current_dir()?))
+        .await?;
+
+    assert!(runtime.sweep_sessions().await.is_empty());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert_eq!(runtime.sweep_sessions().await, vec![created.session_id]);
+    assert_eq!(runtime.session_count().await, 0);
+    assert_eq!(adapter.closed.lock().await.len(), 1);
+    Ok(())
+}
+
+/// Adapter whose prompts take a while and end early on cancel.
+#[derive(Default)]
+struct SlowAdapter {
+    running: AtomicUsize,
+    max_running: AtomicUsize,
+    handled: AtomicUsize,
+    cancel: Notify,
+}
+
+#[async_trait(?Send)]
+impl ProviderAdapter for SlowAdapter {
+    fn agent_capabilities(&self) -> AgentCapabilities {
+        AgentCapabilities::default()
+    }
+
+    async fn handle_prompt(
+        &self,
+        _session: SessionState,
+        _request: PromptRequest,
+        _notifier: SessionNotifier,
+        _config: &RuntimeConfig,
+    ) -> Result<PromptResponse, Error> {
+        self.handled.fetch_add(1, Ordering::SeqCst);
+        let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_running.fetch_max(running, Ordering::SeqCst);
+
+        let stop_reason = tokio::select! {
+            _ = tokio::time::sleep(Duration::from_millis(100)) => StopReason::EndTurn,
+            _ = self.cancel.notified() => StopReason::Cancelled,
+        };
+
+        self.running.fetch_sub(1, Ordering::SeqCst);
+        Ok(PromptResponse {
+            stop_reason,
+            meta: None,
+        })
+    }
+
+    async fn handle_cancel(&self, _notification: CancelNotification) -> Result<(), Error> {
+        self.cancel.notify_one();
+        Ok(())
+    }
+}
+
+fn slow_runtime(adapter: Arc<SlowAdapter>, prompt_queue: PromptQueuePolicy) -> RuntimeServer {
+    let config = RuntimeConfig {
+        evidence_path: None,
+        state_dir: None,
+        prompt_queue,
+        ..RuntimeConfig::default()
+    };
+    RuntimeServer::new(adapter, config, None)
+}
+
+fn prompt_request(session_id: &SessionId, text: &str) -> PromptRequest {
+    PromptRequest {
+        session_id: session_id.clone(),
+        prompt: vec![ContentBlock::from(text)],
+        meta: None,
+    }
+}
+
+#[tokio::test]
+async fn overlapping_prompts_are_serialized() -> Result<()> {
+    let adapter = Arc::new(SlowAdapter::default());
+    let runtime = slow_runtime(adapter.clone(), PromptQueuePolicy::Serialize);
+    let created = runtime
+        .new_session(new_session_request(std::env::current_dir()?))
+        .await?;
+
+    let (first, second) = tokio::join!(
+        runtime.prompt(prompt_request(&created.session_id, "one")),
+        runtime.prompt(prompt_request(&created.session_id, "two")),
+    );
+    assert_eq!(first?.stop_reason, StopReason::EndTurn);
+    assert_eq!(second?.stop_reason, StopReason::EndTurn);
+    assert_eq!(adapter.handled.load(Ordering::SeqCst), 2);
+    assert_eq!(adapter.max_running.load(Ordering::SeqCst), 1);
+    Ok(())
+}
+
+#[tokio::test]
+async fn overlapping_prompt_is_rejected_when_configured() -> Result<()> {
+    let adapter = Arc::new(SlowAdapter::default());
+    let runtime = slow_runtime(adapter.clone(), PromptQueuePolicy::Reject);
+    let created = runtime
+        .new_session(new_session_request(std::env::current_dir()?))
+        .await?;
+
+    let (first, second) = tokio::join!(
+        runtime.prompt(prompt_request(&created.session_id, "one")),
+        runtime.prompt(prompt_request(&created.session_id, "two")),
+    );
+    assert_eq!(first?.stop_reason, StopReason::EndTurn);
+    let error = match second {
+        Ok(_) => bail!("overlapping prompt should be rejected"),
+        Err(err) => err,
+    };
+    assert_error_code(error, ErrorCode::INVALID_REQUEST);
+    assert_eq!(adapter.handled.load(Ordering::SeqCst), 1);
+
+    // The rejected prompt must not leave the session looking busy.
+    runtime
+        .prompt(prompt_request(&created.session_id, "three"))
+        .await?;
+    assert_eq!(adapter.handled.load(Ordering::SeqCst), 2);
+    Ok(())
+}
+
+#[tokio::test]
+async fn cancel_drains_queued_prompts() -> Result<()> {
+    let adapter = Arc::new(SlowAdapter::default());
+    let runtime = slow_runtime(adapter.clone(), PromptQueuePolicy::Serialize);
+    let created = runtime
+        .new_session(new_session_request(std::env::current_dir()?))
+        .await?;
+
+    let cancel = async {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        runtime
+            .cancel(CancelNotification {
+                session_id: created.session_id.clone(),
+                meta: None,
+            })
+            .await
+    };
+    let (first, second, third, cancelled) = tokio::join!(
+        runtime.prompt(prompt_request(&created.session_id, "one")),
+        runtime.prompt(prompt_request(&created.session_id, "two")),
+        runtime.prompt(prompt_request(&created.session_id, "three")),
+        cancel,
+    );
+    cancelled?;
+    for response in [first?, second?, third?] {
+        assert_eq!(response.stop_reason, StopReason::Cancelled);
+    }
+    assert_eq!(adapter.handled.load(Ordering::SeqCst), 1);
+
+    // Prompts sent after the cancel run normally.
+    let response = runtime
+        .prompt(prompt_request(&created.session_id, "four"))
+        .await?;
+    assert_eq!(response.stop_reason, StopReason::EndTurn);
+    Ok(())
+}
+
+/// Adapter that reports token usage in `PromptResponse.meta`.
+struct UsageAdapter;
+
+#[async_trait(?Send)]
+impl ProviderAdapter for UsageAdapter {
+    fn agent_capabilities(&self) -> AgentCapabilities {
+        AgentCapabilities::default()
+    }
+
+    async fn handle_prompt(
+        &self,
+        _session: SessionState,
+        _request: PromptRequest,
+        _notifier: SessionNotifier,
+        _config: &RuntimeConfig,
+    ) -> Result<PromptResponse, Error> {
+        Ok(PromptResponse {
+            stop_reason: StopReason::EndTurn,
+            meta: Some(serde_json::json!({ "usage": { "totalTokens": 42 } })),
+        })
+    }
+}
+
+#[tokio::test]
+async fn prompt_usage_is_recorded_in_evidence_log() -> Result<()> {
+    let evidence_dir = tempfile::tempdir()?;
+    let evidence_path = evidence_dir.path().join("evidence.jsonl");
+    let config = RuntimeConfig {
+        evidence_path: Some(evidence_path.clone()),
+        state_dir: None,
+        ..RuntimeConfig::default()
+    };
+    let runtime = RuntimeServer::new(Arc::new(UsageAdapter), config, None);
+    let created = runtime
+        .new_session(new_session_request(std::env::current_dir()?))
+        .await?;
+
+    let response = runtime
+        .prompt(PromptRequest {
+            session_id: created.session_id,
+            prompt: vec![ContentBlock::from("hi")],
+            meta: None,
+        })
+        .await?;
+    assert!(response.meta.is_some());
+
+    let evidence = std::fs::read_to_string(&evidence_path)?;
+    let completed = evidence
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .find(|event| event["event"] == "prompt_completed")
+        .context("prompt_completed evidence")?;
+    assert_eq!(completed["details"]["usage"]["totalTokens"], 42);
+    Ok(())
+}
+
+fn ext_json(response: Arc<RawValue>) -> Result<serde_json::Value> {
+    serde_json::from_str(response.get()).context("ext response is json")
+}
+
+#[tokio::test]
+async fn status_and_session_info_describe_live_sessions() -> Result<()> {
+    let runtime = RuntimeServer::new(
+        Arc::new(UsageAdapter),
+        RuntimeConfig {
+            evidence_path: None,
+            state_dir: None,
+            ..RuntimeConfig::default()
+        },
+        None,
+    );
+    let created = runtime
+        .new_session(new_session_request(std::env::current_dir()?))
+        .await?;
+    runtime
+        .prompt(prompt_request(&created.session_id, "hi"))
+        .await?;
+
+    let status = ext_json(
+        runtime
+            .ext_method(ExtRequest {
+                method: Arc::from(STATUS_METHOD),
+                params: raw_json(serde_json::json!({}))?,
+            })
+            .await?,
+    )?;
+    assert_eq!(status["sessionCount"], 1);
+    assert_eq!(status["sessions"][0], created.session_id.0.as_ref());
+    assert_eq!(status["runningProcesses"], 0);
+    assert!(status["uptimeMs"].is_u64());
+
+    let info = ext_json(
+        runtime
+            .ext_method(ExtRequest {
+                method: Arc::from(SESSION_INFO_METHOD),
+                params: raw_json(serde_json::json!({ "sessionId": created.session_id.0 }))?,
+            })
+            .await?,
+    )?;
+    assert_eq!(info["mode"], "default");
+    assert_eq!(info["turns"], 1);
+    assert_eq!(info["usage"]["totalTokens"], 42);
+    assert_eq!(info["activePrompts"], 0);
+
+    let error = match runtime
+        .ext_method(ExtRequest {
+            method: Arc::from(SESSION_INFO_METHOD),
+            params: raw_json(serde_json::json!({ "sessionId": "missing" }))?,
+        })
+        .await
+    {
+        Ok(_) => bail!("unknown sessions should be rejected"),
+        Err(err) => err,
+    };
+    assert_error_code(error, ErrorCode::INVALID_PARAMS);
+    Ok(())
+}
+
+#[tokio::test]
+async fn config_ext_method_reports_effective_config() -> Result<()> {
+    let config = RuntimeConfig {
+        idle_timeout_ms: 5_000,
+        evidence_path: None,
+        state_dir: None,
+        max_sessions: Some(3),
+        prompt_queue: PromptQueuePolicy::Reject,
+        ..RuntimeConfig::default()
+    };
+    let runtime = RuntimeServer::new(Arc::new(TestAdapter), config, None);
+
+    let config = ext_json(
+        runtime
+            .ext_method(ExtRequest {
+                method: Arc::from(CONFIG_METHOD),
+                params: raw_json(serde_json::json!({}))?,
+            })
+            .await?,
+    )?;
+    assert_eq!(config["idleTimeoutMs"], 5_000);
+    assert_eq!(config["maxSessions"], 3);
+    assert_eq!(config["promptQueue"], "reject");
+    assert!(config["stateDir"].is_null());
+    Ok(())
+}
+
+/// Adapter registering its own extension method.
+struct ExtAdapter;
+
+#[async_trait(?Send)]
+impl ProviderAdapter for ExtAdapter {
+    fn agent_capabilities(&self) -> AgentCapabilities {
+        AgentCapabilities {
+            meta: Some(serde_json::json!({ "provider": "ext" })),
+            ..AgentCapabilities::default()
+        }
+    }
+
+    fn ext_methods(&self) -> Vec<String> {
+        vec!["acplb/test/echo".to_string()]
+    }
+
+    async fn ext_method(&self, request: ExtRequest) -> Result<Arc<RawValue>, Error> {
+        Ok(request.params)
+    }
+
+    async fn handle_prompt(
+        &self,
+        _session: SessionState,
+        _request: PromptRequest,
+        _notifier: SessionNotifier,
+        _config: &RuntimeConfig,
+    ) -> Result<PromptResponse, Error> {
+        Ok(PromptResponse {
+            stop_reason: StopReason::EndTurn,
+            meta: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn provider_ext_methods_are_routed_and_advertised() -> Result<()> {
+    let runtime = RuntimeServer::with_defaults(Arc::new(ExtAdapter), None);
+    let response = runtime
+        .initialize(InitializeRequest {
+            protocol_version: VERSION,
+            client_capabilities: ClientCapabilities::default(),
+            meta: None,
+        })
+        .await?;
+    let meta = response
+        .agent_capabilities
+        .meta
+        .context("capabilities meta")?;
+    assert_eq!(meta["provider"], "ext");
+    assert_eq!(
+        meta["acplb"]["extMethods"],
+        serde_json::json!([
+            "_acplb/session/close",
+            "_acplb/status",
+            "_acplb/session/info",
+            "_acplb/config",
+            "_acplb/test/echo",
+        ])
+    );
+
+    let echoed = ext_json(
+        runtime
+            .ext_method(ExtRequest {
+                method: Arc::from("acplb/test/echo"),
+                params: raw_json(serde_json::json!({ "ping": true }))?,
+            })
+            .await?,
+    )?;
+    assert_eq!(echoed, serde_json::json!({ "ping": true }));
+    Ok(())
+}
+
+struct ApprovingClient;
+
+#[async_trait(?Send)]
+impl Client for ApprovingClient {
+    async fn request_permission(
+        &self,
+        args: RequestPermissionRequest,
+    ) -> Result<RequestPermissionResponse, Error> {
+        let option_id = args
+            .options
+            .first()
+            .map(|option| option.id.clone())
+            .ok_or_else(Error::invalid_params)?;
+        Ok(RequestPermissionResponse {
+            outcome: RequestPermissionOutcome::Selected { option_id },
+            meta: None,
+        })
+    }
+
+    async fn session_notification(&self, _args: SessionNotification) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn client_handle_forwards_permission_requests() -> Result<()> {
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async {
+            let (client, rx) = ClientHandle::channel();
+            tokio::task::spawn_local(serve_client_requests(Rc::new(ApprovingClient), rx));
+
+            let response = client
+                .request_permission(RequestPermissionRequest {
+                    session_id: session_id("session-permission"),
+                    tool_call: ToolCallUpdate {
+                        id: ToolCallId(Arc::from("call-1")),
+                        fields: ToolCallUpdateFields::default(),
+                        meta: None,
+                    },
+                    options: vec![PermissionOption {
+                        id: PermissionOptionId(Arc::from("allow-once")),
+                        name: "Allow".into(),
+                        kind: PermissionOptionKind::AllowOnce,
+                        meta: None,
+                    }],
+                    meta: None,
+                })
+                .await?;
+
+            match response.outcome {
+                RequestPermissionOutcome::Selected { option_id } => {
+                    assert_eq!(option_id.0.as_ref(), "allow-once");
+                }
+                other => bail!("unexpected outcome: {:?}", other),
+            }
+            Ok(())
+        })
+        .await
+}
+
+/// Client recording the text of every `session/update` it receives.
+#[derive(Default)]
+struct RecordingClient {
+    updates: RefCell<Vec<String>>,
+}
+
+#[async_trait(?Send)]
+impl Client for RecordingClient {
+    async fn request_permission(
+        &self,
+        _args: RequestPermissionRequest,
+    ) -> Result<RequestPermissionResponse, Error> {
+        Err(Error::method_not_found())
+    }
+
+    async fn session_notification(&self, args: SessionNotification) -> Result<(), Error> {
+        if let SessionUpdate::AgentMessageChunk {
+            content: ContentBlock::Text(text),
+        } = args.update
+        {
+            self.updates.borrow_mut().push(text.text);
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn session_notifications_are_forwarded_in_order() -> Result<()> {
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async {
+            let client = Rc::new(RecordingClient::default());
+            let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+            let forwarder =
+                tokio::task::spawn_local(forward_session_notifications(client.clone(), rx));
+
+            for index in 0..20 {
+                tx.send(SessionNotification {
+                    session_id: session_id("session-forward"),
+                    update: SessionUpdate::AgentMessageChunk {
+                        content: ContentBlock::from(format!("chunk-{}", index)),
+                    },
+                    meta: None,
+                })?;
+            }
+            drop(tx);
+            forwarder.await?;
+
+            let expected: Vec<String> = (0..20).map(|index| format!("chunk-{}", index)).collect();
+            assert_eq!(*client.updates.borrow(), expected);
+            Ok(())
+        })
+        .await
+}
+
+/// Adapter recording the client handle and capabilities the runtime hands it.
+#[derive(Default)]
+struct ClientAwareAdapter {
+    client: std::sync::OnceLock<ClientHandle>,
+    capabilities: std::sync::Mutex<Option<ClientCapabilities>>,
+}
+
+#[async_trait(?Send)]
+impl ProviderAdapter for ClientAwareAdapter {
+    fn agent_capabilities(&self) -> AgentCapabilities {
+        AgentCapabilities::default()
+    }
+
+    fn attach_client(&self, client: ClientHandle) {
+        let _ = self.client.set(client);
+    }
+
+    fn on_initialize(&self, capabilities: &ClientCapabilities) {
+        // ast-grep-ignore: rust-no-unwrap
+        *self.capabilities.lock().unwrap() = Some(capabilities.clone());
+    }
+
+    async fn handle_prompt(
+        &self,
+        _session: SessionState,
+        _request: PromptRequest,
+        _notifier: SessionNotifier,
+        _config: &RuntimeConfig,
+    ) -> Result<PromptResponse, Error> {
+        Ok(PromptResponse {
+            stop_reason: StopReason::EndTurn,
+            meta: None,
+        })
+    }
+}
+
+#[tokio::test]
+async fn runtime_shares_client_handle_with_provider() -> Result<()> {
+    let adapter = Arc::new(ClientAwareAdapter::default());
+    let runtime = RuntimeServer::with_defaults(adapter.clone(), None);
+    assert!(runtime.client().is_none());
+
+    let (client, _rx) = ClientHandle::channel();
+    let runtime = runtime.with_client(client);
+    assert!(runtime.client().is_some());
+    assert!(adapter.client.get().is_some());
+    Ok(())
+}
+
+/// Client serving `fs/*` from an in-memory map of editor buffers.
+#[derive(Default)]
+struct BufferClient {
+    buffers: RefCell<HashMap<PathBuf, String>>,
+}
+
+#[async_trait(?Send)]
+impl Client for BufferClient {
+    async fn request_permission(
+        &self,
+        _args: RequestPermissionRequest,
+    ) -> Result<RequestPermissionResponse, Error> {
+        Err(Error::method_not_found())
+    }
+
+    async fn session_notification(&self, _args: SessionNotification) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn read_text_file(
+        &self,
+        args: ReadTextFileRequest,
+    ) -> Result<ReadTextFileResponse, Error> {
+        let content = self
+            .buffers
+            .borrow()
+            .get(&args.path)
+            .cloned()
+            .ok_or_else(|| Error::resource_not_found(None))?;
+        Ok(ReadTextFileResponse {
+            content,
+            meta: None,
+        })
+    }
+
+    async fn write_text_file(
+        &self,
+        args: WriteTextFileRequest,
+    ) -> Result<WriteTextFileResponse, Error> {
+        self.buffers.borrow_mut().insert(args.path, args.content);
+        Ok(WriteTextFileResponse { meta: None })
+    }
+}
+
+#[tokio::test]
+async fn client_fs_follows_initialize_capabilities() -> Result<()> {
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async {
+            let dir = tempfile::tempdir()?;
+            let path = dir.path().join("notes.md");
+            std::fs::write(&path, "on disk")?;
+
+            let buffers = Rc::new(BufferClient::default());
+            buffers
+                .buffers
+                .borrow_mut()
+                .insert(path.clone(), "unsaved".to_string());
+            let (client, rx) = ClientHandle::channel();
+            tokio::task::spawn_local(serve_client_requests(buffers.clone(), rx));
+
+            let adapter = Arc::new(ClientAwareAdapter::default());
+            let runtime = RuntimeServer::with_defaults(adapter.clone(), None).with_client(client);
+            let session = session_id("session-fs");
+
+            // Before initialize nothing is advertised, so the disk is used.
+            let fs = runtime.client_fs();
+            assert!(!fs.reads_from_client());
+            assert_eq!(fs.read_text_file(&session, &path).await?, "on disk");
+            assert!(fs
+                .read_text_file(&session, std::path::Path::new("relative.md"))
+                .await
+                .is_err());
+
+            runtime
+                .initialize(InitializeRequest {
+                    protocol_version: VERSION,
+                    client_capabilities: ClientCapabilities {
+                        fs: FileSystemCapability {
+                            read_text_file: true,
+                            write_text_file: true,
+                            meta: None,
+                        },
+                        terminal: false,
+                        meta: None,
+                    },
+                    meta: None,
+                })
+                .await?;
+            assert!(runtime.client_capabilities().fs.read_text_file);
+            // ast-grep-ignore: rust-no-unwrap
+            let seen = adapter.capabilities.lock().unwrap().clone();
+            assert!(seen.is_some_and(|caps| caps.fs.write_text_file));
+
+            // Sessions carry the capabilities so adapters need no copy of their own.
+            let opened = runtime
+                .new_session(new_session_request(dir.path().to_path_buf()))
+                .await?;
+            let state = runtime.session_state(&opened.session_id).await;
+            assert!(state.is_some_and(|state| state.client_capabilities.fs.read_text_file));
+
+            let fs = runtime.client_fs();
+            assert!(fs.reads_from_client() && fs.writes_to_client());
+            assert_eq!(fs.read_text_file(&session, &path).await?, "unsaved");
+            fs.write_text_file(&session, &path, "edited".to_string())
+                .await?;
+            assert_eq!(
+                buffers.buffers.borrow().get(&path).map(String::as_str),
+                Some("edited")
+            );
+            assert_eq!(std::fs::read_to_string(&path)?, "on disk");
+            Ok(())
+        })
+        .await
+}
+
+/// Client with a single scripted terminal, recording the `terminal/*` calls.
+#[derive(Default)]
+struct TerminalClient {
+    calls: RefCell<Vec<String>>,
+}
+
+#[async_trait(?Send)]
+impl Client for TerminalClient {
+    async fn request_permission(
+        &self,
+        _args: RequestPermissionRequest,
+    ) -> Result<RequestPermissionResponse, Error> {
+        Err(Error::method_not_found())
+    }
+
+    async fn session_notification(&self, _args: SessionNotification) -> Result<(), Error> {
+        Ok(())
+    }
+
+    async fn create_terminal(
+        &self,
+        args: CreateTerminalRequest,
+    ) -> Result<CreateTerminalResponse, Error> {
+        self.calls
+            .borrow_mut()
+            .push(format!("create {}", args.command));
+        Ok(CreateTerminalResponse {
+            terminal_id: TerminalId(Arc::from("term-1")),
+            meta: None,
+        })
+    }
+
+    async fn terminal_output(
+        &self,
+        args: TerminalOutputRequest,
+    ) -> Result<TerminalOutputResponse, Error> {
+        self.calls
+            .borrow_mut()
+            .push(format!("output {}", args.terminal_id.0));
+        Ok(TerminalOutputResponse {
+            output: "partial".to_string(),
+            truncated: false,
+            exit_status: None,
+            meta: None,
+        })
+    }
+
+    async fn wait_for_terminal_exit(
+        &self,
+        args: WaitForTerminalExitRequest,
+    ) -> Result<WaitForTerminalExitResponse, Error> {
+        self.calls
+            .borrow_mut()
+            .push(format!("wait {}", args.terminal_id.0));
+        Ok(WaitForTerminalExitResponse {
+            exit_status: TerminalExitStatus {
+                exit_code: None,
+                signal: Some("SIGTERM".to_string()),
+                meta: None,
+            },
+            meta: None,
+        })
+    }
+
+    async fn kill_terminal_command(
+        &self,
+        args: KillTerminalCommandRequest,
+    ) -> Result<KillTerminalCommandResponse, Error> {
+        self.calls
+            .borrow_mut()
+            .push(format!("kill {}", args.terminal_id.0));
+        Ok(KillTerminalCommandResponse { meta: None })
+    }
+
+    async fn release_terminal(
+        &self,
+        args: ReleaseTerminalRequest,
+    ) -> Result<ReleaseTerminalResponse, Error> {
+        self.calls
+            .borrow_mut()
+            .push(format!("release {}", args.terminal_id.0));
+        Ok(ReleaseTerminalResponse { meta: None })
+    }
+}
+
+fn terminal_request(session: &SessionId, command: &str) -> CreateTerminalRequest {
+    CreateTerminalRequest {
+        session_id: session.clone(),
+        command: command.to_string(),
+        args: Vec::new(),
+        env: Vec::new(),
+        cwd: None,
+        output_byte_limit: None,
+        meta: None,
+    }
+}
+
+#[tokio::test]
+async fn client_terminals_wrap_terminal_methods() -> Result<()> {
+    let local = tokio::task::LocalSet::new();
+    local
+        .run_until(async {
+            let terminal_client = Rc::new(TerminalClient::default());
+            let (client, rx) = ClientHandle::channel();
+            tokio::task::spawn_local(serve_client_requests(terminal_client.clone(), rx));
+
+            let runtime = runtime().with_client(client);
+            let session = session_id("session-terminal");
+
+            // Not advertised yet: nothing reaches the client.
+            let terminals = runtime.client_terminals();
+            assert!(!terminals.is_supported());
+            let err = terminals
+                .create(terminal_request(&session, "cargo"))
+                .await
+                .err()
+                .context("create should fail without the terminal capability")?;
+            assert_error_code(err, ErrorCode::METHOD_NOT_FOUND);
+
+            runtime
+                .initialize(InitializeRequest {
+                    protocol_version: VERSION,
+                    client_capabilities: ClientCapabilities {
+                        terminal: true,
+                        ..ClientCapabilities::default()
+                    },
+                    meta: None,
+                })
+                .await?;
+            let terminals = runtime.client_terminals();
+            assert!(terminals.is_supported());
+
+            let terminal = terminals
+                .create(terminal_request(&session, "cargo"))
+                .await?;
+            assert_eq!(
+                terminal.content(),
+                ToolCallContent::Terminal {
+                    terminal_id: TerminalId(Arc::from("term-1")),
+                }
+            );
+            assert_eq!(terminal.output().await?.output, "partial");
+            terminal.kill().await?;
+            let status = terminal.wait_for_exit().await?;
+            assert_eq!(status.signal.as_deref(), Some("SIGTERM"));
+            terminal.release().await?;
+
+            assert_eq!(
+                *terminal_client.calls.borrow(),
+                [
+                    "create cargo",
+                    "output term-1",
+                    "kill term-1",
+                    "wait term-1",
+                    "release term-1"
+                ]
+            );
+            Ok(())
+        })
+        .await
+}
+
+#[test]
+fn prompt_text_flattens_context_and_links() -> Result<()> {
+    let request = PromptRequest {
+        session_id: session_id("prompt-text"),
+        prompt: vec![
+            ContentBlock::from("Explain this"),
+            ContentBlock::Resource(EmbeddedResource {
+                annotations: None,
+                resource: EmbeddedResourceResource::TextResourceContents(TextResourceContents {
+                    mime_type: None,
+                    text: "fn main() {}".to_string(),
+                    uri: "file:///repo/src/main.rs".to_string(),
+                    meta: None,
+                }),
+                meta: None,
+            }),
+            ContentBlock::ResourceLink(ResourceLink {
+                annotations: None,
+                description: None,
+                mime_type: None,
+                name: "lib.rs".to_string(),
+                size: None,
+                title: None,
+                uri: "file:///repo/src/lib.rs".to_string(),
+                meta: None,
+            }),
+        ],
+        meta: None,
+    };
+
+    assert_eq!(
+        prompt_text(&request)?,
+        "Explain this\n\n<context ref=\"file:///repo/src/main.rs\">\nfn main() {}\n</context>\n\n@/repo/src/lib.rs"
+    );
+
+    let empty = PromptRequest {
+        prompt: Vec::new(),
+        ..request
+    };
+    let error = match prompt_text(&empty) {
+        Ok(_) => bail!("empty prompt should be rejected"),
+        Err(err) => err,
+    };
+    assert_error_code(error, ErrorCode::INVALID_PARAMS);
+    Ok(())
+}
+
+#[test]
+fn file_uris_are_percent_decoded() {
+    assert_eq!(
+        file_uri_to_path("file:///repo/My%20Notes%2Bdraft.md").as_deref(),
+        Some("/repo/My Notes+draft.md")
+    );
+    // A stray `%` without two hex digits is kept as-is.
+    assert_eq!(
+        file_uri_to_path("file:///repo/100%.txt").as_deref(),
+        Some("/repo/100%.txt")
+    );
+    assert_eq!(file_uri_to_path("https://example.com/a%20b"), None);
+    assert_eq!(
+        resource_mention("file:///repo/My%20Notes.md", "My Notes.md"),
+        "@/repo/My Notes.md"
+    );
+    assert_eq!(
+        resource_mention("https://example.com/spec", "spec"),
+        "[spec](https://example.com/spec)"
+    );
+}
+
+#[tokio::test]
+async fn turn_output_streams_lines_and_stops_on_cancel() -> Result<()> {
+    let session = session_id("cli-turn");
+    let turns = TurnRegistry::default();
+    let idle_timeout = Duration::from_secs(10);
+
+    // The script echoes its stdin back once the prompt is complete.
+    let script = vec!["-c".to_string(), "cat".to_string()];
+    let mut process = ProcessTransport::spawn("sh", &script, None, None).await?;
+    send_prompt(&mut process, "first\nsecond\n").await?;
+    let turn = turns.begin(&session);
+    let mut lines = Vec::new();
+    let (end, status) = stream_turn_output(&mut process, &turn, idle_timeout, |line| {
+        lines.push(line.to_string())
+    })
+    .await?;
+    turns.end(&session, &turn);
+    assert_eq!(end, TurnEnd::Exited);
+    assert!(status.success());
+    assert_eq!(lines, vec!["first", "second"]);
+    assert_eq!(turns.running(), 0);
+
+    // A CLI that closes stdout before saving its session is not killed.
+    let dir = tempfile::tempdir()?;
+    let saved = dir.path().join("session.json");
+    let script = vec![
+        "-c".to_string(),
+        "echo done; exec 1>&-; sleep 0.2; echo {} > \"$0\"".to_string(),
+        saved.display().to_string(),
+    ];
+    let mut process = ProcessTransport::spawn("sh", &script, None, None).await?;
+    send_prompt(&mut process, "").await?;
+    let turn = turns.begin(&session);
+    let (end, status) = stream_turn_output(&mut process, &turn, idle_timeout, |_| {}).await?;
+    turns.end(&session, &turn);
+    assert_eq!(end, TurnEnd::Exited);
+    assert!(status.success());
+    assert!(saved.exists());
+
+    let script = vec!["-c".to_string(), "echo started; sleep 30".to_string()];
+    let mut process = ProcessTransport::spawn("sh", &script, None, None).await?;
+    send_prompt(&mut process, "").await?;
+    let turn = turns.begin(&session);
+    assert_eq!(turns.running(), 1);
+    let (end, _) = stream_turn_output(&mut process, &turn, idle_timeout, |_| {
+        assert!(turns.cancel(&session));
+    })
+    .await?;
+    turns.end(&session, &turn);
+    assert_eq!(end, TurnEnd::Cancelled);
+    assert!(turn.is_cancelled());
+    assert!(!turns.cancel(&session));
+    Ok(())
+}
+
+#[test]
+fn finish_turn_prefers_cancellation_then_provider_result() {
+    let provider = || {
+        Some(Ok(PromptResponse {
+            stop_reason: StopReason::MaxTokens,
+            meta: None,
+        }))
+    };
+    let stop_reason = |response: Option<Result<PromptResponse, Error>>| {
+        response.map(|response| response.expect("response").stop_reason)
+    };
+
+    assert_eq!(
+        stop_reason(finish_turn(TurnEnd::Cancelled, provider())),
+        Some(StopReason::Cancelled)
+    );
+    assert_eq!(
+        stop_reason(finish_turn(TurnEnd::IdleTimeout, provider())),
+        Some(StopReason::MaxTokens)
+    );
+    assert_eq!(
+        stop_reason(finish_turn(TurnEnd::IdleTimeout, None)),
+        Some(StopReason::EndTurn)
+    );
+    assert!(finish_turn(TurnEnd::Exited, None).is_none());
+}
@@ -0,0 +1,205 @@
+//! Harness shared by the per-turn adapter tests (Claude, Gemini, JSONL).
+//!
+//! `fake-cli` next to this file stands in for the provider CLI: it prints a
+//! recorded transcript from the including crate's `tests/fixtures/` and
+//! records the arguments and prompt it was given, so turns run offline.
+//! Adapter tests include this file with `#[path]` and keep only their
+//! provider-specific setup and assertions.
+
+use std::path::{Path, PathBuf};
+
+use acp_lazy_core::config::{BridgeConfig, ConfigLoader};
+use acp_lazy_core::runtime::RuntimeServer;
+use agent_client_protocol::{
+    ContentBlock, Error, NewSessionRequest, PlanEntryStatus, PromptRequest, PromptResponse,
+    SessionId, SessionModeId, SessionNotification, SessionUpdate, SetSessionModeRequest,
+    ToolCallContent, ToolCallStatus, ToolKind,
+};
+use anyhow::Result;
+use tempfile::TempDir;
+use tokio::sync::mpsc;
+
+/// The including crate's `tests/fixtures` directory.
+pub fn fixtures_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures")
+}
+
+/// Path of the `fake-cli` script.
+pub fn fake_cli() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../acp-lazy-core/tests/support/fake-cli")
+}
+
+/// Scratch directory for one test: the session cwd, an empty user config
+/// and the file `fake-cli` records its input to.
+pub struct FakeCli {
+    dir: TempDir,
+}
+
+impl FakeCli {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            dir: tempfile::tempdir()?,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        self.dir.path()
+    }
+
+    pub fn record(&self) -> PathBuf {
+        self.dir().join("record.txt")
+    }
+
+    /// `fake-cli` arguments replaying `transcript` and recording to `record()`.
+    pub fn args(&self, transcript: &Path) -> Vec<String> {
+        vec![
+            "--fake-fixture".to_string(),
+            transcript.display().to_string(),
+            "--fake-record".to_string(),
+            self.record().display().to_string(),
+        ]
+    }
+
+    /// Loader applying `overrides`; the empty user config keeps
+    /// ~/.config/acplazybridge out of the test.
+    pub fn config_loader(&self, overrides: BridgeConfig) -> Result<ConfigLoader> {
+        let user_config = self.dir().join("config.toml");
+        std::fs::write(&user_config, "")?;
+        Ok(ConfigLoader::new()
+            .with_user_config(user_config)
+            .with_overrides(overrides))
+    }
+
+    /// Arguments and prompt of the last turn.
+    pub fn recorded(&self) -> Result<String> {
+        Ok(std::fs::read_to_string(self.record())?)
+    }
+
+    /// Open a session on `runtime` with the scratch directory as cwd.
+    pub async fn session(&self, runtime: RuntimeServer) -> Result<TestSession> {
+        let (tx, updates) = mpsc::unbounded_channel();
+        let runtime = runtime.with_notifier(Some(tx));
+        let session = runtime
+            .new_session(NewSessionRequest {
+                cwd: self.dir().to_path_buf(),
+                mcp_servers: Vec::new(),
+                meta: None,
+            })
+            .await?;
+        Ok(TestSession {
+            runtime,
+            id: session.session_id,
+            updates,
+        })
+    }
+}
+
+/// An open session and the updates it has streamed.
+pub struct TestSession {
+    runtime: RuntimeServer,
+    id: SessionId,
+    updates: mpsc::UnboundedReceiver<SessionNotification>,
+}
+
+impl TestSession {
+    pub async fn prompt(&self, text: &str) -> Result<PromptResponse, Error> {
+        self.runtime
+            .prompt(PromptRequest {
+                session_id: self.id.clone(),
+                prompt: vec![ContentBlock::from(text)],
+                meta: None,
+            })
+            .await
+    }
+
+    pub async fn set_mode(&self, mode_id: &str) -> Result<()> {
+        self.runtime
+            .set_session_mode(SetSessionModeRequest {
+                session_id: self.id.clone(),
+                mode_id: SessionModeId(mode_id.into()),
+                meta: None,
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Updates streamed since the last call.
+    pub fn drain(&mut self) -> Vec<SessionUpdate> {
+        let mut updates = Vec::new();
+        while let Ok(notification) = self.updates.try_recv() {
+            updates.push(notification.update);
+        }
+        updates
+    }
+}
+
+/// Check the updates of a `tool_use.jsonl` transcript.
+///
+/// The Claude and Gemini transcripts record the same turn: a two-entry plan,
+/// `ls src` in the shell (tool call `shell_id`), an edit of
+/// `/repo/src/main.rs`, a failed read of `/repo/missing.rs` and a closing
+/// message.
+#[allow(dead_code)]
+pub fn assert_tool_use_turn(updates: &[SessionUpdate], shell_id: &str) {
+    assert_eq!(updates.len(), 8, "{:#?}", updates);
+
+    let SessionUpdate::Plan(plan) = &updates[0] else {
+        panic!("expected a plan, got {:?}", updates[0]);
+    };
+    assert_eq!(plan.entries.len(), 2);
+    assert_eq!(plan.entries[0].status, PlanEntryStatus::InProgress);
+    assert_eq!(plan.entries[1].content, "Fix the greeting");
+
+    let SessionUpdate::ToolCall(shell) = &updates[1] else {
+        panic!("expected a tool call, got {:?}", updates[1]);
+    };
+    assert_eq!(shell.id.0.as_ref(), shell_id);
+    assert_eq!(shell.kind, ToolKind::Execute);
+    assert_eq!(shell.title, "Run ls src");
+    assert_eq!(shell.status, ToolCallStatus::InProgress);
+
+    let SessionUpdate::ToolCallUpdate(shell_done) = &updates[2] else {
+        panic!("expected a tool call update, got {:?}", updates[2]);
+    };
+    assert_eq!(shell_done.fields.status, Some(ToolCallStatus::Completed));
+    assert_eq!(
+        shell_done.fields.content,
+        Some(vec![ToolCallContent::from("main.rs")])
+    );
+
+    let SessionUpdate::ToolCall(edit) = &updates[3] else {
+        panic!("expected a tool call, got {:?}", updates[3]);
+    };
+    assert_eq!(edit.kind, ToolKind::Edit);
+    assert_eq!(edit.locations[0].path, PathBuf::from("/repo/src/main.rs"));
+    assert!(matches!(
+        &edit.content[..],
+        [ToolCallContent::Diff { diff }]
+            if diff.old_text.as_deref() == Some("println!(\"hi\");")
+                && diff.new_text == "println!(\"hello\");"
+    ));
+
+    // The diff stays on the tool call when the edit succeeds.
+    let SessionUpdate::ToolCallUpdate(edit_done) = &updates[4] else {
+        panic!("expected a tool call update, got {:?}", updates[4]);
+    };
+    assert_eq!(edit_done.fields.status, Some(ToolCallStatus::Completed));
+    assert_eq!(edit_done.fields.content, None);
+
+    let SessionUpdate::ToolCall(read) = &updates[5] else {
+        panic!("expected a tool call, got {:?}", updates[5]);
+    };
+    assert_eq!(read.kind, ToolKind::Read);
+    assert_eq!(read.locations[0].path, PathBuf::from("/repo/missing.rs"));
+
+    let SessionUpdate::ToolCallUpdate(read_failed) = &updates[6] else {
+        panic!("expected a tool call update, got {:?}", updates[6]);
+    };
+    assert_eq!(read_failed.fields.status, Some(ToolCallStatus::Failed));
+
+    assert!(matches!(
+        &updates[7],
+        SessionUpdate::AgentMessageChunk { content: ContentBlock::Text(text) }
+            if text.text == "Updated the greeting."
+    ));
+}
@@ -0,0 +1,195 @@
+use acp_lazy_core::config::{ConfigError, ConfigLoader};
+use acp_lazy_core::runtime::{
+    Conversation, ProviderModel, RuntimeServer, SessionState, TurnAdapter, TurnCli, TurnCommand,
+    TurnContext, TurnMapper,
+};
+use agent_client_protocol::{AgentCapabilities, Error, McpCapabilities, McpServer, SessionId};
+use serde_json::{json, Map, Value};
+
+use crate::claude_proto::ClaudeStreamMapper;
+use crate::permissions::map_acp_to_claude;
+
+/// Model aliases offered through `session/set_model`; the first entry is the default.
+const CLAUDE_MODELS: &[(&str, &str)] = &[
+    ("sonnet", "Latest Claude Sonnet"),
+    ("opus", "Latest Claude Opus"),
+    ("haiku", "Latest Claude Haiku"),
+];
+
+/// Claude Code run as one `claude -p` process per prompt.
+///
+/// Claude Code keeps conversation state on disk, so each turn streams its
+/// `stream-json` output and resumes the previous Claude session when there
+/// is one.
+pub struct ClaudeCli;
+
+impl TurnCli for ClaudeCli {
+    fn name(&self) -> &str {
+        "Claude"
+    }
+
+    fn agent_capabilities(&self) -> AgentCapabilities {
+        AgentCapabilities {
+            load_session: false,
+            prompt_capabilities: agent_client_protocol::PromptCapabilities {
+                image: false,
+                audio: false,
+                embedded_context: true,
+                meta: None,
+            },
+            mcp_capabilities: McpCapabilities {
+                http: true,
+                sse: true,
+                meta: None,
+            },
+            meta: None,
+        }
+    }
+
+    fn available_models(&self) -> Vec<ProviderModel> {
+        CLAUDE_MODELS
+            .iter()
+            .map(|(alias, description)| ProviderModel {
+                id: alias.to_string(),
+                name: format!("Claude {}", alias),
+                description: Some(description.to_string()),
+            })
+            .collect()
+    }
+
+    fn turn_command(&self, turn: TurnContext<'_>) -> Result<TurnCommand, Error> {
+        let mut args = turn_args(turn.session, turn.conversation);
+        args.extend(turn.bridge.claude.args.iter().flatten().cloned());
+        Ok(TurnCommand {
+            program: turn.bridge.claude_command().to_string(),
+            args,
+            stdin: turn.prompt,
+        })
+    }
+
+    fn mapper(&self, session_id: SessionId) -> Box<dyn TurnMapper + '_> {
+        Box::new(ClaudeStreamMapper::new(session_id))
+    }
+}
+
+/// Runtime serving Claude Code over ACP.
+pub fn runtime(config_loader: ConfigLoader) -> Result<RuntimeServer, ConfigError> {
+    TurnAdapter::runtime(ClaudeCli, config_loader)
+}
+
+/// Arguments for one turn, before the `[claude].args` from config.
+fn turn_args(session: &SessionState, conversation: &Conversation) -> Vec<String> {
+    let mut args = vec![
+        "-p".to_string(),
+        "--output-format".to_string(),
+        "stream-json".to_string(),
+        "--verbose".to_string(),
+    ];
+    args.extend(map_acp_to_claude(session.permission_mode));
+    if let Some(model) = session.model.as_deref() {
+        args.push("--model".into());
+        args.push(model.to_string());
+    }
+    if let Some(id) = conversation.resume_id.as_deref() {
+        args.push("--resume".into());
+        args.push(id.to_string());
+    }
+    if let Some(mcp_config) = claude_mcp_config(&session.mcp_servers) {
+        args.push("--mcp-config".into());
+        args.push(mcp_config.to_string());
+    }
+    args
+}
+
+/// Claude `--mcp-config` JSON for the client-provided MCP servers.
+fn claude_mcp_config(servers: &[McpServer]) -> Option<Value> {
+    if servers.is_empty() {
+        return None;
+    }
+    let mut config = Map::new();
+    for server in servers {
+        let (name, entry) = match server {
+            McpServer::Stdio {
+                name,
+                command,
+                args,
+                env,
+            } => {
+                let env: Map<String, Value> = env
+                    .iter()
+                    .map(|var| (var.name.clone(), Value::String(var.value.clone())))
+                    .collect();
+                (
+                    name,
+                    json!({
+                        "command": command.to_string_lossy(),
+                        "args": args,
+                        "env": env,
+                    }),
+                )
+            }
+            McpServer::Http { name, url, headers } | McpServer::Sse { name, url, headers } => {
+                let transport = if matches!(server, McpServer::Http { .. }) {
+                    "http"
+                } else {
+                    "sse"
+                };
+                let headers: Map<String, Value> = headers
+                    .iter()
+                    .map(|h| (h.name.clone(), Value::String(h.value.clone())))
+                    .collect();
+                (
+                    name,
+                    json!({
+                        "type": transport,
+                        "url": url,
+                        "headers": headers,
+                    }),
+                )
+            }
+        };
+        config.insert(name.clone(), entry);
+    }
+    Some(json!({ "mcpServers": config }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mcp_servers_become_claude_mcp_config() {
+        let servers = vec![
+            McpServer::Stdio {
+                name: "files".to_string(),
+                command: "/usr/bin/mcp-files".into(),
+                args: vec!["--root".to_string(), "/repo".to_string()],
+                env: Vec::new(),
+            },
+            McpServer::Http {
+                name: "docs".to_string(),
+                url: "https://example.com/mcp".to_string(),
+                headers: Vec::new(),
+            },
+        ];
+
+        assert_eq!(claude_mcp_config(&[]), None);
+        assert_eq!(
+            claude_mcp_config(&servers),
+            Some(json!({
+                "mcpServers": {
+                    "files": {
+                        "command": "/usr/bin/mcp-files",
+                        "args": ["--root", "/repo"],
+                        "env": {},
+                    },
+                    "docs": {
+                        "type": "http",
+                        "url": "https://example.com/mcp",
+                        "headers": {},
+                    },
+                }
+            }))
+        );
+    }
+}
@@ -0,0 +1,525 @@
+//! Claude Code `stream-json` event handling.
+//!
+//! `claude -p --output-format stream-json --verbose` prints one JSON object
+//! per line. This module maps them onto ACP `SessionNotification`s:
+//!
+//! - `system` (`init`) records the Claude session id and model.
+//! - `assistant` messages: `text` blocks become agent message chunks,
+//!   `thinking` blocks thought chunks and `tool_use` blocks tool calls. File
+//!   edits carry `ToolCallContent::Diff` entries, and `TodoWrite` is shown as
+//!   an ACP plan rather than a tool call.
+//! - `user` messages carry `tool_result` blocks that complete or fail the
+//!   matching tool call.
+//! - `result` ends the turn with its stop reason, usage and cost.
+//!
+//! Unknown event and content types are ignored so newer CLI versions keep
+//! working.
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use acp_lazy_core::runtime::TurnMapper;
+use agent_client_protocol::{
+    ContentBlock, Diff, Error, Plan, PlanEntry, PlanEntryPriority, PlanEntryStatus, PromptResponse,
+    SessionId, SessionNotification, SessionUpdate, StopReason, ToolCall, ToolCallContent,
+    ToolCallId, ToolCallLocation, ToolCallStatus, ToolCallUpdate, ToolCallUpdateFields, ToolKind,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::{debug, warn};
+
+/// Claude's task-list tool, presented as an ACP plan.
+const TODO_WRITE_TOOL: &str = "TodoWrite";
+
+/// One line of `stream-json` output.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClaudeEvent {
+    System(ClaudeSystemEvent),
+    Assistant(ClaudeMessageEvent),
+    User(ClaudeMessageEvent),
+    Result(ClaudeResultEvent),
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ClaudeSystemEvent {
+    #[serde(default)]
+    pub subtype: String,
+    #[serde(default)]
+    pub session_id: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ClaudeMessageEvent {
+    #[serde(default)]
+    pub message: ClaudeMessage,
+    #[serde(default)]
+    pub session_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ClaudeMessage {
+    /// A plain string or a list of content blocks.
+    #[serde(default)]
+    pub content: Value,
+}
+
+/// Content block inside an assistant or user message.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClaudeContent {
+    Text {
+        text: String,
+    },
+    Thinking {
+        thinking: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        #[serde(default)]
+        input: Value,
+    },
+    ToolResult {
+        tool_use_id: String,
+        #[serde(default)]
+        content: Value,
+        #[serde(default)]
+        is_error: bool,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ClaudeResultEvent {
+    #[serde(default)]
+    pub subtype: String,
+    #[serde(default)]
+    pub is_error: bool,
+    #[serde(default)]
+    pub result: Option<String>,
+    #[serde(default)]
+    pub session_id: Option<String>,
+    #[serde(default)]
+    pub usage: Option<Value>,
+    #[serde(default)]
+    pub total_cost_usd: Option<f64>,
+    #[serde(default)]
+    pub num_turns: Option<u64>,
+}
+
+impl ClaudeResultEvent {
+    /// ACP stop reason for the turn, or the error Claude reported.
+    pub fn stop_reason(&self) -> Result<StopReason, Error> {
+        if self.subtype == "error_max_turns" {
+            return Ok(StopReason::MaxTurnRequests);
+        }
+        if self.is_error || self.subtype != "success" {
+            let message = self
+                .result
+                .clone()
+                .unwrap_or_else(|| format!("Claude turn failed ({})", self.subtype));
+            return Err(Error::internal_error().with_data(message));
+        }
+        Ok(StopReason::EndTurn)
+    }
+
+    /// `PromptResponse` meta carrying usage and cost.
+    pub fn meta(&self) -> Option<Value> {
+        if self.usage.is_none() && self.total_cost_usd.is_none() {
+            return None;
+        }
+        Some(json!({
+            "usage": self.usage,
+            "costUsd": self.total_cost_usd,
+            "numTurns": self.num_turns,
+        }))
+    }
+}
+
+/// Tool use awaiting its result.
+#[derive(Debug, Clone)]
+struct PendingTool {
+    /// Diff content stays on completion; ACP replaces content wholesale.
+    has_diff: bool,
+}
+
+/// Stateful mapper from Claude events to ACP notifications for one turn.
+pub struct ClaudeStreamMapper {
+    session_id: SessionId,
+    claude_session_id: Option<String>,
+    model: Option<String>,
+    tools: HashMap<String, PendingTool>,
+    plan_tools: HashSet<String>,
+    result: Option<ClaudeResultEvent>,
+}
+
+impl ClaudeStreamMapper {
+    pub fn new(session_id: SessionId) -> Self {
+        Self {
+            session_id,
+            claude_session_id: None,
+            model: None,
+            tools: HashMap::new(),
+            plan_tools: HashSet::new(),
+            result: None,
+        }
+    }
+
+    pub fn process_event(&mut self, event: ClaudeEvent) -> Vec<SessionNotification> {
+        let updates = match event {
+            ClaudeEvent::System(event) => {
+                if event.subtype == "init" {
+                    self.record_session_id(event.session_id);
+                    self.model = event.model.or(self.model.take());
+                }
+                Vec::new()
+            }
+            ClaudeEvent::Assistant(event) => {
+                self.record_session_id(event.session_id);
+                content_blocks(&event.message.content)
+                    .into_iter()
+                    .filter_map(|block| self.assistant_update(block))
+                    .collect()
+            }
+            ClaudeEvent::User(event) => content_blocks(&event.message.content)
+                .into_iter()
+                .filter_map(|block| self.tool_result_update(block))
+                .collect(),
+            ClaudeEvent::Result(event) => {
+                self.record_session_id(event.session_id.clone());
+                self.result = Some(event);
+                Vec::new()
+            }
+            ClaudeEvent::Unknown => Vec::new(),
+        };
+
+        updates
+            .into_iter()
+            .map(|update| SessionNotification {
+                session_id: self.session_id.clone(),
+                update,
+                meta: None,
+            })
+            .collect()
+    }
+
+    fn record_session_id(&mut self, session_id: Option<String>) {
+        if let Some(id) = session_id {
+            self.claude_session_id = Some(id);
+        }
+    }
+
+    fn assistant_update(&mut self, block: ClaudeContent) -> Option<SessionUpdate> {
+        match block {
+            ClaudeContent::Text { text } if !text.is_empty() => {
+                Some(SessionUpdate::AgentMessageChunk {
+                    content: ContentBlock::from(text),
+                })
+            }
+            ClaudeContent::Thinking { thinking } if !thinking.is_empty() => {
+                Some(SessionUpdate::AgentThoughtChunk {
+                    content: ContentBlock::from(thinking),
+                })
+            }
+            ClaudeContent::ToolUse { id, name, input } => {
+                if name == TODO_WRITE_TOOL {
+                    self.plan_tools.insert(id);
+                    return Some(SessionUpdate::Plan(plan_from_todos(&input)));
+                }
+                let tool_call = tool_call_from_use(&id, &name, input);
+                let has_diff = tool_call
+                    .content
+                    .iter()
+                    .any(|content| matches!(content, ToolCallContent::Diff { .. }));
+                self.tools.insert(id, PendingTool { has_diff });
+                Some(SessionUpdate::ToolCall(tool_call))
+            }
+            _ => None,
+        }
+    }
+
+    fn tool_result_update(&mut self, block: ClaudeContent) -> Option<SessionUpdate> {
+        let ClaudeContent::ToolResult {
+            tool_use_id,
+            content,
+            is_error,
+        } = block
+        else {
+            return None;
+        };
+        if self.plan_tools.remove(&tool_use_id) {
+            return None;
+        }
+
+        let has_diff = match self.tools.remove(&tool_use_id) {
+            Some(pending) => pending.has_diff,
+            None => {
+                debug!("tool_result for unknown tool use {}", tool_use_id);
+                false
+            }
+        };
+        let text = tool_result_text(&content);
+        let raw_output = (!content.is_null()).then(|| json!({ "content": content }));
+        let content = if (has_diff && !is_error) || text.is_empty() {
+            None
+        } else {
+            Some(vec![ToolCallContent::from(text)])
+        };
+
+        Some(SessionUpdate::ToolCallUpdate(ToolCallUpdate {
+            id: ToolCallId(Arc::from(tool_use_id.as_str())),
+            fields: ToolCallUpdateFields {
+                status: Some(if is_error {
+                    ToolCallStatus::Failed
+                } else {
+                    ToolCallStatus::Completed
+                }),
+                content,
+                raw_output,
+                ..Default::default()
+            },
+            meta: None,
+        }))
+    }
+}
+
+impl TurnMapper for ClaudeStreamMapper {
+    fn process_line(&mut self, line: &str) -> Vec<SessionNotification> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Vec::new();
+        }
+        match serde_json::from_str::<ClaudeEvent>(line) {
+            Ok(event) => self.process_event(event),
+            Err(err) => {
+                warn!("Ignoring malformed Claude output line: {} ({})", line, err);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Claude's own session id, used with `--resume` on the next turn.
+    fn resume_id(&self) -> Option<&str> {
+        self.claude_session_id.as_deref()
+    }
+
+    /// Model reported by the `init` event.
+    fn model(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+
+    /// Stop reason, usage and cost from the `result` event.
+    fn response(&self) -> Option<Result<PromptResponse, Error>> {
+        self.result.as_ref().map(|result| {
+            Ok(PromptResponse {
+                stop_reason: result.stop_reason()?,
+                meta: result.meta(),
+            })
+        })
+    }
+}
+
+/// Message content as blocks; a bare string is treated as one text block.
+fn content_blocks(content: &Value) -> Vec<ClaudeContent> {
+    match content {
+        Value::String(text) => vec![ClaudeContent::Text { text: text.clone() }],
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter_map(|block| serde_json::from_value(block.clone()).ok())
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Text of a `tool_result` content field (a string or a list of text blocks).
+fn tool_result_text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter_map(|block| block.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn str_field<'a>(input: &'a Value, key: &str) -> Option<&'a str> {
+    input.get(key).and_then(Value::as_str)
+}
+
+fn location(path: &str) -> ToolCallLocation {
+    ToolCallLocation {
+        path: PathBuf::from(path),
+        line: None,
+        meta: None,
+    }
+}
+
+fn diff(path: &str, old_text: Option<&str>, new_text: &str) -> ToolCallContent {
+    ToolCallContent::from(Diff {
+        path: PathBuf::from(path),
+        old_text: old_text.map(str::to_string),
+        new_text: new_text.to_string(),
+        meta: None,
+    })
+}
+
+/// Build the ACP tool call for a Claude `tool_use` block.
+pub fn tool_call_from_use(id: &str, name: &str, input: Value) -> ToolCall {
+    let mut content = Vec::new();
+    let mut locations = Vec::new();
+    let file_path = str_field(&input, "file_path").or_else(|| str_field(&input, "notebook_path"));
+
+    let (kind, title) = match name {
+        "Bash" => {
+            let command = str_field(&input, "command").unwrap_or_default();
+            (ToolKind::Execute, format!("Run {}", command))
+        }
+        "Read" | "NotebookRead" => {
+            let path = file_path.unwrap_or_default();
+            if !path.is_empty() {
+                locations.push(location(path));
+            }
+            (ToolKind::Read, format!("Read {}", path))
+        }
+        "Write" | "Edit" | "MultiEdit" | "NotebookEdit" => {
+            let path = file_path.unwrap_or_default();
+            if !path.is_empty() {
+                locations.push(location(path));
+                match name {
+                    "Write" => {
+                        let new_text = str_field(&input, "content").unwrap_or_default();
+                        content.push(diff(path, None, new_text));
+                    }
+                    "Edit" => {
+                        let old_text = str_field(&input, "old_string");
+                        let new_text = str_field(&input, "new_string").unwrap_or_default();
+                        content.push(diff(path, old_text, new_text));
+                    }
+                    "MultiEdit" => {
+                        let edits = input.get("edits").and_then(Value::as_array);
+                        for edit in edits.into_iter().flatten() {
+                            let old_text = str_field(edit, "old_string");
+                            let new_text = str_field(edit, "new_string").unwrap_or_default();
+                            content.push(diff(path, old_text, new_text));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            (ToolKind::Edit, format!("Edit {}", path))
+        }
+        "Glob" | "Grep" | "LS" => {
+            let pattern = str_field(&input, "pattern")
+                .or_else(|| str_field(&input, "path"))
+                .unwrap_or_default();
+            (ToolKind::Search, format!("{} {}", name, pattern))
+        }
+        "WebFetch" => {
+            let url = str_field(&input, "url").unwrap_or_default();
+            (ToolKind::Fetch, format!("Fetch {}", url))
+        }
+        "WebSearch" => {
+            let query = str_field(&input, "query").unwrap_or_default();
+            (ToolKind::Fetch, format!("Search the web for {}", query))
+        }
+        "Task" => {
+            let description = str_field(&input, "description").unwrap_or(name);
+            (ToolKind::Think, description.to_string())
+        }
+        _ => (ToolKind::Other, name.to_string()),
+    };
+
+    ToolCall {
+        id: ToolCallId(Arc::from(id)),
+        title: title.trim_end().to_string(),
+        kind,
+        status: ToolCallStatus::InProgress,
+        content,
+        locations,
+        raw_input: Some(input),
+        raw_output: None,
+        meta: None,
+    }
+}
+
+/// Convert `TodoWrite` input (`{"todos": [{content, status, priority}]}`) to a plan.
+pub fn plan_from_todos(input: &Value) -> Plan {
+    let todos = input.get("todos").and_then(Value::as_array);
+    let entries = todos
+        .into_iter()
+        .flatten()
+        .filter_map(|todo| {
+            let content = str_field(todo, "content")?.to_string();
+            let status = match str_field(todo, "status") {
+                Some("in_progress") => PlanEntryStatus::InProgress,
+                Some("completed") => PlanEntryStatus::Completed,
+                _ => PlanEntryStatus::Pending,
+            };
+            let priority = match str_field(todo, "priority") {
+                Some("high") => PlanEntryPriority::High,
+                Some("low") => PlanEntryPriority::Low,
+                _ => PlanEntryPriority::Medium,
+            };
+            Some(PlanEntry {
+                content,
+                priority,
+                status,
+                meta: None,
+            })
+        })
+        .collect();
+    Plan {
+        entries,
+        meta: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(subtype: &str, is_error: bool) -> ClaudeResultEvent {
+        ClaudeResultEvent {
+            subtype: subtype.to_string(),
+            is_error,
+            result: Some("boom".to_string()),
+            ..ClaudeResultEvent::default()
+        }
+    }
+
+    #[test]
+    fn result_subtypes_map_to_stop_reasons() {
+        assert_eq!(
+            result("success", false).stop_reason(),
+            Ok(StopReason::EndTurn)
+        );
+        assert_eq!(
+            result("error_max_turns", true).stop_reason(),
+            Ok(StopReason::MaxTurnRequests)
+        );
+        // ast-grep-ignore: rust-no-unwrap
+        let err = result("error_during_execution", true)
+            .stop_reason()
+            .unwrap_err();
+        assert_eq!(err.data, Some(json!("boom")));
+    }
+
+    #[test]
+    fn unknown_events_and_malformed_lines_are_ignored() {
+        let mut mapper = ClaudeStreamMapper::new(SessionId(Arc::from("s")));
+        assert!(mapper.process_line(r#"{"type":"stream_event"}"#).is_empty());
+        assert!(mapper.process_line("not json").is_empty());
+        let updates =
+            mapper.process_line(r#"{"type":"assistant","message":{"content":"plain text"}}"#);
+        assert_eq!(updates.len(), 1);
+    }
+}
@@ -0,0 +1,5 @@
+//! Library interface for claude-cli-acp
+
+pub mod claude_agent;
+pub mod claude_proto;
+pub mod permissions;
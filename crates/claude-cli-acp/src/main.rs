@@ -0,0 +1,11 @@
+use acp_lazy_core::config::ConfigLoader;
+use acp_lazy_core::logging;
+use acp_lazy_core::runtime::serve_stdio;
+use anyhow::Result;
+use claude_cli_acp::claude_agent;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+    logging::init();
+    serve_stdio(claude_agent::runtime(ConfigLoader::new())?).await
+}
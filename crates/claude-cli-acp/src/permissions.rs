@@ -0,0 +1,65 @@
+//! Permission mapping between ACP modes and Claude Code CLI flags.
+//!
+//! Claude runs non-interactively under `-p`, so any tool that would need an
+//! approval prompt is denied. The mode therefore decides up front what the
+//! turn may do.
+
+use acp_lazy_core::permissions::AcpPermissionMode;
+
+/// Claude `--permission-mode` value for an ACP mode.
+pub fn claude_permission_mode(mode: AcpPermissionMode) -> &'static str {
+    match mode {
+        AcpPermissionMode::Default => "default",
+        AcpPermissionMode::Plan => "plan",
+        AcpPermissionMode::AcceptEdits => "acceptEdits",
+        AcpPermissionMode::BypassPermissions | AcpPermissionMode::Yolo => "bypassPermissions",
+    }
+}
+
+/// Map an ACP permission mode to Claude CLI arguments.
+///
+/// Claude has no sandbox, so YOLO differs from bypass mode only by also
+/// skipping the permission checks Claude keeps in bypass mode.
+pub fn map_acp_to_claude(mode: AcpPermissionMode) -> Vec<String> {
+    let mut args = vec![
+        "--permission-mode".to_string(),
+        claude_permission_mode(mode).to_string(),
+    ];
+    if mode == AcpPermissionMode::Yolo {
+        args.push("--dangerously-skip-permissions".to_string());
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modes_map_to_permission_flags() {
+        assert_eq!(
+            map_acp_to_claude(AcpPermissionMode::Default),
+            vec!["--permission-mode", "default"]
+        );
+        assert_eq!(
+            map_acp_to_claude(AcpPermissionMode::Plan),
+            vec!["--permission-mode", "plan"]
+        );
+        assert_eq!(
+            map_acp_to_claude(AcpPermissionMode::AcceptEdits),
+            vec!["--permission-mode", "acceptEdits"]
+        );
+        assert_eq!(
+            map_acp_to_claude(AcpPermissionMode::BypassPermissions),
+            vec!["--permission-mode", "bypassPermissions"]
+        );
+        assert_eq!(
+            map_acp_to_claude(AcpPermissionMode::Yolo),
+            vec![
+                "--permission-mode",
+                "bypassPermissions",
+                "--dangerously-skip-permissions"
+            ]
+        );
+    }
+}
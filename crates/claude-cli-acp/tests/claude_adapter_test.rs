@@ -0,0 +1,103 @@
+//! Claude adapter tests against a scripted stand-in for the Claude Code CLI.
+//!
+//! The shared `fake-cli` replays a recorded `stream-json` transcript from
+//! `tests/fixtures/claude/`; see `support/cli_adapter.rs`.
+
+#[path = "../../acp-lazy-core/tests/support/cli_adapter.rs"]
+mod support;
+
+use acp_lazy_core::config::{BridgeConfig, ClaudeSection};
+use agent_client_protocol::{ContentBlock, SessionUpdate, StopReason};
+use anyhow::Result;
+use claude_cli_acp::claude_agent;
+use support::{assert_tool_use_turn, fake_cli, fixtures_dir, FakeCli, TestSession};
+
+/// Session whose Claude command replays `transcript`.
+async fn fake_claude(cli: &FakeCli, transcript: &str) -> Result<TestSession> {
+    let overrides = BridgeConfig {
+        claude: ClaudeSection {
+            command: Some(fake_cli().display().to_string()),
+            args: Some(cli.args(&fixtures_dir().join("claude").join(transcript))),
+        },
+        ..BridgeConfig::default()
+    };
+    cli.session(claude_agent::runtime(cli.config_loader(overrides)?)?)
+        .await
+}
+
+#[tokio::test]
+async fn text_and_thinking_stream_as_chunks() -> Result<()> {
+    let cli = FakeCli::new()?;
+    let mut session = fake_claude(&cli, "hello.jsonl").await?;
+
+    let response = session.prompt("Say hello").await?;
+
+    assert_eq!(response.stop_reason, StopReason::EndTurn);
+    // ast-grep-ignore: rust-no-unwrap
+    let meta = response.meta.unwrap();
+    assert_eq!(meta["usage"]["output_tokens"], 5);
+    assert_eq!(meta["costUsd"], 0.0042);
+
+    let updates = session.drain();
+    assert_eq!(updates.len(), 2);
+    assert!(matches!(
+        &updates[0],
+        SessionUpdate::AgentThoughtChunk { content: ContentBlock::Text(text) }
+            if text.text == "The user wants a greeting."
+    ));
+    assert!(matches!(
+        &updates[1],
+        SessionUpdate::AgentMessageChunk { content: ContentBlock::Text(text) }
+            if text.text == "Hello from Claude!"
+    ));
+
+    let recorded = cli.recorded()?;
+    assert!(recorded.contains("-p --output-format stream-json --verbose"));
+    assert!(recorded.contains("--permission-mode default"));
+    assert!(!recorded.contains("--resume"));
+    assert!(recorded.contains("PROMPT: Say hello"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn later_turns_resume_the_claude_session_with_the_current_mode() -> Result<()> {
+    let cli = FakeCli::new()?;
+    let session = fake_claude(&cli, "hello.jsonl").await?;
+
+    session.prompt("first").await?;
+    session.set_mode("acceptEdits").await?;
+    session.prompt("second").await?;
+
+    let recorded = cli.recorded()?;
+    assert!(recorded.contains("--permission-mode acceptEdits"));
+    assert!(recorded.contains("--resume claude-session-1"));
+    assert!(recorded.contains("PROMPT: second"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn tool_use_maps_to_tool_calls_and_plans() -> Result<()> {
+    let cli = FakeCli::new()?;
+    let mut session = fake_claude(&cli, "tool_use.jsonl").await?;
+
+    let response = session.prompt("Fix it").await?;
+    assert_eq!(response.stop_reason, StopReason::EndTurn);
+    assert_tool_use_turn(&session.drain(), "toolu_bash");
+    Ok(())
+}
+
+#[tokio::test]
+async fn error_results_fail_the_prompt() -> Result<()> {
+    let cli = FakeCli::new()?;
+    let session = fake_claude(&cli, "error.jsonl").await?;
+
+    let err = session
+        .prompt("hello")
+        .await
+        .expect_err("error result should fail the prompt");
+    assert_eq!(
+        err.data,
+        Some(serde_json::json!("Credit balance is too low"))
+    );
+    Ok(())
+}
@@ -0,0 +1,146 @@
+//! Command-line interface for the `codex-cli-acp` binary.
+//!
+//! Flags form the highest-precedence configuration layer, above the config
+//! file and environment, so an editor's agent settings can be self-contained.
+
+use std::path::PathBuf;
+
+use acp_lazy_core::config::{BridgeConfig, CodexSection, ConfigLoader, RuntimeSection};
+use acp_lazy_core::logging::LogFormat;
+use acp_lazy_core::permissions::AcpPermissionMode;
+use clap::{Parser, Subcommand, ValueEnum};
+
+/// ACP agent server bridging Codex CLI to ACP clients such as Zed.
+#[derive(Debug, Parser)]
+#[command(name = "codex-cli-acp", version)]
+pub struct Cli {
+    /// Configuration file used instead of ~/.config/acplazybridge/config.toml.
+    #[arg(long, value_name = "PATH", global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Codex executable to launch.
+    #[arg(long, value_name = "PATH", global = true)]
+    pub codex_path: Option<String>,
+
+    /// Log line format.
+    #[arg(long, value_enum, default_value_t = LogFormatArg::Text)]
+    pub log_format: LogFormatArg,
+
+    /// Append logs to this file instead of stderr.
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Write runtime evidence events (JSONL) to this file.
+    #[arg(long, value_name = "PATH")]
+    pub evidence_path: Option<PathBuf>,
+
+    /// Permission mode for new sessions (default, plan, acceptEdits, bypassPermissions, yolo).
+    #[arg(long, value_name = "MODE", value_parser = parse_mode)]
+    pub default_mode: Option<AcpPermissionMode>,
+
+    /// Idle timeout for a Codex turn, in milliseconds.
+    #[arg(long, value_name = "MS")]
+    pub idle_timeout: Option<u64>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Check the Codex binary, notify forwarder and notify path, then exit.
+    Doctor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormatArg {
+    Text,
+    Json,
+}
+
+impl From<LogFormatArg> for LogFormat {
+    fn from(format: LogFormatArg) -> Self {
+        match format {
+            LogFormatArg::Text => LogFormat::Text,
+            LogFormatArg::Json => LogFormat::Json,
+        }
+    }
+}
+
+impl Cli {
+    /// Configuration layer built from the flags.
+    pub fn config_overrides(&self) -> BridgeConfig {
+        BridgeConfig {
+            runtime: RuntimeSection {
+                idle_timeout_ms: self.idle_timeout,
+                evidence_path: self.evidence_path.clone(),
+                default_mode: self.default_mode,
+                ..RuntimeSection::default()
+            },
+            codex: CodexSection {
+                command: self.codex_path.clone(),
+                ..CodexSection::default()
+            },
+            ..BridgeConfig::default()
+        }
+    }
+
+    /// Loader applying `--config` and the flag overrides.
+    pub fn config_loader(&self) -> ConfigLoader {
+        let loader = ConfigLoader::new().with_overrides(self.config_overrides());
+        match &self.config {
+            Some(path) => loader.with_user_config(path),
+            None => loader,
+        }
+    }
+}
+
+fn parse_mode(value: &str) -> Result<AcpPermissionMode, String> {
+    value
+        .parse()
+        .map_err(|()| format!("unknown permission mode `{}`", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_become_the_top_config_layer() {
+        // ast-grep-ignore: rust-no-unwrap
+        let cli = Cli::try_parse_from([
+            "codex-cli-acp",
+            "--codex-path",
+            "/opt/codex",
+            "--default-mode",
+            "accept-edits",
+            "--idle-timeout",
+            "5000",
+            "--log-format",
+            "json",
+        ])
+        .unwrap();
+        assert_eq!(cli.log_format, LogFormatArg::Json);
+        assert!(cli.command.is_none());
+
+        let overrides = cli.config_overrides();
+        assert_eq!(overrides.codex.command.as_deref(), Some("/opt/codex"));
+        assert_eq!(
+            overrides.runtime.default_mode,
+            Some(AcpPermissionMode::AcceptEdits)
+        );
+        assert_eq!(overrides.runtime.idle_timeout_ms, Some(5000));
+        assert_eq!(overrides.runtime.evidence_path, None);
+    }
+
+    #[test]
+    fn doctor_subcommand_and_invalid_mode() {
+        // ast-grep-ignore: rust-no-unwrap
+        let cli =
+            Cli::try_parse_from(["codex-cli-acp", "doctor", "--config", "/tmp/x.toml"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Doctor)));
+        assert_eq!(cli.config, Some(PathBuf::from("/tmp/x.toml")));
+
+        assert!(Cli::try_parse_from(["codex-cli-acp", "--default-mode", "turbo"]).is_err());
+    }
+}
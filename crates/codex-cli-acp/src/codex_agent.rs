@@ -1,57 +1,123 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 
-use acp_lazy_core::permissions::map_acp_to_codex;
+use acp_lazy_core::config::{BridgeConfig, ConfigError, ConfigLoader};
+use acp_lazy_core::permissions::PermissionOverrides;
 use acp_lazy_core::runtime::{
-    ProviderAdapter, RuntimeConfig, RuntimeServer, SessionNotifier, SessionState,
+    file_uri_to_path, forward_update, resource_mention, ClientFs, ClientHandle, ClientTerminals,
+    ProviderAdapter, ProviderModel, RuntimeConfig, RuntimeServer, SessionNotifier, SessionState,
 };
 use acp_lazy_core::transport::{write_line, ProcessTransport};
 use agent_client_protocol::{
-    Agent, AgentCapabilities, AuthenticateRequest, AuthenticateResponse, CancelNotification,
-    ContentBlock, Error, ExtNotification, ExtRequest, ExtResponse, InitializeRequest,
-    InitializeResponse, LoadSessionRequest, LoadSessionResponse, NewSessionRequest,
-    NewSessionResponse, PromptRequest, PromptResponse, SessionId, SessionNotification,
-    SessionUpdate, StopReason,
+    Agent, AgentCapabilities, AuthMethod, AuthMethodId, AuthenticateRequest, AuthenticateResponse,
+    CancelNotification, ContentBlock, EmbeddedResource, EmbeddedResourceResource, Error,
+    ExtNotification, ExtRequest, ExtResponse, InitializeRequest, InitializeResponse,
+    LoadSessionRequest, LoadSessionResponse, McpCapabilities, McpServer, NewSessionRequest,
+    NewSessionResponse, PermissionOption, PermissionOptionId, PermissionOptionKind, PromptRequest,
+    PromptResponse, RawValue, RequestPermissionOutcome, RequestPermissionRequest, SessionId,
+    SessionNotification, SessionUpdate, StopReason, TextResourceContents,
 };
 use anyhow::Error as AnyhowError;
 use async_trait::async_trait;
 use serde_json::{json, Value};
-use tokio::sync::{mpsc, Mutex, Notify, RwLock};
-use tokio::task::JoinSet;
+use tokio::sync::{mpsc, watch, Mutex, Notify, RwLock};
+use tokio::task::{JoinHandle, JoinSet};
 use tokio::time::{self, Duration, Instant};
 use tracing::{debug, warn};
 
-use crate::codex_proto;
+use crate::codex_proto::{
+    self, CodexApprovalRequest, CodexReviewDecision, CodexTurnCompletion, CodexTurnUsage,
+};
 use crate::notify_source::{create_notify_source, NotifyEvent};
 use uuid::Uuid;
 
+/// Permission option identifiers offered for Codex approval requests.
+const ALLOW_ONCE_OPTION: &str = "allow-once";
+const ALLOW_ALWAYS_OPTION: &str = "allow-always";
+const REJECT_OPTION: &str = "reject";
+
+/// Authentication method identifiers advertised in `initialize`.
+const API_KEY_AUTH_METHOD: &str = "openai-api-key";
+const CHATGPT_AUTH_METHOD: &str = "chatgpt";
+
+/// Upper bound on `codex login status`.
+const LOGIN_STATUS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Extension notification carrying per-turn token usage (sent as `_acplb/usage`).
+const USAGE_NOTIFICATION: &str = "acplb/usage";
+
+/// How long a turn ended by notify waits for Codex's `task_complete` after its last event.
+const NOTIFY_DRAIN_GRACE: Duration = Duration::from_secs(2);
+
+/// Largest editor buffer inlined into a submission; bigger files stay `@path` mentions.
+const MAX_STAGED_BUFFER_BYTES: usize = 256 * 1024;
+
 #[derive(Default)]
 struct CodexProviderAdapter {
     processes: Arc<RwLock<HashMap<String, Arc<ProcessEntry>>>>,
+    /// Set by `RuntimeServer::with_client`; approvals are denied without it.
+    client: OnceLock<ClientHandle>,
+    /// Re-resolved per spawn so a project `.acplb.toml` in the session cwd applies.
+    config_loader: ConfigLoader,
+    /// Set once `codex login status` confirms a login kept outside `auth.json`.
+    login_verified: AtomicBool,
 }
 
+/// Session-scoped Codex process.
+///
+/// The transport and stdout reader live for the whole session; each prompt
+/// borrows the turn receivers, submits a `user_input` op and waits for the
+/// matching `task_complete`.
 struct ProcessEntry {
     transport: Mutex<Option<ProcessTransport>>,
     cancelled: AtomicBool,
     cancel_notify: Notify,
     notify_source: Mutex<Option<Box<dyn crate::notify_source::NotifySource + Send>>>,
+    /// Receivers drained by the active prompt; holding the lock serializes turns.
+    turn: Mutex<TurnChannels>,
+    reader: JoinHandle<()>,
+    /// Model id the process was spawned with (`None` uses the Codex config default).
+    selected_model: Option<String>,
+    /// Model Codex reports in `session_configured`.
+    reported_model: watch::Receiver<Option<String>>,
+}
+
+struct TurnChannels {
+    updates: mpsc::UnboundedReceiver<SessionNotification>,
+    completions: mpsc::UnboundedReceiver<CodexTurnCompletion>,
+    approvals: mpsc::UnboundedReceiver<CodexApprovalRequest>,
+    notify: Option<mpsc::UnboundedReceiver<NotifyEvent>>,
 }
 
 impl ProcessEntry {
-    fn new() -> Self {
+    fn new(
+        transport: ProcessTransport,
+        turn: TurnChannels,
+        reader: JoinHandle<()>,
+        selected_model: Option<String>,
+        reported_model: watch::Receiver<Option<String>>,
+    ) -> Self {
         Self {
-            transport: Mutex::new(None),
+            transport: Mutex::new(Some(transport)),
             cancelled: AtomicBool::new(false),
             cancel_notify: Notify::new(),
             notify_source: Mutex::new(None),
+            turn: Mutex::new(turn),
+            reader,
+            selected_model,
+            reported_model,
         }
     }
 
-    async fn store_transport(&self, transport: ProcessTransport) {
-        let mut guard = self.transport.lock().await;
-        *guard = Some(transport);
+    /// Model id in use, preferring the selected id when Codex confirms its base model.
+    fn active_model(&self) -> Option<String> {
+        let reported = self.reported_model.borrow().clone()?;
+        match self.selected_model.as_deref() {
+            Some(selected) if split_model_id(selected).0 == reported => Some(selected.to_string()),
+            _ => Some(reported),
+        }
     }
 
     async fn take_transport(&self) -> Option<ProcessTransport> {
@@ -72,6 +138,29 @@ impl ProcessEntry {
         self.notify_source.lock().await.take()
     }
 
+    /// Whether the Codex process is still running and can accept submissions.
+    async fn is_alive(&self) -> bool {
+        match self.transport.lock().await.as_mut() {
+            Some(process) => process.is_running(),
+            None => false,
+        }
+    }
+
+    /// Write a submission to the running Codex process, if it is still alive.
+    async fn write_submission(&self, submission: &Value) -> Result<(), Error> {
+        let mut guard = self.transport.lock().await;
+        match guard.as_mut() {
+            Some(process) => write_line(process.stdin(), &submission.to_string())
+                .await
+                .map_err(anyhow_to_acp),
+            None => Err(Error::internal_error().with_data("codex process is not running")),
+        }
+    }
+
+    fn reset_cancelled(&self) {
+        self.cancelled.store(false, Ordering::SeqCst);
+    }
+
     fn mark_cancelled(&self) {
         self.cancelled.store(true, Ordering::SeqCst);
         self.cancel_notify.notify_waiters();
@@ -116,32 +205,85 @@ impl ProviderAdapter for TestProviderAdapter {
 }
 
 impl CodexProviderAdapter {
+    fn new(config_loader: ConfigLoader) -> Self {
+        Self {
+            processes: Arc::default(),
+            client: OnceLock::new(),
+            config_loader,
+            login_verified: AtomicBool::new(false),
+        }
+    }
+
+    fn client_fs(&self, session: &SessionState) -> ClientFs {
+        ClientFs::new(
+            self.client.get().cloned(),
+            session.client_capabilities.fs.clone(),
+        )
+    }
+
+    fn client_terminals(&self, session: &SessionState) -> ClientTerminals {
+        ClientTerminals::new(
+            self.client.get().cloned(),
+            session.client_capabilities.terminal,
+        )
+    }
+
+    fn bridge_config(&self, project_dir: Option<&Path>) -> Result<BridgeConfig, Error> {
+        self.config_loader
+            .load(project_dir)
+            .map_err(|err| Error::internal_error().with_data(err.to_string()))
+    }
+
     fn agent_capabilities_internal(&self) -> AgentCapabilities {
         AgentCapabilities {
             load_session: false,
             prompt_capabilities: acp_prompt_caps(),
-            mcp_capabilities: Default::default(),
+            mcp_capabilities: McpCapabilities {
+                http: true,
+                sse: false,
+                meta: None,
+            },
             meta: None,
         }
     }
 
-    async fn prepare_entry(&self, session_key: &str) -> Arc<ProcessEntry> {
-        if let Some(entry) = self.remove_entry(session_key).await {
-            self.shutdown_entry(&entry).await;
+    /// Return the session's Codex process, spawning it if absent or exited.
+    async fn session_entry(
+        &self,
+        session: &SessionState,
+        config: &RuntimeConfig,
+        session_key: &str,
+    ) -> Result<Arc<ProcessEntry>, Error> {
+        let existing = self.processes.read().await.get(session_key).cloned();
+        if let Some(entry) = existing {
+            if entry.is_alive().await {
+                return Ok(entry);
+            }
+            debug!(
+                "Codex process for session {} exited; respawning",
+                session_key
+            );
+            self.teardown_session(session_key).await;
         }
 
-        let entry = Arc::new(ProcessEntry::new());
+        let entry = self.spawn_entry(session, config, session_key).await?;
         self.processes
             .write()
             .await
             .insert(session_key.to_string(), entry.clone());
-        entry
+        Ok(entry)
     }
 
     async fn remove_entry(&self, session_key: &str) -> Option<Arc<ProcessEntry>> {
         self.processes.write().await.remove(session_key)
     }
 
+    async fn teardown_session(&self, session_key: &str) {
+        if let Some(entry) = self.remove_entry(session_key).await {
+            self.shutdown_entry(&entry).await;
+        }
+    }
+
     async fn shutdown_entry(&self, entry: &Arc<ProcessEntry>) {
         if let Some(mut process) = entry.take_transport().await {
             if process.is_running() {
@@ -154,35 +296,23 @@ impl CodexProviderAdapter {
             }
         }
 
+        entry.reader.abort();
+
         if let Some(mut source) = entry.take_notify_source().await {
             if let Err(e) = source.stop().await {
                 warn!("Failed to stop notify source: {}", e);
             }
         }
     }
-
-    async fn finish_prompt(
-        &self,
-        session_key: &str,
-        entry: Arc<ProcessEntry>,
-        join_set: &mut JoinSet<Result<(), Error>>,
-    ) {
-        self.shutdown_entry(&entry).await;
-
-        while let Some(result) = join_set.join_next().await {
-            match result {
-                Ok(Ok(())) => {}
-                Ok(Err(err)) => warn!("Background task error: {:?}", err),
-                Err(join_err) => warn!("Background task join error: {}", join_err),
-            }
-        }
-
-        self.processes.write().await.remove(session_key);
-    }
 }
 
 fn acp_prompt_caps() -> agent_client_protocol::PromptCapabilities {
-    agent_client_protocol::PromptCapabilities::default()
+    agent_client_protocol::PromptCapabilities {
+        image: true,
+        audio: false,
+        embedded_context: true,
+        meta: None,
+    }
 }
 
 fn anyhow_to_acp(err: AnyhowError) -> Error {
@@ -195,6 +325,49 @@ impl ProviderAdapter for CodexProviderAdapter {
         self.agent_capabilities_internal()
     }
 
+    fn attach_client(&self, client: ClientHandle) {
+        if self.client.set(client).is_err() {
+            warn!("Codex adapter already has a client handle; ignoring the new one");
+        }
+    }
+
+    fn auth_methods(&self) -> Vec<AuthMethod> {
+        codex_auth_methods()
+    }
+
+    async fn authenticate(&self, method_id: &AuthMethodId) -> Result<(), Error> {
+        match method_id.0.as_ref() {
+            API_KEY_AUTH_METHOD => {
+                if codex_api_key().is_some() {
+                    Ok(())
+                } else {
+                    Err(Error::auth_required()
+                        .with_data("set OPENAI_API_KEY (or CODEX_API_KEY) and retry"))
+                }
+            }
+            CHATGPT_AUTH_METHOD => {
+                if codex_auth_file().exists() {
+                    return Ok(());
+                }
+                // `codex login` needs a terminal and a browser while the ACP
+                // channel owns our stdio, so only validate an existing login.
+                let config = self.bridge_config(None)?;
+                if codex_login_status(config.codex_command()).await? {
+                    self.login_verified.store(true, Ordering::SeqCst);
+                    Ok(())
+                } else {
+                    Err(Error::auth_required()
+                        .with_data("run `codex login` in a terminal, then retry"))
+                }
+            }
+            _ => Err(Error::invalid_params().with_data("unknown auth method")),
+        }
+    }
+
+    fn available_models(&self) -> Vec<ProviderModel> {
+        codex_models()
+    }
+
     async fn handle_prompt(
         &self,
         session: SessionState,
@@ -205,7 +378,7 @@ impl ProviderAdapter for CodexProviderAdapter {
         let session_id_str = session.session_id.0.to_string();
 
         match self
-            .spawn_and_stream_codex(
+            .run_codex_turn(
                 &session,
                 &request,
                 notifier.clone(),
@@ -214,13 +387,16 @@ impl ProviderAdapter for CodexProviderAdapter {
             )
             .await
         {
-            Ok(stop_reason) => Ok(PromptResponse {
-                stop_reason,
-                meta: None,
-            }),
+            Ok((stop_reason, usage)) => {
+                let meta = usage.map(|usage| {
+                    self.report_usage(&session.session_id, &usage);
+                    json!({ "usage": usage })
+                });
+                Ok(PromptResponse { stop_reason, meta })
+            }
             Err(spawn_err) => {
                 warn!(
-                    "Codex process failed to start for session {}: {}",
+                    "Codex turn failed for session {}: {}",
                     session_id_str, spawn_err.message
                 );
                 Err(spawn_err)
@@ -237,15 +413,16 @@ impl ProviderAdapter for CodexProviderAdapter {
 
         if let Some(entry) = entry {
             entry.mark_cancelled();
-            if let Some(mut process) = entry.take_transport().await {
-                if let Err(e) = process.kill().await {
-                    return Err(Error::internal_error().with_data(e.to_string()));
-                }
-            }
-            if let Some(mut source) = entry.take_notify_source().await {
-                if let Err(e) = source.stop().await {
-                    warn!("Failed to stop notify source during cancel: {}", e);
-                }
+            // Interrupt the running turn but keep the process (and its context) alive.
+            let interrupt = json!({
+                "id": format!("interrupt-{}", Uuid::new_v4()),
+                "op": { "type": "interrupt" }
+            });
+            if let Err(e) = entry.write_submission(&interrupt).await {
+                warn!(
+                    "Failed to interrupt Codex turn for {}: {:?}",
+                    session_key, e
+                );
             }
         }
 
@@ -257,163 +434,298 @@ impl ProviderAdapter for CodexProviderAdapter {
         session_id: &SessionId,
         _session: &SessionState,
     ) -> Result<(), Error> {
-        if let Some(entry) = self.remove_entry(&session_id.0).await {
-            self.shutdown_entry(&entry).await;
-        }
+        // Sandbox and approval flags are fixed at spawn time, so restart on next prompt.
+        self.teardown_session(&session_id.0).await;
         Ok(())
     }
+
+    async fn on_model_changed(
+        &self,
+        session_id: &SessionId,
+        _session: &SessionState,
+    ) -> Result<(), Error> {
+        // `-c model=...` is also a spawn-time flag.
+        self.teardown_session(&session_id.0).await;
+        Ok(())
+    }
+
+    async fn on_session_closed(&self, session_id: &SessionId) -> Result<(), Error> {
+        self.teardown_session(&session_id.0).await;
+        Ok(())
+    }
+
+    async fn active_model(&self, session_id: &SessionId) -> Option<String> {
+        let map = self.processes.read().await;
+        map.get(session_id.0.as_ref())?.active_model()
+    }
+
+    async fn running_processes(&self) -> usize {
+        let entries: Vec<Arc<ProcessEntry>> =
+            self.processes.read().await.values().cloned().collect();
+        let mut running = 0;
+        for entry in entries {
+            if entry.is_alive().await {
+                running += 1;
+            }
+        }
+        running
+    }
 }
 
 impl CodexProviderAdapter {
-    async fn spawn_and_stream_codex(
+    async fn spawn_entry(
         &self,
         session: &SessionState,
-        request: &PromptRequest,
-        notifier: SessionNotifier,
         config: &RuntimeConfig,
-        session_key: String,
-    ) -> Result<StopReason, Error> {
-        let entry = self.prepare_entry(&session_key).await;
+        session_key: &str,
+    ) -> Result<Arc<ProcessEntry>, Error> {
+        // Fail fast with auth_required instead of a confusing Codex startup error.
+        if !self.login_verified.load(Ordering::SeqCst) {
+            ensure_codex_credentials()?;
+        }
+
+        let bridge = self.bridge_config(Some(&session.working_dir))?;
 
-        // Prepare CLI args based on permission mode.
-        let overrides = map_acp_to_codex(session.permission_mode);
+        // Prepare CLI args based on permission mode and `[permissions]` config;
+        // the default mode runs with `approval_policy=on-request`, answered
+        // via request_permission.
+        let overrides =
+            PermissionOverrides::default().apply(bridge.codex_overrides(session.permission_mode));
         let mut args = vec!["proto".to_string()];
         args.extend(overrides.to_cli_args());
+        args.extend(codex_mcp_overrides(&session.mcp_servers));
+        if let Some(model) = session.model.as_deref() {
+            args.extend(codex_model_overrides(model));
+        }
+        args.extend(bridge.codex.args.iter().flatten().cloned());
 
         // Notify integration (mirrors legacy behavior).
-        let notify_path = std::env::var("ACPLB_NOTIFY_PATH").ok();
-        let notify_inject = std::env::var("ACPLB_NOTIFY_INJECT").unwrap_or_else(|_| "auto".into());
-        let notify_cmd = std::env::var("ACPLB_NOTIFY_CMD").ok();
-
-        if notify_path.is_some() {
-            let should_inject = match notify_inject.as_str() {
+        let notify = &bridge.notify;
+        let notify_kind = notify.kind.as_deref();
+        let mut env = Vec::new();
+        if let Some(path) = notify.path.as_ref() {
+            let should_inject = match notify.inject.as_deref().unwrap_or("auto") {
                 "never" => false,
                 "force" => true,
-                _ => notify_cmd.is_none(),
+                _ => notify.command.is_none(),
             };
             if should_inject {
                 if let Ok(forwarder) = resolve_forwarder_path() {
                     args.push("-c".into());
                     args.push(format!("notify=[\"{}\"]", forwarder));
+                    // The forwarder reads its sink from the environment Codex passes on.
+                    env.push(("ACPLB_NOTIFY_PATH".to_string(), path.display().to_string()));
+                    env.push((
+                        "ACPLB_NOTIFY_KIND".to_string(),
+                        notify_kind.unwrap_or("file").to_string(),
+                    ));
                 }
-            } else if let Some(cmd) = notify_cmd.clone() {
+            } else if let Some(cmd) = notify.command.as_ref() {
                 args.push("-c".into());
                 args.push(format!("notify={}", cmd));
             }
         }
 
-        let codex_cmd = std::env::var("CODEX_RUN")
-            .or_else(|_| std::env::var("CODEX_CMD"))
-            .unwrap_or_else(|_| "codex".into());
-
-        let mut process =
-            match ProcessTransport::spawn(&codex_cmd, &args, None, session.working_dir.to_str())
-                .await
-            {
-                Ok(proc) => proc,
-                Err(err) => {
-                    self.processes.write().await.remove(&session_key);
-                    return Err(Error::internal_error().with_data(err.to_string()));
-                }
-            };
+        let mut process = ProcessTransport::spawn(
+            bridge.codex_command(),
+            &args,
+            Some(env),
+            session.working_dir.to_str(),
+        )
+        .await
+        .map_err(anyhow_to_acp)?;
 
         if let Err(e) = process.monitor_stderr() {
             warn!("Failed to monitor Codex stderr: {}", e);
         }
 
-        let submission = build_codex_submission(request)?;
-
-        if let Err(e) = write_line(process.stdin(), &submission.to_string()).await {
-            self.processes.write().await.remove(&session_key);
-            return Err(Error::internal_error().with_data(e.to_string()));
-        }
-
-        let stdout = match process.take_stdout() {
-            Some(stdout) => stdout,
-            None => {
-                self.processes.write().await.remove(&session_key);
-                return Err(Error::internal_error().with_data("missing stdout"));
+        let stdout = process
+            .take_stdout()
+            .ok_or_else(|| Error::internal_error().with_data("missing stdout"))?;
+
+        let (update_tx, update_rx) = mpsc::unbounded_channel::<SessionNotification>();
+        let (approval_tx, approval_rx) = mpsc::unbounded_channel::<CodexApprovalRequest>();
+        let (completion_tx, completion_rx) = mpsc::unbounded_channel::<CodexTurnCompletion>();
+        let (model_tx, model_rx) = watch::channel::<Option<String>>(None);
+        let stream_session_id = SessionId(Arc::from(session_key));
+        let stream_key = session_key.to_string();
+        let terminals = self.client_terminals(session);
+        let reader = tokio::spawn(async move {
+            if let Err(e) = codex_proto::stream_codex_session(
+                stdout,
+                stream_session_id,
+                update_tx,
+                Some(approval_tx),
+                completion_tx,
+                Some(model_tx),
+                terminals,
+            )
+            .await
+            {
+                warn!("Codex stdout reader for {} failed: {}", stream_key, e);
             }
-        };
-
-        entry.store_transport(process).await;
-
-        let (update_tx, mut update_rx) = mpsc::unbounded_channel::<SessionNotification>();
-        let mut join_set: JoinSet<Result<(), Error>> = JoinSet::new();
-        let stream_session_id = SessionId(Arc::from(session_key.as_str()));
-        join_set.spawn(async move {
-            codex_proto::stream_codex_output(stdout, stream_session_id, update_tx)
-                .await
-                .map_err(anyhow_to_acp)
         });
 
-        let (notify_tx, mut notify_rx) = mpsc::unbounded_channel::<NotifyEvent>();
-        let mut notify_enabled = false;
-        if let Some(path) = notify_path.clone() {
-            let notify_kind = std::env::var("ACPLB_NOTIFY_KIND").ok();
-            let mut source =
-                create_notify_source(&path, notify_kind.as_deref(), config.polling_interval_ms);
-            if let Err(e) = source.start_monitoring(notify_tx.clone()).await {
+        let (notify_tx, notify_rx) = mpsc::unbounded_channel::<NotifyEvent>();
+        let mut notify_source = None;
+        if let Some(path) = notify.path.as_ref() {
+            let mut source = create_notify_source(path, notify_kind, config.polling_interval_ms);
+            if let Err(e) = source.start_monitoring(notify_tx).await {
                 warn!("Notify monitoring failed for {}: {}", session_key, e);
             } else {
-                entry.store_notify_source(source).await;
-                notify_enabled = true;
+                notify_source = Some(source);
             }
         }
-        drop(notify_tx);
 
-        let notifier = notifier.clone();
+        let turn = TurnChannels {
+            updates: update_rx,
+            completions: completion_rx,
+            approvals: approval_rx,
+            notify: notify_source.is_some().then_some(notify_rx),
+        };
+        let entry = Arc::new(ProcessEntry::new(
+            process,
+            turn,
+            reader,
+            session.model.clone(),
+            model_rx,
+        ));
+        if let Some(source) = notify_source {
+            entry.store_notify_source(source).await;
+        }
+
+        debug!("Spawned session-scoped Codex process for {}", session_key);
+        Ok(entry)
+    }
+
+    async fn run_codex_turn(
+        &self,
+        session: &SessionState,
+        request: &PromptRequest,
+        notifier: SessionNotifier,
+        config: &RuntimeConfig,
+        session_key: String,
+    ) -> Result<(StopReason, Option<CodexTurnUsage>), Error> {
+        let request = stage_editor_buffers(&self.client_fs(session), request).await;
+        let submission = build_codex_submission(&request)?;
+        let submission_id = submission
+            .get("id")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+
+        let entry = self.session_entry(session, config, &session_key).await?;
+        let mut turn = entry.turn.lock().await;
+        entry.reset_cancelled();
+
+        // Drop leftovers from an interrupted turn so they do not leak into this one.
+        while turn.updates.try_recv().is_ok() {}
+        while turn.completions.try_recv().is_ok() {}
+
+        if let Err(err) = entry.write_submission(&submission).await {
+            drop(turn);
+            self.teardown_session(&session_key).await;
+            return Err(err);
+        }
+
+        let mut approval_tasks: JoinSet<Result<(), Error>> = JoinSet::new();
         let idle_interval = Duration::from_millis(config.polling_interval_ms.max(1));
         let idle_timeout = Duration::from_millis(config.idle_timeout_ms.max(1));
         let mut last_activity = Instant::now();
-        let mut stream_open = true;
         let mut stop_reason = StopReason::EndTurn;
+        let mut usage = None;
+        let mut teardown = false;
+        let mut notified = false;
 
         let idle_timer = time::sleep(idle_interval);
         tokio::pin!(idle_timer);
 
+        let TurnChannels {
+            updates,
+            completions,
+            approvals,
+            notify,
+        } = &mut *turn;
+
         loop {
             tokio::select! {
                 _ = entry.cancel_notify.notified() => {
                     stop_reason = StopReason::Cancelled;
                     break;
                 }
-                update = update_rx.recv(), if stream_open => {
+                update = updates.recv() => {
                     match update {
                         Some(update) => {
-                            debug!(
-                                "Received update from CodexStreamManager: session={}, update_type={:?}",
-                                session_key,
-                                std::mem::discriminant(&update.update)
-                            );
-                            if let Some(tx) = notifier.as_ref() {
-                                if let Err(e) = tx.send(update.clone()) {
-                                    warn!("Failed to send update to notifier channel: {}", e);
-                                } else {
-                                    debug!("Successfully sent update to notifier channel");
-                                }
-                            } else {
-                                warn!("No notifier channel available for session {}", session_key);
-                            }
+                            forward_update(&notifier, &session_key, update);
                             last_activity = Instant::now();
                         }
                         None => {
-                            debug!("CodexStreamManager channel closed for session {}", session_key);
-                            stream_open = false;
+                            debug!("Codex process for session {} closed its stdout", session_key);
+                            teardown = true;
+                            break;
+                        }
+                    }
+                }
+                Some(completed) = completions.recv() => {
+                    if completed.submission_id == submission_id {
+                        debug!(
+                            "Session {} completed submission {}",
+                            session_key, completed.submission_id
+                        );
+                        // The reader queues a turn's updates before its completion;
+                        // flush them so select ordering cannot drop the tail.
+                        while let Ok(update) = updates.try_recv() {
+                            forward_update(&notifier, &session_key, update);
                         }
+                        stop_reason = StopReason::EndTurn;
+                        usage = completed.usage;
+                        break;
+                    }
+                    debug!(
+                        "Ignoring completion for stale submission {}",
+                        completed.submission_id
+                    );
+                }
+                Some(approval) = approvals.recv() => {
+                    debug!(
+                        "Codex approval requested: session={}, tool_call={}",
+                        session_key,
+                        approval.tool_call.id.0
+                    );
+                    approval_tasks.spawn(resolve_approval(
+                        self.client.get().cloned(),
+                        entry.clone(),
+                        session.session_id.clone(),
+                        approval,
+                    ));
+                    last_activity = Instant::now();
+                }
+                Some(result) = approval_tasks.join_next() => {
+                    match result {
+                        Ok(Ok(())) => {}
+                        Ok(Err(err)) => warn!("Codex approval failed: {:?}", err),
+                        Err(join_err) => warn!("Codex approval task join error: {}", join_err),
                     }
+                    last_activity = Instant::now();
                 }
-                notify = notify_rx.recv(), if notify_enabled => {
-                    match notify {
+                notify_event = async {
+                    match notify.as_mut() {
+                        Some(rx) => rx.recv().await,
+                        None => None,
+                    }
+                }, if notify.is_some() => {
+                    match notify_event {
                         Some(event) => {
                             if event.event_type == "agent-turn-complete" {
+                                // Notify can overtake the proto stream; keep forwarding
+                                // until `task_complete` so the turn's tail is not lost.
                                 debug!("Session {} received agent-turn-complete", session_key);
-                                stop_reason = StopReason::EndTurn;
-                                break;
+                                notified = true;
+                                last_activity = Instant::now();
                             }
                         }
                         None => {
-                            notify_enabled = false;
+                            *notify = None;
                         }
                     }
                 }
@@ -424,35 +736,358 @@ impl CodexProviderAdapter {
                     }
 
                     let now = Instant::now();
+                    if notified && now.duration_since(last_activity) >= NOTIFY_DRAIN_GRACE {
+                        debug!(
+                            "Session {} saw no task_complete after agent-turn-complete",
+                            session_key
+                        );
+                        while let Ok(update) = updates.try_recv() {
+                            forward_update(&notifier, &session_key, update);
+                        }
+                        stop_reason = StopReason::EndTurn;
+                        break;
+                    }
                     if now.duration_since(last_activity) >= idle_timeout {
-                        debug!("Session idle timeout reached");
+                        debug!("Session idle timeout reached; tearing down Codex process");
                         // Note: Using EndTurn for idle timeout as per ACP protocol v0.4.3
                         // The protocol doesn't have a specific IdleTimeout variant
                         stop_reason = StopReason::EndTurn;
+                        teardown = true;
                         break;
                     }
 
                     idle_timer.as_mut().reset(now + idle_interval);
                 }
             }
-
-            if !stream_open && !notify_enabled {
-                break;
-            }
         }
 
-        self.finish_prompt(&session_key, entry.clone(), &mut join_set)
-            .await;
+        // Outstanding permission prompts cannot be answered once the turn ends.
+        approval_tasks.abort_all();
+        drop(turn);
+
+        if teardown {
+            self.teardown_session(&session_key).await;
+        }
 
         if entry.cancelled() {
-            Ok(StopReason::Cancelled)
+            Ok((StopReason::Cancelled, usage))
         } else {
-            Ok(stop_reason)
+            Ok((stop_reason, usage))
+        }
+    }
+
+    /// Publish turn usage as a `_acplb/usage` extension notification.
+    fn report_usage(&self, session_id: &SessionId, usage: &CodexTurnUsage) {
+        let Some(client) = self.client.get() else {
+            return;
+        };
+        let params = json!({ "sessionId": session_id.0, "usage": usage });
+        let notification = match RawValue::from_string(params.to_string()) {
+            Ok(params) => ExtNotification {
+                method: Arc::from(USAGE_NOTIFICATION),
+                params: Arc::from(params),
+            },
+            Err(e) => {
+                warn!("Failed to encode usage notification: {}", e);
+                return;
+            }
+        };
+        if let Err(e) = client.ext_notification(notification) {
+            debug!("Usage notification not delivered: {:?}", e);
         }
     }
 }
 
-fn resolve_forwarder_path() -> Result<String, Error> {
+/// Ask the client to approve a Codex exec/patch request and answer Codex on stdin.
+async fn resolve_approval(
+    client: Option<ClientHandle>,
+    entry: Arc<ProcessEntry>,
+    session_id: SessionId,
+    approval: CodexApprovalRequest,
+) -> Result<(), Error> {
+    let decision = match client {
+        Some(client) => {
+            let request = RequestPermissionRequest {
+                session_id,
+                tool_call: approval.tool_call.clone(),
+                options: approval_permission_options(),
+                meta: None,
+            };
+            match client.request_permission(request).await {
+                Ok(response) => review_decision_from_outcome(&response.outcome),
+                Err(err) => {
+                    warn!(
+                        "request_permission failed, denying Codex request: {:?}",
+                        err
+                    );
+                    CodexReviewDecision::Denied
+                }
+            }
+        }
+        None => {
+            warn!("No ACP client attached; denying Codex approval request");
+            CodexReviewDecision::Denied
+        }
+    };
+
+    let submission_id = format!("approval-{}", Uuid::new_v4());
+    entry
+        .write_submission(&approval.response_submission(&submission_id, decision))
+        .await
+}
+
+fn approval_permission_options() -> Vec<PermissionOption> {
+    vec![
+        PermissionOption {
+            id: PermissionOptionId(Arc::from(ALLOW_ONCE_OPTION)),
+            name: "Allow".into(),
+            kind: PermissionOptionKind::AllowOnce,
+            meta: None,
+        },
+        PermissionOption {
+            id: PermissionOptionId(Arc::from(ALLOW_ALWAYS_OPTION)),
+            name: "Always Allow".into(),
+            kind: PermissionOptionKind::AllowAlways,
+            meta: None,
+        },
+        PermissionOption {
+            id: PermissionOptionId(Arc::from(REJECT_OPTION)),
+            name: "Reject".into(),
+            kind: PermissionOptionKind::RejectOnce,
+            meta: None,
+        },
+    ]
+}
+
+fn review_decision_from_outcome(outcome: &RequestPermissionOutcome) -> CodexReviewDecision {
+    match outcome {
+        RequestPermissionOutcome::Cancelled => CodexReviewDecision::Abort,
+        RequestPermissionOutcome::Selected { option_id } => match option_id.0.as_ref() {
+            ALLOW_ONCE_OPTION => CodexReviewDecision::Approved,
+            ALLOW_ALWAYS_OPTION => CodexReviewDecision::ApprovedForSession,
+            _ => CodexReviewDecision::Denied,
+        },
+    }
+}
+
+/// Models offered through `session/set_model`, as `(model, reasoning efforts)`.
+///
+/// Ids take the form `<model>/<effort>`; the first entry is the default.
+const CODEX_MODELS: &[(&str, &[&str])] = &[
+    ("gpt-5-codex", &["medium", "low", "high"]),
+    ("gpt-5", &["medium", "minimal", "low", "high"]),
+];
+
+fn codex_models() -> Vec<ProviderModel> {
+    CODEX_MODELS
+        .iter()
+        .flat_map(|(model, efforts)| {
+            efforts.iter().map(move |effort| ProviderModel {
+                id: format!("{}/{}", model, effort),
+                name: format!("{} ({})", model, effort),
+                description: Some(format!("{} with {} reasoning effort", model, effort)),
+            })
+        })
+        .collect()
+}
+
+/// Split a model id into the Codex model name and optional reasoning effort.
+fn split_model_id(id: &str) -> (&str, Option<&str>) {
+    match id.split_once('/') {
+        Some((model, effort)) if !effort.is_empty() => (model, Some(effort)),
+        Some((model, _)) => (model, None),
+        None => (id, None),
+    }
+}
+
+/// Codex `-c` overrides selecting a model and, when given, its reasoning effort.
+fn codex_model_overrides(id: &str) -> Vec<String> {
+    let (model, effort) = split_model_id(id);
+    let mut args = vec!["-c".to_string(), format!("model={}", toml_string(model))];
+    if let Some(effort) = effort {
+        args.push("-c".into());
+        args.push(format!("model_reasoning_effort={}", toml_string(effort)));
+    }
+    args
+}
+
+/// Translate client-provided MCP servers into Codex `-c mcp_servers.<name>.*` overrides.
+///
+/// Values are emitted as TOML literals (JSON strings are valid TOML basic strings).
+/// Codex has no SSE transport, so SSE servers are skipped.
+fn codex_mcp_overrides(servers: &[McpServer]) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut keys = HashSet::new();
+    for server in servers {
+        match server {
+            McpServer::Stdio {
+                name,
+                command,
+                args: server_args,
+                env,
+            } => {
+                let prefix = format!("mcp_servers.{}", unique_config_key(name, &mut keys));
+                args.push("-c".into());
+                args.push(format!(
+                    "{}.command={}",
+                    prefix,
+                    toml_string(&command.to_string_lossy())
+                ));
+                if !server_args.is_empty() {
+                    let list: Vec<String> = server_args.iter().map(|a| toml_string(a)).collect();
+                    args.push("-c".into());
+                    args.push(format!("{}.args=[{}]", prefix, list.join(", ")));
+                }
+                if !env.is_empty() {
+                    let table: Vec<String> = env
+                        .iter()
+                        .map(|var| {
+                            format!("{} = {}", toml_string(&var.name), toml_string(&var.value))
+                        })
+                        .collect();
+                    args.push("-c".into());
+                    args.push(format!("{}.env={{{}}}", prefix, table.join(", ")));
+                }
+            }
+            McpServer::Http { name, url, headers } => {
+                let prefix = format!("mcp_servers.{}", unique_config_key(name, &mut keys));
+                args.push("-c".into());
+                args.push(format!("{}.url={}", prefix, toml_string(url)));
+                if !headers.is_empty() {
+                    let table: Vec<String> = headers
+                        .iter()
+                        .map(|h| format!("{} = {}", toml_string(&h.name), toml_string(&h.value)))
+                        .collect();
+                    args.push("-c".into());
+                    args.push(format!("{}.http_headers={{{}}}", prefix, table.join(", ")));
+                }
+            }
+            McpServer::Sse { name, .. } => {
+                warn!("Codex does not support SSE MCP servers; skipping {}", name);
+            }
+        }
+    }
+    args
+}
+
+/// Codex splits `-c` keys on '.', so server names are reduced to bare TOML key characters.
+fn codex_config_key(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Config key for `name` that no earlier server in `keys` uses.
+///
+/// Names that only differ in replaced characters ("a b" and "a_b") would
+/// otherwise merge into one Codex server, so later ones get a numeric suffix.
+fn unique_config_key(name: &str, keys: &mut HashSet<String>) -> String {
+    let base = codex_config_key(name);
+    let mut key = base.clone();
+    let mut n = 2;
+    while !keys.insert(key.clone()) {
+        key = format!("{}_{}", base, n);
+        n += 1;
+    }
+    if key != base {
+        warn!(
+            "MCP server {:?} collides with another server; using {}",
+            name, key
+        );
+    }
+    key
+}
+
+fn toml_string(value: &str) -> String {
+    Value::String(value.to_string()).to_string()
+}
+
+fn codex_auth_methods() -> Vec<AuthMethod> {
+    vec![
+        AuthMethod {
+            id: AuthMethodId(Arc::from(CHATGPT_AUTH_METHOD)),
+            name: "Log in with ChatGPT".to_string(),
+            description: Some(
+                "Run `codex login` in a terminal first; checked with `codex login status`".into(),
+            ),
+            meta: None,
+        },
+        AuthMethod {
+            id: AuthMethodId(Arc::from(API_KEY_AUTH_METHOD)),
+            name: "Use OPENAI_API_KEY".to_string(),
+            description: Some("Uses the OPENAI_API_KEY environment variable".into()),
+            meta: None,
+        },
+    ]
+}
+
+pub(crate) fn codex_api_key() -> Option<String> {
+    ["OPENAI_API_KEY", "CODEX_API_KEY"]
+        .iter()
+        .filter_map(|key| std::env::var(key).ok())
+        .find(|value| !value.trim().is_empty())
+}
+
+/// `$CODEX_HOME/auth.json`, written by `codex login`.
+pub(crate) fn codex_auth_file() -> PathBuf {
+    let home = std::env::var_os("CODEX_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".codex")))
+        .unwrap_or_else(|| PathBuf::from(".codex"));
+    home.join("auth.json")
+}
+
+pub(crate) fn codex_credentials_present(
+    api_key: Option<&str>,
+    auth_file: &std::path::Path,
+) -> bool {
+    api_key.is_some_and(|key| !key.trim().is_empty()) || auth_file.exists()
+}
+
+/// Return `auth_required` when Codex has neither an API key nor a stored login.
+///
+/// `ACPLB_SKIP_AUTH_CHECK=1` disables the check (e.g. credentials kept in a
+/// keyring); so does a successful `chatgpt` authenticate.
+fn ensure_codex_credentials() -> Result<(), Error> {
+    if std::env::var("ACPLB_SKIP_AUTH_CHECK").is_ok_and(|value| value == "1" || value == "true") {
+        return Ok(());
+    }
+    require_codex_credentials(codex_api_key().as_deref(), &codex_auth_file())
+}
+
+fn require_codex_credentials(api_key: Option<&str>, auth_file: &Path) -> Result<(), Error> {
+    if codex_credentials_present(api_key, auth_file) {
+        return Ok(());
+    }
+    Err(Error::auth_required()
+        .with_data("Codex is not logged in; authenticate with `chatgpt` or set OPENAI_API_KEY"))
+}
+
+/// Whether `codex login status` reports a login; it needs no terminal.
+async fn codex_login_status(codex_cmd: &str) -> Result<bool, Error> {
+    let status = tokio::process::Command::new(codex_cmd)
+        .args(["login", "status"])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .kill_on_drop(true)
+        .status();
+    let status = time::timeout(LOGIN_STATUS_TIMEOUT, status)
+        .await
+        .map_err(|_| Error::auth_required().with_data("codex login status timed out"))?
+        .map_err(|e| {
+            Error::internal_error().with_data(format!("failed to run codex login status: {e}"))
+        })?;
+    Ok(status.success())
+}
+
+pub(crate) fn resolve_forwarder_path() -> Result<String, Error> {
     if let Ok(current_exe) = std::env::current_exe() {
         if let Some(parent) = current_exe.parent() {
             let forwarder = parent.join("acplb-notify-forwarder");
@@ -480,6 +1115,53 @@ fn resolve_forwarder_path() -> Result<String, Error> {
     Ok("acplb-notify-forwarder".into())
 }
 
+/// Inline the editor's copy of `file://` resource links so Codex sees unsaved
+/// edits rather than the file on disk.
+///
+/// Only applies when the client serves `fs/read_text_file`; links that cannot
+/// be read, or are too large, are left for Codex to open itself.
+async fn stage_editor_buffers(fs: &ClientFs, request: &PromptRequest) -> PromptRequest {
+    let mut staged = request.clone();
+    if !fs.reads_from_client() {
+        return staged;
+    }
+
+    for block in &mut staged.prompt {
+        let ContentBlock::ResourceLink(link) = block else {
+            continue;
+        };
+        let Some(path) = file_uri_to_path(&link.uri) else {
+            continue;
+        };
+        match fs
+            .read_text_file(&request.session_id, Path::new(&path))
+            .await
+        {
+            Ok(text) if text.len() <= MAX_STAGED_BUFFER_BYTES => {
+                *block = ContentBlock::Resource(EmbeddedResource {
+                    annotations: link.annotations.clone(),
+                    resource: EmbeddedResourceResource::TextResourceContents(
+                        TextResourceContents {
+                            mime_type: link.mime_type.clone(),
+                            text,
+                            uri: link.uri.clone(),
+                            meta: None,
+                        },
+                    ),
+                    meta: None,
+                });
+            }
+            Ok(text) => debug!(
+                "Not inlining {} ({} bytes exceeds the staging limit)",
+                path,
+                text.len()
+            ),
+            Err(err) => debug!("Could not read {} through the client: {:?}", path, err),
+        }
+    }
+    staged
+}
+
 fn build_codex_submission(request: &PromptRequest) -> Result<Value, Error> {
     let mut items: Vec<Value> = Vec::new();
     for block in &request.prompt {
@@ -490,6 +1172,41 @@ fn build_codex_submission(request: &PromptRequest) -> Result<Value, Error> {
                     "text": text.text,
                 }));
             }
+            ContentBlock::Image(image) => {
+                items.push(codex_image_item(
+                    &image.data,
+                    &image.mime_type,
+                    image.uri.as_deref(),
+                ));
+            }
+            ContentBlock::Resource(resource) => match &resource.resource {
+                EmbeddedResourceResource::TextResourceContents(contents) => {
+                    items.push(json!({
+                        "type": "text",
+                        "text": format!(
+                            "<context ref=\"{}\">\n{}\n</context>",
+                            contents.uri, contents.text
+                        ),
+                    }));
+                }
+                EmbeddedResourceResource::BlobResourceContents(contents) => {
+                    match contents.mime_type.as_deref() {
+                        Some(mime) if mime.starts_with("image/") => {
+                            items.push(codex_image_item(&contents.blob, mime, None));
+                        }
+                        _ => items.push(json!({
+                            "type": "text",
+                            "text": resource_mention(&contents.uri, &contents.uri),
+                        })),
+                    }
+                }
+            },
+            ContentBlock::ResourceLink(link) => {
+                items.push(json!({
+                    "type": "text",
+                    "text": resource_mention(&link.uri, &link.name),
+                }));
+            }
             other => {
                 return Err(Error::invalid_params()
                     .with_data(format!("unsupported content block in prompt: {:?}", other)));
@@ -499,7 +1216,7 @@ fn build_codex_submission(request: &PromptRequest) -> Result<Value, Error> {
 
     if items.is_empty() {
         return Err(
-            Error::invalid_params().with_data("prompt must contain at least one text block")
+            Error::invalid_params().with_data("prompt must contain at least one content block")
         );
     }
 
@@ -512,6 +1229,23 @@ fn build_codex_submission(request: &PromptRequest) -> Result<Value, Error> {
     }))
 }
 
+/// Codex input item for an image: inline data as a data URL, or a path when only a file URI is given.
+fn codex_image_item(data: &str, mime_type: &str, uri: Option<&str>) -> Value {
+    if let Some(path) = uri.and_then(file_uri_to_path) {
+        if data.is_empty() {
+            return json!({
+                "type": "local_image",
+                "path": path,
+            });
+        }
+    }
+
+    json!({
+        "type": "image",
+        "image_url": format!("data:{};base64,{}", mime_type, data),
+    })
+}
+
 /// Shared runtime agent used by the Codex adapter.
 #[derive(Clone)]
 pub struct CodexAgent {
@@ -539,9 +1273,34 @@ impl CodexAgent {
     }
 
     pub fn new_with_notifier(notifier: SessionNotifier) -> Self {
-        let adapter: Arc<dyn ProviderAdapter> = Arc::new(CodexProviderAdapter::default());
+        Self::new_with_client(notifier, None)
+    }
+
+    /// Construct an agent that forwards Codex approval requests to the ACP client.
+    pub fn new_with_client(notifier: SessionNotifier, client: Option<ClientHandle>) -> Self {
+        let adapter: Arc<dyn ProviderAdapter> =
+            Arc::new(CodexProviderAdapter::new(ConfigLoader::new()));
         let runtime = RuntimeServer::with_defaults(adapter, notifier);
-        Self { runtime }
+        Self {
+            runtime: attach_client(runtime, client),
+        }
+    }
+
+    /// Construct an agent from the layered bridge configuration.
+    ///
+    /// The user file and environment are validated here so mistakes surface
+    /// at startup; project `.acplb.toml` files are applied per session.
+    pub fn with_config_loader(
+        config_loader: ConfigLoader,
+        notifier: SessionNotifier,
+        client: Option<ClientHandle>,
+    ) -> Result<Self, ConfigError> {
+        let config = config_loader.load(None)?.runtime_config();
+        let adapter: Arc<dyn ProviderAdapter> = Arc::new(CodexProviderAdapter::new(config_loader));
+        let runtime = RuntimeServer::new(adapter, config, notifier);
+        Ok(Self {
+            runtime: attach_client(runtime, client),
+        })
     }
 
     pub fn runtime(&self) -> &RuntimeServer {
@@ -549,6 +1308,13 @@ impl CodexAgent {
     }
 }
 
+fn attach_client(runtime: RuntimeServer, client: Option<ClientHandle>) -> RuntimeServer {
+    match client {
+        Some(client) => runtime.with_client(client),
+        None => runtime,
+    }
+}
+
 impl Default for CodexAgent {
     fn default() -> Self {
         Self::new()
@@ -604,3 +1370,284 @@ impl Agent for CodexAgent {
         self.runtime.ext_notification(notification).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agent_client_protocol::{
+        EmbeddedResource, ImageContent, ResourceLink, TextResourceContents,
+    };
+
+    fn prompt(blocks: Vec<ContentBlock>) -> PromptRequest {
+        PromptRequest {
+            session_id: SessionId(Arc::from("session-test")),
+            prompt: blocks,
+            meta: None,
+        }
+    }
+
+    #[test]
+    fn submission_translates_rich_prompt_blocks() {
+        let request = prompt(vec![
+            ContentBlock::from("Explain this"),
+            ContentBlock::Image(ImageContent {
+                annotations: None,
+                data: "iVBORw0KGgo=".to_string(),
+                mime_type: "image/png".to_string(),
+                uri: None,
+                meta: None,
+            }),
+            ContentBlock::Resource(EmbeddedResource {
+                annotations: None,
+                resource: EmbeddedResourceResource::TextResourceContents(TextResourceContents {
+                    mime_type: Some("text/x-rust".to_string()),
+                    text: "fn main() {}".to_string(),
+                    uri: "file:///repo/src/main.rs".to_string(),
+                    meta: None,
+                }),
+                meta: None,
+            }),
+            ContentBlock::ResourceLink(ResourceLink {
+                annotations: None,
+                description: None,
+                mime_type: None,
+                name: "My Notes.md".to_string(),
+                size: None,
+                title: None,
+                uri: "file:///repo/My%20Notes.md".to_string(),
+                meta: None,
+            }),
+        ]);
+
+        // ast-grep-ignore: rust-no-unwrap
+        let submission = build_codex_submission(&request).unwrap();
+        assert_eq!(
+            submission["op"]["items"],
+            json!([
+                {"type": "text", "text": "Explain this"},
+                {"type": "image", "image_url": "data:image/png;base64,iVBORw0KGgo="},
+                {
+                    "type": "text",
+                    "text": "<context ref=\"file:///repo/src/main.rs\">\nfn main() {}\n</context>"
+                },
+                {"type": "text", "text": "@/repo/My Notes.md"}
+            ])
+        );
+    }
+
+    #[test]
+    fn submission_uses_local_image_for_file_uris_without_data() {
+        let request = prompt(vec![ContentBlock::Image(ImageContent {
+            annotations: None,
+            data: String::new(),
+            mime_type: "image/jpeg".to_string(),
+            uri: Some("file:///tmp/shot.jpg".to_string()),
+            meta: None,
+        })]);
+
+        // ast-grep-ignore: rust-no-unwrap
+        let submission = build_codex_submission(&request).unwrap();
+        assert_eq!(
+            submission["op"]["items"],
+            json!([{"type": "local_image", "path": "/tmp/shot.jpg"}])
+        );
+    }
+
+    #[test]
+    fn credentials_require_api_key_or_auth_file() {
+        // ast-grep-ignore: rust-no-unwrap
+        let home = tempfile::tempdir().unwrap();
+        let auth_file = home.path().join("auth.json");
+
+        assert!(!codex_credentials_present(None, &auth_file));
+        assert!(!codex_credentials_present(Some("  "), &auth_file));
+        assert!(codex_credentials_present(Some("sk-test"), &auth_file));
+
+        // ast-grep-ignore: rust-no-unwrap
+        std::fs::write(&auth_file, "{}").unwrap();
+        assert!(codex_credentials_present(None, &auth_file));
+    }
+
+    #[test]
+    fn missing_credentials_are_auth_required() {
+        // ast-grep-ignore: rust-no-unwrap
+        let home = tempfile::tempdir().unwrap();
+        let auth_file = home.path().join("auth.json");
+
+        let err = require_codex_credentials(None, &auth_file)
+            .expect_err("no credentials should be auth_required");
+        assert_eq!(err.code, Error::auth_required().code);
+        assert!(require_codex_credentials(Some("sk-test"), &auth_file).is_ok());
+    }
+
+    #[tokio::test]
+    async fn login_status_validates_an_existing_login() {
+        // ast-grep-ignore: rust-no-unwrap
+        let dir = tempfile::tempdir().unwrap();
+        for (name, exit_code, logged_in) in [("logged-in", 0, true), ("logged-out", 1, false)] {
+            let script = dir.path().join(name);
+            // ast-grep-ignore: rust-no-unwrap
+            std::fs::write(
+                &script,
+                format!(
+                    "#!/bin/sh\n[ \"$1 $2\" = \"login status\" ] || exit 2\nexit {exit_code}\n"
+                ),
+            )
+            .unwrap();
+            // ast-grep-ignore: rust-no-unwrap
+            std::fs::set_permissions(&script, std::os::unix::fs::PermissionsExt::from_mode(0o755))
+                .unwrap();
+            // ast-grep-ignore: rust-no-unwrap
+            let status = codex_login_status(&script.display().to_string())
+                .await
+                .unwrap();
+            assert_eq!(status, logged_in, "{}", name);
+        }
+    }
+
+    #[test]
+    fn model_ids_map_to_model_and_effort_overrides() {
+        assert_eq!(
+            codex_model_overrides("gpt-5-codex/high"),
+            vec![
+                "-c",
+                r#"model="gpt-5-codex""#,
+                "-c",
+                r#"model_reasoning_effort="high""#,
+            ]
+        );
+        assert_eq!(codex_model_overrides("o3"), vec!["-c", r#"model="o3""#]);
+        assert_eq!(
+            codex_models().first().map(|model| model.id.as_str()),
+            Some("gpt-5-codex/medium")
+        );
+    }
+
+    #[test]
+    fn mcp_servers_become_codex_overrides() {
+        let servers = vec![
+            McpServer::Stdio {
+                name: "repo tools".to_string(),
+                command: PathBuf::from("/usr/bin/mcp-repo"),
+                args: vec!["--root".to_string(), "/repo".to_string()],
+                env: vec![agent_client_protocol::EnvVariable {
+                    name: "TOKEN".to_string(),
+                    value: "abc".to_string(),
+                    meta: None,
+                }],
+            },
+            McpServer::Http {
+                name: "docs".to_string(),
+                url: "https://mcp.example.com".to_string(),
+                headers: Vec::new(),
+            },
+            McpServer::Sse {
+                name: "legacy".to_string(),
+                url: "https://sse.example.com".to_string(),
+                headers: Vec::new(),
+            },
+        ];
+
+        assert_eq!(
+            codex_mcp_overrides(&servers),
+            vec![
+                "-c",
+                r#"mcp_servers.repo_tools.command="/usr/bin/mcp-repo""#,
+                "-c",
+                r#"mcp_servers.repo_tools.args=["--root", "/repo"]"#,
+                "-c",
+                r#"mcp_servers.repo_tools.env={"TOKEN" = "abc"}"#,
+                "-c",
+                r#"mcp_servers.docs.url="https://mcp.example.com""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn colliding_mcp_server_names_get_distinct_keys() {
+        let servers: Vec<McpServer> = [("a b", "one"), ("a_b", "two"), ("a.b", "three")]
+            .into_iter()
+            .map(|(name, host)| McpServer::Http {
+                name: name.to_string(),
+                url: format!("https://{}.example.com", host),
+                headers: Vec::new(),
+            })
+            .collect();
+
+        assert_eq!(
+            codex_mcp_overrides(&servers),
+            vec![
+                "-c",
+                r#"mcp_servers.a_b.url="https://one.example.com""#,
+                "-c",
+                r#"mcp_servers.a_b_2.url="https://two.example.com""#,
+                "-c",
+                r#"mcp_servers.a_b_3.url="https://three.example.com""#,
+            ]
+        );
+    }
+
+    fn file_link(uri: &str) -> ContentBlock {
+        ContentBlock::ResourceLink(ResourceLink {
+            annotations: None,
+            description: None,
+            mime_type: None,
+            name: "lib.rs".to_string(),
+            size: None,
+            title: None,
+            uri: uri.to_string(),
+            meta: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn editor_buffers_are_staged_when_client_reads_files() {
+        let (client, mut rx) = ClientHandle::channel();
+        tokio::spawn(async move {
+            while let Some(request) = rx.recv().await {
+                if let acp_lazy_core::runtime::ClientRequest::ReadTextFile { request, reply } =
+                    request
+                {
+                    let result = if request.path == Path::new("/repo/src/lib.rs") {
+                        Ok(agent_client_protocol::ReadTextFileResponse {
+                            content: "// unsaved edit".to_string(),
+                            meta: None,
+                        })
+                    } else {
+                        Err(Error::resource_not_found(None))
+                    };
+                    let _ = reply.send(result);
+                }
+            }
+        });
+        let request = prompt(vec![
+            file_link("file:///repo/src/lib.rs"),
+            file_link("file:///repo/missing.rs"),
+        ]);
+
+        let capabilities = agent_client_protocol::FileSystemCapability {
+            read_text_file: true,
+            ..Default::default()
+        };
+        let staged =
+            stage_editor_buffers(&ClientFs::new(Some(client.clone()), capabilities), &request)
+                .await;
+        // ast-grep-ignore: rust-no-unwrap
+        let submission = build_codex_submission(&staged).unwrap();
+        assert_eq!(
+            submission["op"]["items"],
+            json!([
+                {
+                    "type": "text",
+                    "text": "<context ref=\"file:///repo/src/lib.rs\">\n// unsaved edit\n</context>"
+                },
+                {"type": "text", "text": "@/repo/missing.rs"}
+            ])
+        );
+
+        // Without the capability Codex reads the files from disk itself.
+        let untouched =
+            stage_editor_buffers(&ClientFs::new(Some(client), Default::default()), &request).await;
+        assert_eq!(untouched.prompt, request.prompt);
+    }
+}
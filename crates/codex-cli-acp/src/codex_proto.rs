@@ -7,9 +7,29 @@
 //!
 //! - Deserialising Codex protocol JSON into strongly typed helper events.
 //! - Building ACP content blocks (text, reasoning, plan, available commands,
-//!   mode updates, tool calls) directly with the v0.4.2 models.
+//!   tool calls) directly with the v0.4.2 models.
 //! - Preserving Codex metadata such as tool raw I/O, stop reasons, and
 //!   notification timing while applying the LastChunkGuard deduplication rules.
+//! - Mapping `exec_command_*` events onto a live Execute tool call whose
+//!   stdout/stderr previews are streamed as `ToolCallUpdate` deltas; when the
+//!   client supports terminals, the finished output is also mirrored into a
+//!   client terminal (Codex has already run the command) and a follow-up
+//!   update embeds it as `ToolCallContent::Terminal`.
+//! - Surfacing `exec_approval_request` / `apply_patch_approval_request` as
+//!   `CodexApprovalRequest`s so the adapter can ask the client for permission.
+//! - Mapping `mcp_tool_call_*` events onto `server.tool` tool calls whose
+//!   completion carries the MCP `CallToolResult` content.
+//! - Presenting `web_search_*` events as Fetch tool calls titled with the
+//!   query, completed with `ResourceLink`s for result URLs.
+//! - Summarising each turn's cumulative `turn_diff` as one completed Edit tool
+//!   call with per-file diffs, emitted just before the turn completes.
+//! - Rendering `patch_apply_*` events as Edit/Delete/Move tool calls carrying
+//!   per-file `ToolCallContent::Diff` entries.
+//! - Recording the model reported by `session_configured` so the adapter can
+//!   tell the runtime which model is actually in use.
+//! - Tracking turn boundaries (`task_started` / `task_complete` per submission
+//!   id) so one Codex process can serve every prompt in a session.
+//! - Accumulating `token_count` events into per-turn `CodexTurnUsage`.
 //! - Emitting updates through `AgentSideConnection::session_notification` so
 //!   notify/idle stop reasons propagate as `StopReason::EndTurn` and
 //!   `StopReason::IdleTimeout`.
@@ -20,23 +40,25 @@
 
 use crate::tool_calls::{
     extract_shell_command, extract_shell_params, format_tool_output, map_tool_kind,
-    MAX_OUTPUT_PREVIEW_BYTES,
+    truncate_output, ExtractedShellParams, MAX_OUTPUT_PREVIEW_BYTES,
 };
+use acp_lazy_core::runtime::ClientTerminals;
 use agent_client_protocol::Error as AcpError;
 use agent_client_protocol::{
-    AudioContent, AvailableCommand, BlobResourceContents, ContentBlock, EmbeddedResource,
-    EmbeddedResourceResource, ImageContent, Plan, PlanEntry, PlanEntryPriority, PlanEntryStatus,
-    ResourceLink, SessionId, SessionModeId, SessionNotification, SessionUpdate, TextContent,
-    TextResourceContents, ToolCall, ToolCallContent, ToolCallId, ToolCallLocation, ToolCallStatus,
-    ToolCallUpdate, ToolCallUpdateFields, ToolKind,
+    AudioContent, AvailableCommand, BlobResourceContents, ContentBlock, CreateTerminalRequest,
+    Diff, EmbeddedResource, EmbeddedResourceResource, ImageContent, Plan, PlanEntry,
+    PlanEntryPriority, PlanEntryStatus, ResourceLink, SessionId, SessionNotification,
+    SessionUpdate, TextContent, TextResourceContents, ToolCall, ToolCallContent, ToolCallId,
+    ToolCallLocation, ToolCallStatus, ToolCallUpdate, ToolCallUpdateFields, ToolKind,
 };
 use anyhow::{Context, Result};
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::{collections::HashMap, path::PathBuf, sync::Arc, time::Duration};
 use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::sync::mpsc;
-use tracing::{debug, error, info, trace};
+use tokio::sync::{mpsc, watch};
+use tracing::{debug, error, info, trace, warn};
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct CodexUserMessageEvent {
@@ -104,6 +126,332 @@ pub struct CodexSessionConfiguredEvent {
     pub extra: HashMap<String, Value>,
 }
 
+/// Token counts as reported by Codex (snake_case in, camelCase out).
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Default, PartialEq, Eq)]
+#[serde(rename_all(serialize = "camelCase"))]
+pub struct CodexTokenUsage {
+    #[serde(default)]
+    pub input_tokens: u64,
+    #[serde(default)]
+    pub cached_input_tokens: u64,
+    #[serde(default)]
+    pub output_tokens: u64,
+    #[serde(default)]
+    pub reasoning_output_tokens: u64,
+    #[serde(default)]
+    pub total_tokens: u64,
+}
+
+impl CodexTokenUsage {
+    fn saturating_add(self, other: Self) -> Self {
+        Self {
+            input_tokens: self.input_tokens.saturating_add(other.input_tokens),
+            cached_input_tokens: self
+                .cached_input_tokens
+                .saturating_add(other.cached_input_tokens),
+            output_tokens: self.output_tokens.saturating_add(other.output_tokens),
+            reasoning_output_tokens: self
+                .reasoning_output_tokens
+                .saturating_add(other.reasoning_output_tokens),
+            total_tokens: self.total_tokens.saturating_add(other.total_tokens),
+        }
+    }
+
+    fn saturating_sub(self, other: Self) -> Self {
+        Self {
+            input_tokens: self.input_tokens.saturating_sub(other.input_tokens),
+            cached_input_tokens: self
+                .cached_input_tokens
+                .saturating_sub(other.cached_input_tokens),
+            output_tokens: self.output_tokens.saturating_sub(other.output_tokens),
+            reasoning_output_tokens: self
+                .reasoning_output_tokens
+                .saturating_sub(other.reasoning_output_tokens),
+            total_tokens: self.total_tokens.saturating_sub(other.total_tokens),
+        }
+    }
+
+    /// Tokens occupying the context window after this request (reasoning is dropped).
+    fn context_tokens(&self) -> u64 {
+        self.total_tokens
+            .saturating_sub(self.reasoning_output_tokens)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct CodexTokenUsageInfo {
+    #[serde(default)]
+    pub total_token_usage: CodexTokenUsage,
+    #[serde(default)]
+    pub last_token_usage: CodexTokenUsage,
+    #[serde(default)]
+    pub model_context_window: Option<u64>,
+}
+
+/// `token_count` payload. Current Codex nests cumulative and last-request usage
+/// under `info`; older builds sent a flat per-request usage.
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct CodexTokenCountEvent {
+    #[serde(default)]
+    pub info: Option<CodexTokenUsageInfo>,
+    #[serde(flatten)]
+    pub usage: CodexTokenUsage,
+}
+
+/// Token usage for one turn, attached to `PromptResponse.meta.usage`.
+#[derive(Debug, Clone, Copy, Serialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CodexTurnUsage {
+    /// Tokens consumed by this turn.
+    #[serde(flatten)]
+    pub turn: CodexTokenUsage,
+    /// Cumulative tokens consumed by the session so far.
+    pub session_total_tokens: u64,
+    /// Tokens currently occupying the model context window.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_tokens: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_context_window: Option<u64>,
+}
+
+/// A finished Codex turn, reported by `stream_codex_session`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodexTurnCompletion {
+    pub submission_id: String,
+    pub usage: Option<CodexTurnUsage>,
+}
+
+/// Tool name used when presenting Codex exec events as ACP tool calls.
+const EXEC_TOOL_NAME: &str = "shell";
+
+/// Per-stream cap on output mirrored into a client terminal, kept below the
+/// 128 KiB Linux allows for a single `execve` argument.
+const MAX_TERMINAL_MIRROR_BYTES: usize = 96 * 1024;
+
+/// Upper bound on each client call (create, wait for exit) of a terminal mirror.
+const TERMINAL_MIRROR_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Prints the captured stdout and stderr, then exits with the command's status.
+const TERMINAL_MIRROR_SCRIPT: &str = r#"printf '%s' "$1"; printf '%s' "$2" >&2; exit "$3""#;
+
+/// Output stream identifier carried by Codex exec output deltas.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CodexExecOutputStream {
+    #[default]
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct CodexExecCommandBeginEvent {
+    pub call_id: String,
+    #[serde(default)]
+    pub command: Vec<String>,
+    #[serde(default)]
+    pub cwd: Option<String>,
+    #[serde(default)]
+    pub parsed_cmd: Option<Value>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct CodexExecCommandOutputDeltaEvent {
+    pub call_id: String,
+    #[serde(default)]
+    pub stream: CodexExecOutputStream,
+    /// Raw output bytes: a base64 string, or a byte array from older builds.
+    #[serde(default)]
+    pub chunk: Value,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct CodexExecCommandEndEvent {
+    pub call_id: String,
+    #[serde(default)]
+    pub stdout: String,
+    #[serde(default)]
+    pub stderr: String,
+    #[serde(default)]
+    pub aggregated_output: Option<String>,
+    /// Missing when Codex could not determine the exit status.
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    #[serde(default)]
+    pub duration: Option<Value>,
+    #[serde(default)]
+    pub formatted_output: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct CodexExecApprovalRequestEvent {
+    pub call_id: String,
+    #[serde(default)]
+    pub command: Vec<String>,
+    #[serde(default)]
+    pub cwd: Option<String>,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct CodexApplyPatchApprovalRequestEvent {
+    pub call_id: String,
+    #[serde(default)]
+    pub changes: HashMap<String, Value>,
+    #[serde(default)]
+    pub reason: Option<String>,
+    #[serde(default)]
+    pub grant_root: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct CodexPatchApplyBeginEvent {
+    pub call_id: String,
+    #[serde(default)]
+    pub auto_approved: bool,
+    #[serde(default)]
+    pub changes: HashMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct CodexPatchApplyEndEvent {
+    pub call_id: String,
+    #[serde(default)]
+    pub stdout: String,
+    #[serde(default)]
+    pub stderr: String,
+    #[serde(default)]
+    pub success: bool,
+}
+
+/// Server/tool pair and arguments of an MCP tool invocation.
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct CodexMcpInvocation {
+    pub server: String,
+    pub tool: String,
+    #[serde(default)]
+    pub arguments: Option<Value>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct CodexMcpToolCallBeginEvent {
+    pub call_id: String,
+    #[serde(default)]
+    pub invocation: CodexMcpInvocation,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct CodexMcpToolCallEndEvent {
+    pub call_id: String,
+    #[serde(default)]
+    pub invocation: CodexMcpInvocation,
+    #[serde(default)]
+    pub duration: Option<Value>,
+    /// `{"Ok": CallToolResult}` or `{"Err": "message"}`.
+    #[serde(default)]
+    pub result: Value,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct CodexWebSearchBeginEvent {
+    pub call_id: String,
+    /// Older Codex builds send the query up front; newer ones only on end.
+    #[serde(default)]
+    pub query: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct CodexWebSearchEndEvent {
+    pub call_id: String,
+    #[serde(default)]
+    pub query: Option<String>,
+    /// Result entries, either URL strings or `{url, title, snippet}` objects.
+    #[serde(default)]
+    pub results: Vec<Value>,
+}
+
+/// A single file change inside a Codex patch.
+///
+/// Codex serialises these externally tagged (`{"update": {...}}`); some
+/// builds emit `{"type": "update", ...}` instead, see `CodexFileChange::from_value`.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CodexFileChange {
+    Add {
+        content: String,
+    },
+    Delete {
+        #[serde(default)]
+        content: Option<String>,
+    },
+    Update {
+        #[serde(default)]
+        unified_diff: String,
+        #[serde(default)]
+        move_path: Option<String>,
+    },
+}
+
+impl CodexFileChange {
+    /// Parse either the externally tagged or the `type`-tagged representation.
+    pub fn from_value(value: &Value) -> Option<Self> {
+        if let Some(tag) = value.get("type").and_then(|t| t.as_str()) {
+            let mut body = value.clone();
+            if let Some(obj) = body.as_object_mut() {
+                obj.remove("type");
+            }
+            return serde_json::from_value(json!({ tag: body })).ok();
+        }
+        serde_json::from_value(value.clone()).ok()
+    }
+}
+
+/// Which Codex approval gate is waiting on a decision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodexApprovalKind {
+    Exec,
+    Patch,
+}
+
+/// Codex `ReviewDecision` values accepted by the approval ops.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CodexReviewDecision {
+    Approved,
+    ApprovedForSession,
+    Denied,
+    Abort,
+}
+
+/// Pending Codex approval, forwarded to the adapter so it can call
+/// `session/request_permission` and answer on Codex stdin.
+#[derive(Debug, Clone)]
+pub struct CodexApprovalRequest {
+    pub kind: CodexApprovalKind,
+    /// Identifier Codex expects in the `exec_approval` / `patch_approval` op.
+    pub approval_id: String,
+    /// Tool call presented to the client in the permission request.
+    pub tool_call: ToolCallUpdate,
+}
+
+impl CodexApprovalRequest {
+    /// Build the Codex submission that answers this approval.
+    pub fn response_submission(&self, submission_id: &str, decision: CodexReviewDecision) -> Value {
+        let op_type = match self.kind {
+            CodexApprovalKind::Exec => "exec_approval",
+            CodexApprovalKind::Patch => "patch_approval",
+        };
+        json!({
+            "id": submission_id,
+            "op": {
+                "type": op_type,
+                "id": self.approval_id,
+                "decision": decision,
+            }
+        })
+    }
+}
+
 /// Codex proto event types
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -146,11 +494,28 @@ pub enum CodexEvent {
     ToolCalls {
         calls: Vec<ToolCallItem>,
     },
+    ExecCommandBegin(CodexExecCommandBeginEvent),
+    ExecCommandOutputDelta(CodexExecCommandOutputDeltaEvent),
+    ExecCommandStdoutDelta(CodexExecCommandOutputDeltaEvent),
+    ExecCommandStderrDelta(CodexExecCommandOutputDeltaEvent),
+    ExecCommandEnd(CodexExecCommandEndEvent),
+    ExecApprovalRequest(CodexExecApprovalRequestEvent),
+    ApplyPatchApprovalRequest(CodexApplyPatchApprovalRequestEvent),
+    PatchApplyBegin(CodexPatchApplyBeginEvent),
+    PatchApplyEnd(CodexPatchApplyEndEvent),
+    McpToolCallBegin(CodexMcpToolCallBeginEvent),
+    McpToolCallEnd(CodexMcpToolCallEndEvent),
+    WebSearchBegin(CodexWebSearchBeginEvent),
+    WebSearchEnd(CodexWebSearchEndEvent),
+    TurnDiff {
+        unified_diff: String,
+    },
     PlanUpdate(CodexPlanUpdateEvent),
     McpListToolsResponse {
         tools: HashMap<String, CodexToolDefinition>,
     },
     SessionConfigured(CodexSessionConfiguredEvent),
+    TokenCount(CodexTokenCountEvent),
     TaskStarted {
         #[serde(default)]
         model_context_window: Option<u32>,
@@ -192,6 +557,14 @@ struct ToolCallRecord {
     raw_input: Option<Value>,
 }
 
+#[derive(Debug, Clone, Default)]
+struct ExecOutputBuffers {
+    // Accumulated exec output so each ToolCallUpdate carries the full preview;
+    // ACP replaces tool call content wholesale on update.
+    stdout: String,
+    stderr: String,
+}
+
 /// Manages streaming from Codex proto to ACP
 ///
 /// Note: Uses singular update field per ACP spec SessionNotification structure,
@@ -203,6 +576,19 @@ pub struct CodexStreamManager {
     finalized: bool,
     tool_calls: HashMap<String, ToolCallRecord>,
     last_tool_call_id: Option<String>,
+    exec_outputs: HashMap<String, ExecOutputBuffers>,
+    approvals: Option<mpsc::UnboundedSender<CodexApprovalRequest>>,
+    terminals: ClientTerminals,
+    patch_diffs: HashMap<String, Vec<ToolCallContent>>,
+    last_completed_submission: Option<String>,
+    completed_submission: Option<CodexTurnCompletion>,
+    configured_model: Option<String>,
+    turn_diff: Option<String>,
+    session_usage: CodexTokenUsage,
+    turn_start_usage: CodexTokenUsage,
+    turn_usage_seen: bool,
+    context_tokens: Option<u64>,
+    model_context_window: Option<u64>,
 }
 
 impl CodexStreamManager {
@@ -214,9 +600,42 @@ impl CodexStreamManager {
             finalized: false,
             tool_calls: HashMap::new(),
             last_tool_call_id: None,
+            exec_outputs: HashMap::new(),
+            approvals: None,
+            terminals: ClientTerminals::default(),
+            patch_diffs: HashMap::new(),
+            last_completed_submission: None,
+            completed_submission: None,
+            configured_model: None,
+            turn_diff: None,
+            session_usage: CodexTokenUsage::default(),
+            turn_start_usage: CodexTokenUsage::default(),
+            turn_usage_seen: false,
+            context_tokens: None,
+            model_context_window: None,
         }
     }
 
+    /// Forward Codex approval requests to `approvals` instead of dropping them.
+    pub fn with_approvals(
+        mut self,
+        approvals: mpsc::UnboundedSender<CodexApprovalRequest>,
+    ) -> Self {
+        self.approvals = Some(approvals);
+        self
+    }
+
+    /// Mirror finished shell output into client terminals when `terminals` is supported.
+    pub fn with_terminals(mut self, terminals: ClientTerminals) -> Self {
+        self.terminals = terminals;
+        self
+    }
+
+    /// Model Codex reported in its most recent `session_configured` event.
+    pub fn configured_model(&self) -> Option<&str> {
+        self.configured_model.as_deref()
+    }
+
     /// Process a line from Codex stdout
     pub async fn process_line(&mut self, line: &str) -> Result<()> {
         if line.trim().is_empty() {
@@ -233,6 +652,10 @@ impl CodexStreamManager {
 
         // Codex proto outputs events in {"id":"...", "msg":{...}} format
         // Extract the msg field which contains the actual event
+        let event_id = value
+            .get("id")
+            .and_then(|id| id.as_str())
+            .map(str::to_string);
         let event_value = if let Some(msg) = value.get("msg") {
             msg.clone()
         } else {
@@ -240,6 +663,11 @@ impl CodexStreamManager {
             value.clone()
         };
 
+        // A new submission id after task_complete means the next turn started.
+        if self.finalized && event_id.is_some() && event_id != self.last_completed_submission {
+            self.begin_turn();
+        }
+
         let event: CodexEvent = match serde_json::from_value(event_value.clone()) {
             Ok(e) => e,
             Err(e) => {
@@ -297,6 +725,54 @@ impl CodexStreamManager {
                     .await?;
                 }
             }
+            CodexEvent::ExecCommandBegin(event) => {
+                self.send_exec_command_begin(event).await?;
+            }
+            CodexEvent::ExecCommandOutputDelta(event) => {
+                let stream = event.stream;
+                self.send_exec_command_output(event, stream).await?;
+            }
+            CodexEvent::ExecCommandStdoutDelta(event) => {
+                self.send_exec_command_output(event, CodexExecOutputStream::Stdout)
+                    .await?;
+            }
+            CodexEvent::ExecCommandStderrDelta(event) => {
+                self.send_exec_command_output(event, CodexExecOutputStream::Stderr)
+                    .await?;
+            }
+            CodexEvent::ExecCommandEnd(event) => {
+                self.send_exec_command_end(event).await?;
+            }
+            CodexEvent::ExecApprovalRequest(event) => {
+                let approval_id = event_id.unwrap_or_else(|| event.call_id.clone());
+                self.forward_approval(exec_approval_request(approval_id, event));
+            }
+            CodexEvent::ApplyPatchApprovalRequest(event) => {
+                let approval_id = event_id.unwrap_or_else(|| event.call_id.clone());
+                self.forward_approval(patch_approval_request(approval_id, event));
+            }
+            CodexEvent::PatchApplyBegin(event) => {
+                self.send_patch_apply_begin(event).await?;
+            }
+            CodexEvent::PatchApplyEnd(event) => {
+                self.send_patch_apply_end(event).await?;
+            }
+            CodexEvent::McpToolCallBegin(event) => {
+                self.send_mcp_tool_call_begin(event).await?;
+            }
+            CodexEvent::McpToolCallEnd(event) => {
+                self.send_mcp_tool_call_end(event).await?;
+            }
+            CodexEvent::WebSearchBegin(event) => {
+                self.send_web_search_begin(event).await?;
+            }
+            CodexEvent::WebSearchEnd(event) => {
+                self.send_web_search_end(event).await?;
+            }
+            CodexEvent::TurnDiff { unified_diff } => {
+                // Each turn_diff is cumulative for the turn; keep the latest.
+                self.turn_diff = Some(unified_diff);
+            }
             CodexEvent::PlanUpdate(update) => {
                 self.send_plan_update(update).await?;
             }
@@ -304,17 +780,31 @@ impl CodexStreamManager {
                 self.send_available_commands(tools).await?;
             }
             CodexEvent::SessionConfigured(event) => {
-                self.send_current_mode_update(event).await?;
+                self.record_session_configured(event);
+            }
+            CodexEvent::TokenCount(event) => {
+                self.record_token_count(event);
             }
             CodexEvent::TaskStarted {
                 model_context_window,
             } => {
                 debug!("Task started: context_window={:?}", model_context_window);
+                if let Some(window) = model_context_window {
+                    self.model_context_window = Some(u64::from(window));
+                }
                 // Task started indicates Codex is processing the request
+                self.begin_turn();
             }
             CodexEvent::TaskComplete { reason } => {
                 info!("Task complete: {:?}", reason);
                 self.finalized = true;
+                let submission_id = event_id.unwrap_or_default();
+                self.send_turn_diff_summary(&submission_id).await?;
+                self.last_completed_submission = Some(submission_id.clone());
+                self.completed_submission = Some(CodexTurnCompletion {
+                    submission_id,
+                    usage: self.turn_usage(),
+                });
             }
             CodexEvent::Error { message, code } => {
                 self.handle_error(message, code).await?;
@@ -441,19 +931,12 @@ impl CodexStreamManager {
         Ok(())
     }
 
-    async fn send_current_mode_update(&mut self, event: CodexSessionConfiguredEvent) -> Result<()> {
+    fn record_session_configured(&mut self, event: CodexSessionConfiguredEvent) {
         let Some(model) = event.model.filter(|m| !m.trim().is_empty()) else {
-            return Ok(());
+            return;
         };
-
-        let notification = self.build_notification(SessionUpdate::CurrentModeUpdate {
-            current_mode_id: SessionModeId(Arc::from(model.as_str())),
-        });
-
-        self.tx
-            .send(notification)
-            .context("Failed to send current mode update")?;
-        Ok(())
+        info!("Codex session configured with model {}", model);
+        self.configured_model = Some(model);
     }
 
     async fn send_tool_call(
@@ -482,15 +965,10 @@ impl CodexStreamManager {
             };
 
         let title = if let Some(cmd) = extract_shell_command(&name, &arguments) {
-            if let Some(params) = shell_params.as_ref() {
-                if let Some(ref workdir) = params.workdir {
-                    format!("{}: {} (in {})", name, cmd, workdir)
-                } else {
-                    format!("{}: {}", name, cmd)
-                }
-            } else {
-                format!("{}: {}", name, cmd)
-            }
+            let workdir = shell_params
+                .as_ref()
+                .and_then(|params| params.workdir.as_deref());
+            shell_tool_title(&name, &cmd, workdir)
         } else {
             name.clone()
         };
@@ -499,27 +977,31 @@ impl CodexStreamManager {
 
         let locations = shell_params
             .as_ref()
-            .and_then(|params| {
-                params.workdir.as_ref().map(|dir| {
-                    vec![ToolCallLocation {
-                        path: PathBuf::from(dir),
-                        line: None,
-                        meta: None,
-                    }]
-                })
-            })
+            .map(shell_locations)
             .unwrap_or_default();
 
         let mut content_blocks: Vec<ToolCallContent> = Vec::new();
+        let mut mirror = None;
         if let Some(ref out) = output {
+            if shell_params.is_some()
+                && matches!(
+                    tool_status,
+                    ToolCallStatus::Completed | ToolCallStatus::Failed
+                )
+            {
+                mirror = Some(shell_output_parts(out, tool_status));
+            }
             let formatted = format_tool_output(&name, out, MAX_OUTPUT_PREVIEW_BYTES);
             if !formatted.is_empty() {
                 content_blocks.push(ToolCallContent::from(formatted));
             }
         }
 
-        if let Some(ref err) = error {
-            content_blocks.push(ToolCallContent::from(format!("[Error]: {}", err)));
+        let error_block = error
+            .as_ref()
+            .map(|err| ToolCallContent::from(format!("[Error]: {}", err)));
+        if let Some(block) = error_block.clone() {
+            content_blocks.push(block);
         }
 
         if matches!(
@@ -538,30 +1020,7 @@ impl CodexStreamManager {
         }
 
         let enhanced_raw_input = if let Some(params) = shell_params.as_ref() {
-            let mut enhanced = json!({
-                "original": arguments.clone(),
-                "extracted": {}
-            });
-
-            if let Some(obj) = enhanced["extracted"].as_object_mut() {
-                if let Some(ref cmd) = params.command {
-                    obj.insert("command".to_string(), json!(cmd));
-                }
-                if let Some(ref workdir) = params.workdir {
-                    obj.insert("workdir".to_string(), json!(workdir));
-                }
-                if let Some(timeout) = params.timeout_ms {
-                    obj.insert("timeout_ms".to_string(), json!(timeout));
-                }
-                if let Some(escalated) = params.with_escalated_permissions {
-                    obj.insert("with_escalated_permissions".to_string(), json!(escalated));
-                }
-                if let Some(ref justification) = params.justification {
-                    obj.insert("justification".to_string(), json!(justification));
-                }
-            }
-
-            enhanced
+            shell_raw_input(&arguments, params)
         } else {
             arguments.clone()
         };
@@ -669,56 +1128,565 @@ impl CodexStreamManager {
                 .context("Failed to send tool call")?;
         }
 
+        if let Some((stdout, stderr, exit_code)) = mirror {
+            self.spawn_terminal_mirror(&id, stdout, stderr, exit_code, error_block);
+        }
         self.tool_calls.insert(id, record);
 
         Ok(())
     }
 
-    async fn handle_error(&mut self, message: String, code: Option<String>) -> Result<()> {
-        error!("Codex error: {} (code: {:?})", message, code);
-
-        let error_category = match code.as_deref() {
-            Some("timeout") | Some("TIMEOUT") => "timeout",
-            Some("permission_denied") | Some("PERMISSION_DENIED") => "permission_denied",
-            Some("not_found") | Some("NOT_FOUND") => "not_found",
-            Some("cancelled") | Some("CANCELLED") => "cancelled",
-            Some("rate_limit") | Some("RATE_LIMIT") => "rate_limit",
-            _ => "error",
+    async fn send_exec_command_begin(&mut self, event: CodexExecCommandBeginEvent) -> Result<()> {
+        let arguments = json!({
+            "command": event.command,
+            "cwd": event.cwd,
+            "parsed_cmd": event.parsed_cmd,
+        });
+        let params = extract_shell_params(EXEC_TOOL_NAME, &arguments);
+        let title = match params.command.as_deref() {
+            Some(cmd) => shell_tool_title(EXEC_TOOL_NAME, cmd, params.workdir.as_deref()),
+            None => EXEC_TOOL_NAME.to_string(),
         };
+        let locations = shell_locations(&params);
+        let raw_input = shell_raw_input(&arguments, &params);
+
+        self.last_tool_call_id = Some(event.call_id.clone());
+        self.exec_outputs
+            .insert(event.call_id.clone(), ExecOutputBuffers::default());
+        self.tool_calls.insert(
+            event.call_id.clone(),
+            ToolCallRecord {
+                status: ToolCallStatus::InProgress,
+                title: title.clone(),
+                kind: ToolKind::Execute,
+                locations: locations.clone(),
+                raw_input: Some(raw_input.clone()),
+            },
+        );
 
-        if let Some(tool_id) = self.last_tool_call_id.clone() {
-            let error_text = match error_category {
-                "timeout" => format!("Tool execution timed out: {}", message),
-                "permission_denied" => format!("Permission denied: {}", message),
-                "not_found" => format!("Resource not found: {}", message),
-                "cancelled" => format!("Tool execution cancelled: {}", message),
-                "rate_limit" => format!("Rate limit exceeded: {}", message),
-                _ => {
-                    if let Some(ref code) = code {
-                        format!("Error [{}]: {}", code, message)
-                    } else {
-                        format!("Error: {}", message)
-                    }
-                }
-            };
+        debug!("Sending exec tool call {} ({})", event.call_id, title);
+        let notification = self.build_notification(SessionUpdate::ToolCall(ToolCall {
+            id: ToolCallId(Arc::from(event.call_id.as_str())),
+            title,
+            kind: ToolKind::Execute,
+            status: ToolCallStatus::InProgress,
+            content: Vec::new(),
+            locations,
+            raw_input: Some(raw_input),
+            raw_output: None,
+            meta: None,
+        }));
+        self.tx
+            .send(notification)
+            .context("Failed to send exec tool call")?;
+        Ok(())
+    }
 
-            let mut fields = ToolCallUpdateFields {
-                status: Some(ToolCallStatus::Failed),
-                content: Some(vec![ToolCallContent::from(error_text)]),
-                raw_output: Some(acp_error_value(error_category, &message, code.as_deref())),
+    async fn send_exec_command_output(
+        &mut self,
+        event: CodexExecCommandOutputDeltaEvent,
+        stream: CodexExecOutputStream,
+    ) -> Result<()> {
+        let chunk = decode_exec_chunk(&event.chunk);
+        if chunk.is_empty() {
+            return Ok(());
+        }
+
+        if !self.tool_calls.contains_key(&event.call_id) {
+            self.send_exec_command_begin(CodexExecCommandBeginEvent {
+                call_id: event.call_id.clone(),
                 ..Default::default()
-            };
+            })
+            .await?;
+        }
 
-            if let Some(record) = self.tool_calls.get_mut(&tool_id) {
-                record.status = ToolCallStatus::Failed;
-                if let Some(raw_input) = record.raw_input.clone() {
-                    fields.raw_input = Some(raw_input);
-                }
-                if !record.locations.is_empty() {
-                    fields.locations = Some(record.locations.clone());
-                }
-                fields.title = Some(record.title.clone());
-                fields.kind = Some(record.kind);
+        let buffers = self.exec_outputs.entry(event.call_id.clone()).or_default();
+        match stream {
+            CodexExecOutputStream::Stdout => buffers.stdout.push_str(&chunk),
+            CodexExecOutputStream::Stderr => buffers.stderr.push_str(&chunk),
+        }
+        let content = exec_output_content(&buffers.stdout, &buffers.stderr);
+
+        let notification = self.build_notification(SessionUpdate::ToolCallUpdate(ToolCallUpdate {
+            id: ToolCallId(Arc::from(event.call_id.as_str())),
+            fields: ToolCallUpdateFields {
+                content: Some(content),
+                ..Default::default()
+            },
+            meta: None,
+        }));
+        self.tx
+            .send(notification)
+            .context("Failed to send exec output update")?;
+        Ok(())
+    }
+
+    async fn send_exec_command_end(&mut self, event: CodexExecCommandEndEvent) -> Result<()> {
+        if !self.tool_calls.contains_key(&event.call_id) {
+            self.send_exec_command_begin(CodexExecCommandBeginEvent {
+                call_id: event.call_id.clone(),
+                ..Default::default()
+            })
+            .await?;
+        }
+
+        let buffered = self.exec_outputs.remove(&event.call_id).unwrap_or_default();
+        let mut stdout = if event.stdout.is_empty() {
+            buffered.stdout
+        } else {
+            event.stdout.clone()
+        };
+        let stderr = if event.stderr.is_empty() {
+            buffered.stderr
+        } else {
+            event.stderr.clone()
+        };
+        if stdout.is_empty() && stderr.is_empty() {
+            if let Some(aggregated) = event.aggregated_output.as_ref() {
+                stdout = aggregated.clone();
+            }
+        }
+
+        // An unknown exit status is not reported as success.
+        let status = if event.exit_code == Some(0) {
+            ToolCallStatus::Completed
+        } else {
+            ToolCallStatus::Failed
+        };
+        let duration_ms = event.duration.as_ref().and_then(duration_ms_from_value);
+
+        let mut content = exec_output_content(&stdout, &stderr);
+        match event.exit_code {
+            Some(0) => {}
+            Some(code) => content.push(ToolCallContent::from(format!("[exit code: {}]", code))),
+            None => content.push(ToolCallContent::from("[exit code: unknown]")),
+        }
+        if status == ToolCallStatus::Completed && content.is_empty() {
+            content.push(ToolCallContent::from(
+                "Tool execution completed successfully",
+            ));
+        }
+
+        let raw_output = json!({
+            "exit_code": event.exit_code,
+            "duration_ms": duration_ms,
+            "stdout": stdout,
+            "stderr": stderr,
+            "formatted_output": event.formatted_output,
+        });
+
+        if let Some(record) = self.tool_calls.get_mut(&event.call_id) {
+            record.status = status;
+        }
+
+        let notification = self.build_notification(SessionUpdate::ToolCallUpdate(ToolCallUpdate {
+            id: ToolCallId(Arc::from(event.call_id.as_str())),
+            fields: ToolCallUpdateFields {
+                status: Some(status),
+                content: Some(content),
+                raw_output: Some(raw_output),
+                ..Default::default()
+            },
+            meta: None,
+        }));
+        self.tx
+            .send(notification)
+            .context("Failed to send exec completion update")?;
+        // A terminal shows the output and exit status itself.
+        self.spawn_terminal_mirror(
+            &event.call_id,
+            &stdout,
+            &stderr,
+            event.exit_code.unwrap_or(1),
+            std::iter::empty(),
+        );
+        Ok(())
+    }
+
+    /// Mirror finished command output into a client terminal.
+    ///
+    /// Codex has already run the command in its sandbox, so the terminal only
+    /// prints the captured streams and exits with the same status rather than
+    /// running the command a second time. The completion update keeps its text
+    /// preview; the mirror runs on its own task, off the stdout reader, and a
+    /// follow-up update swaps the preview for the terminal (then `extra`) once
+    /// it has finished printing. Nothing happens when terminals are unsupported.
+    fn spawn_terminal_mirror(
+        &self,
+        call_id: &str,
+        stdout: &str,
+        stderr: &str,
+        exit_code: i32,
+        extra: impl IntoIterator<Item = ToolCallContent>,
+    ) {
+        if !self.terminals.is_supported() {
+            return;
+        }
+
+        let request = CreateTerminalRequest {
+            session_id: self.session_id.clone(),
+            command: "sh".to_string(),
+            args: vec![
+                "-c".to_string(),
+                TERMINAL_MIRROR_SCRIPT.to_string(),
+                "acplb-exec".to_string(),
+                truncate_output(stdout, MAX_TERMINAL_MIRROR_BYTES),
+                truncate_output(stderr, MAX_TERMINAL_MIRROR_BYTES),
+                exit_code.rem_euclid(256).to_string(),
+            ],
+            env: Vec::new(),
+            cwd: None,
+            output_byte_limit: None,
+            meta: None,
+        };
+        tokio::spawn(mirror_to_terminal(
+            self.terminals.clone(),
+            request,
+            ToolCallId(Arc::from(call_id)),
+            extra.into_iter().collect(),
+            self.tx.clone(),
+        ));
+    }
+
+    async fn send_mcp_tool_call_begin(&mut self, event: CodexMcpToolCallBeginEvent) -> Result<()> {
+        let title = mcp_tool_title(&event.invocation);
+        let kind = map_tool_kind(&event.invocation.tool);
+        let raw_input = event.invocation.arguments.clone().unwrap_or(Value::Null);
+
+        self.last_tool_call_id = Some(event.call_id.clone());
+        self.tool_calls.insert(
+            event.call_id.clone(),
+            ToolCallRecord {
+                status: ToolCallStatus::InProgress,
+                title: title.clone(),
+                kind,
+                locations: Vec::new(),
+                raw_input: Some(raw_input.clone()),
+            },
+        );
+
+        debug!("Sending MCP tool call {} ({})", event.call_id, title);
+        let notification = self.build_notification(SessionUpdate::ToolCall(ToolCall {
+            id: ToolCallId(Arc::from(event.call_id.as_str())),
+            title,
+            kind,
+            status: ToolCallStatus::InProgress,
+            content: Vec::new(),
+            locations: Vec::new(),
+            raw_input: Some(raw_input),
+            raw_output: None,
+            meta: None,
+        }));
+        self.tx
+            .send(notification)
+            .context("Failed to send MCP tool call")?;
+        Ok(())
+    }
+
+    async fn send_mcp_tool_call_end(&mut self, event: CodexMcpToolCallEndEvent) -> Result<()> {
+        if !self.tool_calls.contains_key(&event.call_id) {
+            self.send_mcp_tool_call_begin(CodexMcpToolCallBeginEvent {
+                call_id: event.call_id.clone(),
+                invocation: event.invocation.clone(),
+            })
+            .await?;
+        }
+
+        let (status, content) = match mcp_call_result(&event.result) {
+            Ok(result) => {
+                let is_error = result
+                    .get("isError")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false);
+                let mut content = mcp_result_content(result);
+                if content.is_empty() {
+                    content.push(ToolCallContent::from(if is_error {
+                        "Tool execution failed"
+                    } else {
+                        "Tool execution completed successfully"
+                    }));
+                }
+                let status = if is_error {
+                    ToolCallStatus::Failed
+                } else {
+                    ToolCallStatus::Completed
+                };
+                (status, content)
+            }
+            Err(message) => (
+                ToolCallStatus::Failed,
+                vec![ToolCallContent::from(format!("[Error]: {}", message))],
+            ),
+        };
+
+        let raw_output = json!({
+            "result": event.result,
+            "duration_ms": event.duration.as_ref().and_then(duration_ms_from_value),
+        });
+
+        if let Some(record) = self.tool_calls.get_mut(&event.call_id) {
+            record.status = status;
+        }
+
+        let notification = self.build_notification(SessionUpdate::ToolCallUpdate(ToolCallUpdate {
+            id: ToolCallId(Arc::from(event.call_id.as_str())),
+            fields: ToolCallUpdateFields {
+                status: Some(status),
+                content: Some(content),
+                raw_output: Some(raw_output),
+                ..Default::default()
+            },
+            meta: None,
+        }));
+        self.tx
+            .send(notification)
+            .context("Failed to send MCP tool call completion")?;
+        Ok(())
+    }
+
+    async fn send_web_search_begin(&mut self, event: CodexWebSearchBeginEvent) -> Result<()> {
+        let title = web_search_title(event.query.as_deref());
+        let raw_input = json!({ "query": event.query });
+
+        self.last_tool_call_id = Some(event.call_id.clone());
+        self.tool_calls.insert(
+            event.call_id.clone(),
+            ToolCallRecord {
+                status: ToolCallStatus::InProgress,
+                title: title.clone(),
+                kind: ToolKind::Fetch,
+                locations: Vec::new(),
+                raw_input: Some(raw_input.clone()),
+            },
+        );
+
+        debug!("Sending web search tool call {} ({})", event.call_id, title);
+        let notification = self.build_notification(SessionUpdate::ToolCall(ToolCall {
+            id: ToolCallId(Arc::from(event.call_id.as_str())),
+            title,
+            kind: ToolKind::Fetch,
+            status: ToolCallStatus::InProgress,
+            content: Vec::new(),
+            locations: Vec::new(),
+            raw_input: Some(raw_input),
+            raw_output: None,
+            meta: None,
+        }));
+        self.tx
+            .send(notification)
+            .context("Failed to send web search tool call")?;
+        Ok(())
+    }
+
+    async fn send_web_search_end(&mut self, event: CodexWebSearchEndEvent) -> Result<()> {
+        if !self.tool_calls.contains_key(&event.call_id) {
+            self.send_web_search_begin(CodexWebSearchBeginEvent {
+                call_id: event.call_id.clone(),
+                query: event.query.clone(),
+            })
+            .await?;
+        }
+
+        let mut fields = ToolCallUpdateFields {
+            status: Some(ToolCallStatus::Completed),
+            ..Default::default()
+        };
+
+        // Newer Codex only reveals the query once the search finishes.
+        let title = web_search_title(event.query.as_deref());
+        if let Some(record) = self.tool_calls.get_mut(&event.call_id) {
+            record.status = ToolCallStatus::Completed;
+            if event.query.is_some() && record.title != title {
+                record.title = title.clone();
+                fields.title = Some(title);
+                fields.raw_input = Some(json!({ "query": event.query }));
+            }
+        }
+
+        let mut content: Vec<ToolCallContent> = event
+            .results
+            .iter()
+            .filter_map(web_search_result_link)
+            .map(|link| ToolCallContent::Content {
+                content: ContentBlock::ResourceLink(link),
+            })
+            .collect();
+        if content.is_empty() {
+            content.push(ToolCallContent::from("Search completed"));
+        }
+        fields.content = Some(content);
+        fields.raw_output = Some(json!({
+            "query": event.query,
+            "results": event.results,
+        }));
+
+        let notification = self.build_notification(SessionUpdate::ToolCallUpdate(ToolCallUpdate {
+            id: ToolCallId(Arc::from(event.call_id.as_str())),
+            fields,
+            meta: None,
+        }));
+        self.tx
+            .send(notification)
+            .context("Failed to send web search completion")?;
+        Ok(())
+    }
+
+    async fn send_patch_apply_begin(&mut self, event: CodexPatchApplyBeginEvent) -> Result<()> {
+        let summary = PatchSummary::from_changes(&event.changes);
+        let raw_input = json!({
+            "changes": event.changes,
+            "auto_approved": event.auto_approved,
+        });
+
+        self.last_tool_call_id = Some(event.call_id.clone());
+        self.patch_diffs
+            .insert(event.call_id.clone(), summary.content.clone());
+        self.tool_calls.insert(
+            event.call_id.clone(),
+            ToolCallRecord {
+                status: ToolCallStatus::InProgress,
+                title: summary.title.clone(),
+                kind: summary.kind,
+                locations: summary.locations.clone(),
+                raw_input: Some(raw_input.clone()),
+            },
+        );
+
+        debug!(
+            "Sending patch tool call {} ({})",
+            event.call_id, summary.title
+        );
+        let notification = self.build_notification(SessionUpdate::ToolCall(ToolCall {
+            id: ToolCallId(Arc::from(event.call_id.as_str())),
+            title: summary.title,
+            kind: summary.kind,
+            status: ToolCallStatus::InProgress,
+            content: summary.content,
+            locations: summary.locations,
+            raw_input: Some(raw_input),
+            raw_output: None,
+            meta: None,
+        }));
+        self.tx
+            .send(notification)
+            .context("Failed to send patch tool call")?;
+        Ok(())
+    }
+
+    async fn send_patch_apply_end(&mut self, event: CodexPatchApplyEndEvent) -> Result<()> {
+        let status = if event.success {
+            ToolCallStatus::Completed
+        } else {
+            ToolCallStatus::Failed
+        };
+
+        // Diffs stay as-is on success; a failure appends Codex's error output.
+        let content = if event.success {
+            None
+        } else {
+            let mut content = self
+                .patch_diffs
+                .get(&event.call_id)
+                .cloned()
+                .unwrap_or_default();
+            let message = if event.stderr.is_empty() {
+                &event.stdout
+            } else {
+                &event.stderr
+            };
+            if !message.is_empty() {
+                content.push(ToolCallContent::from(truncate_output(
+                    message,
+                    MAX_OUTPUT_PREVIEW_BYTES,
+                )));
+            }
+            Some(content)
+        };
+        self.patch_diffs.remove(&event.call_id);
+
+        if let Some(record) = self.tool_calls.get_mut(&event.call_id) {
+            record.status = status;
+        }
+
+        let notification = self.build_notification(SessionUpdate::ToolCallUpdate(ToolCallUpdate {
+            id: ToolCallId(Arc::from(event.call_id.as_str())),
+            fields: ToolCallUpdateFields {
+                status: Some(status),
+                content,
+                raw_output: Some(json!({
+                    "success": event.success,
+                    "stdout": event.stdout,
+                    "stderr": event.stderr,
+                })),
+                ..Default::default()
+            },
+            meta: None,
+        }));
+        self.tx
+            .send(notification)
+            .context("Failed to send patch completion update")?;
+        Ok(())
+    }
+
+    fn forward_approval(&self, request: CodexApprovalRequest) {
+        match self.approvals.as_ref() {
+            Some(tx) => {
+                if tx.send(request).is_err() {
+                    warn!("Approval channel closed; Codex approval request dropped");
+                }
+            }
+            None => warn!(
+                "Codex requested approval for {} but no approval handler is attached",
+                request.tool_call.id.0
+            ),
+        }
+    }
+
+    async fn handle_error(&mut self, message: String, code: Option<String>) -> Result<()> {
+        error!("Codex error: {} (code: {:?})", message, code);
+
+        let error_category = match code.as_deref() {
+            Some("timeout") | Some("TIMEOUT") => "timeout",
+            Some("permission_denied") | Some("PERMISSION_DENIED") => "permission_denied",
+            Some("not_found") | Some("NOT_FOUND") => "not_found",
+            Some("cancelled") | Some("CANCELLED") => "cancelled",
+            Some("rate_limit") | Some("RATE_LIMIT") => "rate_limit",
+            _ => "error",
+        };
+
+        if let Some(tool_id) = self.last_tool_call_id.clone() {
+            let error_text = match error_category {
+                "timeout" => format!("Tool execution timed out: {}", message),
+                "permission_denied" => format!("Permission denied: {}", message),
+                "not_found" => format!("Resource not found: {}", message),
+                "cancelled" => format!("Tool execution cancelled: {}", message),
+                "rate_limit" => format!("Rate limit exceeded: {}", message),
+                _ => {
+                    if let Some(ref code) = code {
+                        format!("Error [{}]: {}", code, message)
+                    } else {
+                        format!("Error: {}", message)
+                    }
+                }
+            };
+
+            let mut fields = ToolCallUpdateFields {
+                status: Some(ToolCallStatus::Failed),
+                content: Some(vec![ToolCallContent::from(error_text)]),
+                raw_output: Some(acp_error_value(error_category, &message, code.as_deref())),
+                ..Default::default()
+            };
+
+            if let Some(record) = self.tool_calls.get_mut(&tool_id) {
+                record.status = ToolCallStatus::Failed;
+                if let Some(raw_input) = record.raw_input.clone() {
+                    fields.raw_input = Some(raw_input);
+                }
+                if !record.locations.is_empty() {
+                    fields.locations = Some(record.locations.clone());
+                }
+                fields.title = Some(record.title.clone());
+                fields.kind = Some(record.kind);
 
                 let notification =
                     self.build_notification(SessionUpdate::ToolCallUpdate(ToolCallUpdate {
@@ -729,16 +1697,16 @@ impl CodexStreamManager {
                 self.tx
                     .send(notification)
                     .context("Failed to send error update")?;
-            } else {
-                let notification =
-                    self.build_notification(SessionUpdate::ToolCallUpdate(ToolCallUpdate {
-                        id: ToolCallId(Arc::from(tool_id.as_str())),
-                        fields,
-                        meta: None,
-                    }));
-                self.tx
-                    .send(notification)
-                    .context("Failed to send error update")?;
+            } else {
+                let notification =
+                    self.build_notification(SessionUpdate::ToolCallUpdate(ToolCallUpdate {
+                        id: ToolCallId(Arc::from(tool_id.as_str())),
+                        fields,
+                        meta: None,
+                    }));
+                self.tx
+                    .send(notification)
+                    .context("Failed to send error update")?;
             }
         } else {
             let error_msg = match error_category {
@@ -773,6 +1741,99 @@ impl CodexStreamManager {
     pub fn is_finalized(&self) -> bool {
         self.finalized
     }
+
+    /// Take the turn that just finished, if any.
+    pub fn take_completed_turn(&mut self) -> Option<CodexTurnCompletion> {
+        self.completed_submission.take()
+    }
+
+    /// Token usage of the current turn, once Codex has reported any.
+    pub fn turn_usage(&self) -> Option<CodexTurnUsage> {
+        self.turn_usage_seen.then(|| CodexTurnUsage {
+            turn: self.session_usage.saturating_sub(self.turn_start_usage),
+            session_total_tokens: self.session_usage.total_tokens,
+            context_tokens: self.context_tokens,
+            model_context_window: self.model_context_window,
+        })
+    }
+
+    fn record_token_count(&mut self, event: CodexTokenCountEvent) {
+        match event.info {
+            Some(info) => {
+                self.session_usage = info.total_token_usage;
+                self.context_tokens = Some(info.last_token_usage.context_tokens());
+                if info.model_context_window.is_some() {
+                    self.model_context_window = info.model_context_window;
+                }
+            }
+            None => {
+                self.session_usage = self.session_usage.saturating_add(event.usage);
+                self.context_tokens = Some(event.usage.context_tokens());
+            }
+        }
+        self.turn_usage_seen = true;
+        trace!("Token usage updated: {:?}", self.session_usage);
+    }
+
+    /// Reset per-turn streaming state (finalization, chunk dedup, exec buffers, usage).
+    fn begin_turn(&mut self) {
+        self.finalized = false;
+        self.last_text_chunk = None;
+        self.exec_outputs.clear();
+        self.turn_start_usage = self.session_usage;
+        self.turn_usage_seen = false;
+        self.turn_diff = None;
+    }
+
+    /// Emit the turn's aggregated `turn_diff` as one completed Edit tool call.
+    async fn send_turn_diff_summary(&mut self, submission_id: &str) -> Result<()> {
+        let Some(unified_diff) = self.turn_diff.take() else {
+            return Ok(());
+        };
+        let diffs = parse_turn_diff(&unified_diff);
+        if diffs.is_empty() {
+            return Ok(());
+        }
+
+        let title = match diffs.as_slice() {
+            [single] => format!("Turn diff: {}", single.path.display()),
+            _ => format!("Turn diff: {} files", diffs.len()),
+        };
+        let locations = diffs
+            .iter()
+            .map(|diff| ToolCallLocation {
+                path: diff.path.clone(),
+                line: None,
+                meta: None,
+            })
+            .collect();
+        let content = diffs
+            .into_iter()
+            .map(|diff| ToolCallContent::Diff { diff })
+            .collect();
+        let id = if submission_id.is_empty() {
+            "turn_diff".to_string()
+        } else {
+            format!("turn_diff-{}", submission_id)
+        };
+
+        debug!("Sending turn diff summary {} ({})", id, title);
+        let notification = self.build_notification(SessionUpdate::ToolCall(ToolCall {
+            id: ToolCallId(Arc::from(id.as_str())),
+            title,
+            kind: ToolKind::Edit,
+            status: ToolCallStatus::Completed,
+            content,
+            locations,
+            raw_input: None,
+            raw_output: Some(json!({ "unified_diff": unified_diff })),
+            meta: None,
+        }));
+        self.tx
+            .send(notification)
+            .context("Failed to send turn diff summary")?;
+        Ok(())
+    }
 }
 
 fn plan_entry_from_codex(item: CodexPlanItem) -> PlanEntry {
@@ -788,6 +1849,601 @@ fn plan_entry_from_codex(item: CodexPlanItem) -> PlanEntry {
     }
 }
 
+fn shell_tool_title(name: &str, command: &str, workdir: Option<&str>) -> String {
+    match workdir {
+        Some(workdir) => format!("{}: {} (in {})", name, command, workdir),
+        None => format!("{}: {}", name, command),
+    }
+}
+
+fn shell_locations(params: &ExtractedShellParams) -> Vec<ToolCallLocation> {
+    params
+        .workdir
+        .as_ref()
+        .map(|dir| {
+            vec![ToolCallLocation {
+                path: PathBuf::from(dir),
+                line: None,
+                meta: None,
+            }]
+        })
+        .unwrap_or_default()
+}
+
+fn shell_raw_input(arguments: &Value, params: &ExtractedShellParams) -> Value {
+    let mut enhanced = json!({
+        "original": arguments.clone(),
+        "extracted": {}
+    });
+
+    if let Some(obj) = enhanced["extracted"].as_object_mut() {
+        if let Some(ref cmd) = params.command {
+            obj.insert("command".to_string(), json!(cmd));
+        }
+        if let Some(ref workdir) = params.workdir {
+            obj.insert("workdir".to_string(), json!(workdir));
+        }
+        if let Some(timeout) = params.timeout_ms {
+            obj.insert("timeout_ms".to_string(), json!(timeout));
+        }
+        if let Some(escalated) = params.with_escalated_permissions {
+            obj.insert("with_escalated_permissions".to_string(), json!(escalated));
+        }
+        if let Some(ref justification) = params.justification {
+            obj.insert("justification".to_string(), json!(justification));
+        }
+    }
+
+    enhanced
+}
+
+/// Run `request` in a client terminal and embed it in `tool_call_id` once it
+/// has printed; the client is given `TERMINAL_MIRROR_TIMEOUT` for each step.
+async fn mirror_to_terminal(
+    terminals: ClientTerminals,
+    request: CreateTerminalRequest,
+    tool_call_id: ToolCallId,
+    extra: Vec<ToolCallContent>,
+    tx: mpsc::UnboundedSender<SessionNotification>,
+) {
+    let session_id = request.session_id.clone();
+    let terminal =
+        match tokio::time::timeout(TERMINAL_MIRROR_TIMEOUT, terminals.create(request)).await {
+            Ok(Ok(terminal)) => terminal,
+            outcome => {
+                warn!("Failed to create client terminal: {:?}", outcome);
+                return;
+            }
+        };
+
+    match tokio::time::timeout(TERMINAL_MIRROR_TIMEOUT, terminal.wait_for_exit()).await {
+        Ok(Ok(_)) => {
+            let mut content = vec![terminal.content()];
+            content.extend(extra);
+            let notification = SessionNotification {
+                session_id,
+                update: SessionUpdate::ToolCallUpdate(ToolCallUpdate {
+                    id: tool_call_id,
+                    fields: ToolCallUpdateFields {
+                        content: Some(content),
+                        ..Default::default()
+                    },
+                    meta: None,
+                }),
+                meta: None,
+            };
+            if tx.send(notification).is_err() {
+                debug!(
+                    "Session closed before terminal {} was embedded",
+                    terminal.id().0
+                );
+            }
+        }
+        outcome => {
+            warn!(
+                "Client terminal {} did not finish: {:?}",
+                terminal.id().0,
+                outcome
+            );
+            if let Err(err) = terminal.kill().await {
+                debug!("Failed to kill client terminal: {:?}", err);
+            }
+        }
+    }
+
+    // Tool calls embedding the terminal keep its output after release.
+    let id = terminal.id().0.clone();
+    if let Err(err) = terminal.release().await {
+        debug!("Failed to release client terminal {}: {:?}", id, err);
+    }
+}
+
+/// Stdout, stderr and exit code of a finished shell tool call's output.
+fn shell_output_parts(output: &Value, status: ToolCallStatus) -> (&str, &str, i32) {
+    let stdout = output
+        .as_str()
+        .or_else(|| output.get("stdout").and_then(Value::as_str))
+        .unwrap_or_default();
+    let stderr = output
+        .get("stderr")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let exit_code = output
+        .get("exit_code")
+        .and_then(Value::as_i64)
+        .and_then(|code| i32::try_from(code).ok())
+        .unwrap_or(if status == ToolCallStatus::Failed {
+            1
+        } else {
+            0
+        });
+    (stdout, stderr, exit_code)
+}
+
+/// Build the stdout/stderr preview blocks for an exec tool call, keeping the
+/// two streams in separate content entries.
+fn exec_output_content(stdout: &str, stderr: &str) -> Vec<ToolCallContent> {
+    let mut content = Vec::new();
+    if !stdout.is_empty() {
+        content.push(ToolCallContent::from(truncate_output(
+            stdout,
+            MAX_OUTPUT_PREVIEW_BYTES,
+        )));
+    }
+    if !stderr.is_empty() {
+        content.push(ToolCallContent::from(format!(
+            "[stderr]:\n{}",
+            truncate_output(stderr, MAX_OUTPUT_PREVIEW_BYTES)
+        )));
+    }
+    content
+}
+
+/// Title shown for an MCP tool call: `server.tool`.
+fn mcp_tool_title(invocation: &CodexMcpInvocation) -> String {
+    if invocation.server.is_empty() {
+        invocation.tool.clone()
+    } else {
+        format!("{}.{}", invocation.server, invocation.tool)
+    }
+}
+
+/// Title shown for a web search tool call: the query once known.
+fn web_search_title(query: Option<&str>) -> String {
+    match query.map(str::trim).filter(|q| !q.is_empty()) {
+        Some(query) => query.to_string(),
+        None => "Web search".to_string(),
+    }
+}
+
+/// Build a resource link for a web search result that carries a URL.
+fn web_search_result_link(result: &Value) -> Option<ResourceLink> {
+    let (uri, title, description) = match result {
+        Value::String(url) => (url.clone(), None, None),
+        Value::Object(obj) => {
+            let uri = obj
+                .get("url")
+                .or_else(|| obj.get("uri"))
+                .and_then(Value::as_str)?
+                .to_string();
+            let title = obj
+                .get("title")
+                .or_else(|| obj.get("name"))
+                .and_then(Value::as_str)
+                .map(String::from);
+            let description = obj
+                .get("snippet")
+                .or_else(|| obj.get("description"))
+                .and_then(Value::as_str)
+                .map(String::from);
+            (uri, title, description)
+        }
+        _ => return None,
+    };
+    if !(uri.starts_with("http://") || uri.starts_with("https://")) {
+        return None;
+    }
+
+    Some(ResourceLink {
+        annotations: None,
+        description,
+        mime_type: None,
+        name: title.clone().unwrap_or_else(|| uri.clone()),
+        size: None,
+        title,
+        uri,
+        meta: None,
+    })
+}
+
+/// Unwrap Codex's `Result<CallToolResult, String>` serialisation.
+///
+/// A bare `CallToolResult` object (no `Ok`/`Err` wrapper) is accepted as success.
+fn mcp_call_result(result: &Value) -> std::result::Result<&Value, String> {
+    if let Some(ok) = result.get("Ok") {
+        return Ok(ok);
+    }
+    match result.get("Err") {
+        Some(Value::String(message)) => Err(message.clone()),
+        Some(other) => Err(other.to_string()),
+        None => Ok(result),
+    }
+}
+
+/// Convert MCP `CallToolResult.content` items into tool call content blocks.
+fn mcp_result_content(result: &Value) -> Vec<ToolCallContent> {
+    let Some(items) = result.get("content").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+    items
+        .iter()
+        .map(|item| match content_block_from_value(item) {
+            Some(ContentBlock::Text(text)) => {
+                ToolCallContent::from(truncate_output(&text.text, MAX_OUTPUT_PREVIEW_BYTES))
+            }
+            Some(block) => ToolCallContent::Content { content: block },
+            None => ToolCallContent::from(item.to_string()),
+        })
+        .collect()
+}
+
+/// Decode an exec output chunk into text.
+///
+/// The JSON type decides the encoding: strings are always base64 and arrays
+/// are raw bytes. A string that is not valid base64 is dropped rather than
+/// shown as-is, since plain text is never sent in this field.
+fn decode_exec_chunk(chunk: &Value) -> String {
+    match chunk {
+        Value::String(encoded) => match base64::engine::general_purpose::STANDARD.decode(encoded) {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(err) => {
+                warn!("Dropping exec output chunk that is not base64: {}", err);
+                String::new()
+            }
+        },
+        Value::Array(items) => {
+            let bytes: Vec<u8> = items
+                .iter()
+                .filter_map(|v| v.as_u64())
+                .filter_map(|b| u8::try_from(b).ok())
+                .collect();
+            String::from_utf8_lossy(&bytes).into_owned()
+        }
+        _ => String::new(),
+    }
+}
+
+/// Convert a Codex duration (`{secs, nanos}`, milliseconds, or `"1.5s"`) to milliseconds.
+fn duration_ms_from_value(value: &Value) -> Option<u64> {
+    match value {
+        Value::Object(obj) => {
+            let secs = obj.get("secs").and_then(|v| v.as_u64()).unwrap_or(0);
+            let nanos = obj.get("nanos").and_then(|v| v.as_u64()).unwrap_or(0);
+            Some(secs * 1000 + nanos / 1_000_000)
+        }
+        Value::Number(number) => number.as_u64(),
+        Value::String(text) => {
+            let text = text.trim();
+            if let Some(ms) = text.strip_suffix("ms") {
+                ms.trim().parse::<f64>().ok().map(|ms| ms as u64)
+            } else if let Some(secs) = text.strip_suffix('s') {
+                secs.trim().parse::<f64>().ok().map(|s| (s * 1000.0) as u64)
+            } else {
+                text.parse::<u64>().ok()
+            }
+        }
+        _ => None,
+    }
+}
+
+fn exec_approval_request(
+    approval_id: String,
+    event: CodexExecApprovalRequestEvent,
+) -> CodexApprovalRequest {
+    let arguments = json!({
+        "command": event.command,
+        "cwd": event.cwd,
+        "reason": event.reason,
+    });
+    let params = extract_shell_params(EXEC_TOOL_NAME, &arguments);
+    let title = match params.command.as_deref() {
+        Some(cmd) => shell_tool_title(EXEC_TOOL_NAME, cmd, params.workdir.as_deref()),
+        None => EXEC_TOOL_NAME.to_string(),
+    };
+
+    CodexApprovalRequest {
+        kind: CodexApprovalKind::Exec,
+        approval_id,
+        tool_call: ToolCallUpdate {
+            id: ToolCallId(Arc::from(event.call_id.as_str())),
+            fields: ToolCallUpdateFields {
+                kind: Some(ToolKind::Execute),
+                status: Some(ToolCallStatus::Pending),
+                title: Some(title),
+                content: event
+                    .reason
+                    .map(|reason| vec![ToolCallContent::from(reason)]),
+                locations: Some(shell_locations(&params)),
+                raw_input: Some(shell_raw_input(&arguments, &params)),
+                ..Default::default()
+            },
+            meta: None,
+        },
+    }
+}
+
+fn patch_approval_request(
+    approval_id: String,
+    event: CodexApplyPatchApprovalRequestEvent,
+) -> CodexApprovalRequest {
+    let summary = PatchSummary::from_changes(&event.changes);
+    let mut content = Vec::new();
+    if let Some(reason) = event.reason.clone() {
+        content.push(ToolCallContent::from(reason));
+    }
+    content.extend(summary.content);
+    let raw_input = json!({
+        "changes": event.changes,
+        "reason": event.reason,
+        "grant_root": event.grant_root,
+    });
+
+    CodexApprovalRequest {
+        kind: CodexApprovalKind::Patch,
+        approval_id,
+        tool_call: ToolCallUpdate {
+            id: ToolCallId(Arc::from(event.call_id.as_str())),
+            fields: ToolCallUpdateFields {
+                kind: Some(summary.kind),
+                status: Some(ToolCallStatus::Pending),
+                title: Some(summary.title),
+                content: Some(content),
+                locations: Some(summary.locations),
+                raw_input: Some(raw_input),
+                ..Default::default()
+            },
+            meta: None,
+        },
+    }
+}
+
+/// Tool call presentation derived from a Codex patch's file changes.
+struct PatchSummary {
+    title: String,
+    kind: ToolKind,
+    locations: Vec<ToolCallLocation>,
+    content: Vec<ToolCallContent>,
+}
+
+impl PatchSummary {
+    fn from_changes(changes: &HashMap<String, Value>) -> Self {
+        let mut paths: Vec<&String> = changes.keys().collect();
+        paths.sort();
+
+        let title = match paths.as_slice() {
+            [single] => format!("apply_patch: {}", single),
+            _ => format!("apply_patch: {} files", paths.len()),
+        };
+
+        let mut locations = Vec::new();
+        let mut content = Vec::new();
+        let mut all_deletes = !paths.is_empty();
+        let mut all_moves = !paths.is_empty();
+
+        for path in paths {
+            locations.push(ToolCallLocation {
+                path: PathBuf::from(path.as_str()),
+                line: None,
+                meta: None,
+            });
+
+            let Some(change) = changes.get(path).and_then(CodexFileChange::from_value) else {
+                all_deletes = false;
+                all_moves = false;
+                continue;
+            };
+
+            all_deletes &= matches!(change, CodexFileChange::Delete { .. });
+            all_moves &= matches!(
+                change,
+                CodexFileChange::Update {
+                    move_path: Some(_),
+                    ..
+                }
+            );
+
+            let diffs = match change {
+                CodexFileChange::Add { content } => vec![Diff {
+                    path: PathBuf::from(path.as_str()),
+                    old_text: None,
+                    new_text: content,
+                    meta: None,
+                }],
+                CodexFileChange::Delete { content } => vec![Diff {
+                    path: PathBuf::from(path.as_str()),
+                    old_text: Some(content.unwrap_or_default()),
+                    new_text: String::new(),
+                    meta: None,
+                }],
+                CodexFileChange::Update {
+                    unified_diff,
+                    move_path,
+                } => {
+                    let target = match move_path {
+                        Some(dest) => {
+                            locations.push(ToolCallLocation {
+                                path: PathBuf::from(&dest),
+                                line: None,
+                                meta: None,
+                            });
+                            PathBuf::from(dest)
+                        }
+                        None => PathBuf::from(path.as_str()),
+                    };
+                    split_unified_diff(&unified_diff)
+                        .into_iter()
+                        .map(|(old_text, new_text)| Diff {
+                            path: target.clone(),
+                            old_text: Some(old_text),
+                            new_text,
+                            meta: None,
+                        })
+                        .collect()
+                }
+            };
+            content.extend(diffs.into_iter().map(ToolCallContent::from));
+        }
+
+        let kind = if all_deletes {
+            ToolKind::Delete
+        } else if all_moves {
+            ToolKind::Move
+        } else {
+            ToolKind::Edit
+        };
+
+        Self {
+            title,
+            kind,
+            locations,
+            content,
+        }
+    }
+}
+
+/// Rebuild the old/new text of each hunk in a unified diff.
+///
+/// Only the hunk regions are available, so every hunk becomes its own partial
+/// before/after pair instead of gluing unrelated regions together. `---` and
+/// `+++` lines are file headers only before the first `@@`; inside a hunk they
+/// are removed/added lines. A diff without hunks yields one empty pair.
+fn split_unified_diff(unified_diff: &str) -> Vec<(String, String)> {
+    let mut hunks: Vec<(String, String)> = Vec::new();
+
+    for line in unified_diff.lines() {
+        if line.starts_with("@@") {
+            hunks.push(Default::default());
+            continue;
+        }
+        // Anything before the first hunk is a header.
+        let Some((old_text, new_text)) = hunks.last_mut() else {
+            continue;
+        };
+        if line.starts_with('\\') {
+            continue;
+        }
+        if let Some(removed) = line.strip_prefix('-') {
+            old_text.push_str(removed);
+            old_text.push('\n');
+        } else if let Some(added) = line.strip_prefix('+') {
+            new_text.push_str(added);
+            new_text.push('\n');
+        } else {
+            let context = line.strip_prefix(' ').unwrap_or(line);
+            old_text.push_str(context);
+            old_text.push('\n');
+            new_text.push_str(context);
+            new_text.push('\n');
+        }
+    }
+
+    if hunks.is_empty() {
+        hunks.push(Default::default());
+    }
+    hunks
+}
+
+/// Split a multi-file (git-style) unified diff into per-file ACP diffs.
+fn parse_turn_diff(unified_diff: &str) -> Vec<Diff> {
+    #[derive(Default)]
+    struct FileSection {
+        git_path: Option<String>,
+        old_path: Option<String>,
+        new_path: Option<String>,
+        added: bool,
+        deleted: bool,
+        hunks: String,
+    }
+
+    impl FileSection {
+        fn into_diffs(self) -> Vec<Diff> {
+            let Some(path) = self.new_path.or(self.old_path).or(self.git_path) else {
+                return Vec::new();
+            };
+            split_unified_diff(&self.hunks)
+                .into_iter()
+                .map(|(old_text, new_text)| Diff {
+                    path: PathBuf::from(&path),
+                    old_text: (!self.added).then_some(old_text),
+                    new_text: if self.deleted {
+                        String::new()
+                    } else {
+                        new_text
+                    },
+                    meta: None,
+                })
+                .collect()
+        }
+    }
+
+    fn diff_path(header: &str) -> Option<String> {
+        let path = header.split('\t').next().unwrap_or(header).trim();
+        if path == "/dev/null" {
+            return None;
+        }
+        let path = path
+            .strip_prefix("a/")
+            .or_else(|| path.strip_prefix("b/"))
+            .unwrap_or(path);
+        Some(path.to_string())
+    }
+
+    let mut files = Vec::new();
+    let mut current: Option<FileSection> = None;
+
+    let mut lines = unified_diff.lines().peekable();
+    while let Some(line) = lines.next() {
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            files.extend(current.take());
+            current = Some(FileSection {
+                git_path: rest.split_once(" b/").map(|(_, path)| path.to_string()),
+                ..Default::default()
+            });
+            continue;
+        }
+
+        // A `---`/`+++` pair is a file header; a lone `--- x` is a removed `-- x` line.
+        let header = line
+            .strip_prefix("--- ")
+            .zip(lines.peek().and_then(|next| next.strip_prefix("+++ ")));
+        if let Some((old, new)) = header {
+            lines.next();
+            // Plain diffs have no `diff --git` line; a header after hunks starts a file.
+            if current.as_ref().is_none_or(|file| !file.hunks.is_empty()) {
+                files.extend(current.take());
+                current = Some(FileSection::default());
+            }
+            if let Some(file) = current.as_mut() {
+                file.old_path = diff_path(old);
+                file.new_path = diff_path(new);
+                file.added = file.old_path.is_none();
+                file.deleted = file.new_path.is_none();
+            }
+        } else if let Some(file) = current.as_mut() {
+            if line.starts_with("@@") || !file.hunks.is_empty() {
+                file.hunks.push_str(line);
+                file.hunks.push('\n');
+            }
+        }
+    }
+    files.extend(current);
+
+    files
+        .into_iter()
+        .flat_map(FileSection::into_diffs)
+        .collect()
+}
+
 fn available_command_from_tool(key: &str, tool: CodexToolDefinition) -> Option<AvailableCommand> {
     let name = key.to_string();
     let description = tool
@@ -1009,6 +2665,7 @@ pub async fn stream_codex_output<R>(
     reader: R,
     session_id: SessionId,
     tx: mpsc::UnboundedSender<SessionNotification>,
+    approvals: Option<mpsc::UnboundedSender<CodexApprovalRequest>>,
 ) -> Result<()>
 where
     R: tokio::io::AsyncRead + Unpin,
@@ -1016,6 +2673,9 @@ where
     let mut reader = BufReader::new(reader);
     let mut line = String::new();
     let mut manager = CodexStreamManager::new(session_id, tx);
+    if let Some(approvals) = approvals {
+        manager = manager.with_approvals(approvals);
+    }
 
     loop {
         line.clear();
@@ -1041,6 +2701,71 @@ where
     Ok(())
 }
 
+/// Stream a session-scoped Codex process until its stdout closes.
+///
+/// Unlike `stream_codex_output`, the reader outlives individual turns: the
+/// submission id and token usage of every `task_complete` are reported on
+/// `completions` so the adapter can end the matching prompt, and the model from `session_configured`
+/// is published on `configured_model`. Finished shell output is mirrored into
+/// client terminals when `terminals` is supported.
+pub async fn stream_codex_session<R>(
+    reader: R,
+    session_id: SessionId,
+    tx: mpsc::UnboundedSender<SessionNotification>,
+    approvals: Option<mpsc::UnboundedSender<CodexApprovalRequest>>,
+    completions: mpsc::UnboundedSender<CodexTurnCompletion>,
+    configured_model: Option<watch::Sender<Option<String>>>,
+    terminals: ClientTerminals,
+) -> Result<()>
+where
+    R: tokio::io::AsyncRead + Unpin,
+{
+    let mut reader = BufReader::new(reader);
+    let mut line = String::new();
+    let mut manager = CodexStreamManager::new(session_id, tx).with_terminals(terminals);
+    if let Some(approvals) = approvals {
+        manager = manager.with_approvals(approvals);
+    }
+
+    loop {
+        line.clear();
+        let bytes_read = reader
+            .read_line(&mut line)
+            .await
+            .context("Failed to read from Codex stdout")?;
+
+        if bytes_read == 0 {
+            break;
+        }
+
+        if let Err(e) = manager.process_line(&line).await {
+            error!("Error processing Codex output line {}: {}", line.trim(), e);
+        }
+
+        if let (Some(reporter), Some(model)) =
+            (configured_model.as_ref(), manager.configured_model())
+        {
+            reporter.send_if_modified(|current| {
+                if current.as_deref() == Some(model) {
+                    return false;
+                }
+                *current = Some(model.to_string());
+                true
+            });
+        }
+
+        if let Some(completion) = manager.take_completed_turn() {
+            debug!("Codex turn {} complete", completion.submission_id);
+            if completions.send(completion).is_err() {
+                debug!("Turn completion receiver dropped");
+            }
+        }
+    }
+
+    debug!("Codex session stream closed");
+    Ok(())
+}
+
 /// Serialize a session notification to JSON line
 pub fn serialize_update(update: &SessionNotification) -> Result<String> {
     serde_json::to_string(update).context("Failed to serialize session notification")
@@ -0,0 +1,287 @@
+//! `codex-cli-acp doctor`: environment checks run without starting the ACP loop.
+
+use std::fmt;
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use acp_lazy_core::config::{BridgeConfig, ConfigLoader, NotifySection};
+
+use crate::codex_agent::{
+    codex_api_key, codex_auth_file, codex_credentials_present, resolve_forwarder_path,
+};
+
+/// Upper bound on `codex --version`.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Outcome of a single check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Ok,
+    Warn,
+    Fail,
+    Skip,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::Ok => "ok",
+            Self::Warn => "warn",
+            Self::Fail => "FAIL",
+            Self::Skip => "skip",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DoctorCheck {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+}
+
+impl DoctorCheck {
+    fn new(name: &'static str, status: CheckStatus, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            detail: detail.into(),
+        }
+    }
+}
+
+impl fmt::Display for DoctorCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{:>4}] {:<12} {}", self.status, self.name, self.detail)
+    }
+}
+
+/// Run every check against the configuration resolved for `project_dir`.
+pub async fn run_doctor(loader: &ConfigLoader, project_dir: Option<&Path>) -> Vec<DoctorCheck> {
+    let config = match loader.load(project_dir) {
+        Ok(config) => config,
+        Err(err) => {
+            return vec![DoctorCheck::new(
+                "config",
+                CheckStatus::Fail,
+                err.to_string(),
+            )];
+        }
+    };
+
+    vec![
+        DoctorCheck::new("config", CheckStatus::Ok, "configuration is valid"),
+        check_codex(&config).await,
+        check_credentials(),
+        check_forwarder(&config.notify),
+        check_notify_path(&config.notify),
+    ]
+}
+
+/// True when no check failed.
+pub fn all_passed(checks: &[DoctorCheck]) -> bool {
+    checks.iter().all(|check| check.status != CheckStatus::Fail)
+}
+
+async fn check_codex(config: &BridgeConfig) -> DoctorCheck {
+    let command = config.codex_command();
+    let Some(path) = resolve_executable(command) else {
+        return DoctorCheck::new(
+            "codex",
+            CheckStatus::Fail,
+            format!(
+                "`{}` not found (set --codex-path or [codex].command)",
+                command
+            ),
+        );
+    };
+
+    let output = tokio::time::timeout(
+        VERSION_TIMEOUT,
+        tokio::process::Command::new(&path)
+            .arg("--version")
+            .stdin(std::process::Stdio::null())
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await;
+    match output {
+        Ok(Ok(output)) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let version = stdout.lines().next().unwrap_or_default().trim();
+            DoctorCheck::new(
+                "codex",
+                CheckStatus::Ok,
+                format!("{} ({})", path.display(), version),
+            )
+        }
+        Ok(Ok(output)) => DoctorCheck::new(
+            "codex",
+            CheckStatus::Fail,
+            format!("{} --version exited with {}", path.display(), output.status),
+        ),
+        Ok(Err(err)) => DoctorCheck::new(
+            "codex",
+            CheckStatus::Fail,
+            format!("failed to run {}: {}", path.display(), err),
+        ),
+        Err(_) => DoctorCheck::new(
+            "codex",
+            CheckStatus::Fail,
+            format!("{} --version timed out", path.display()),
+        ),
+    }
+}
+
+fn check_credentials() -> DoctorCheck {
+    let auth_file = codex_auth_file();
+    if codex_credentials_present(codex_api_key().as_deref(), &auth_file) {
+        DoctorCheck::new("credentials", CheckStatus::Ok, "API key or login found")
+    } else {
+        // Clients can still log in through ACP `authenticate`.
+        DoctorCheck::new(
+            "credentials",
+            CheckStatus::Warn,
+            format!(
+                "no OPENAI_API_KEY and no {}; run `codex login`",
+                auth_file.display()
+            ),
+        )
+    }
+}
+
+fn check_forwarder(notify: &NotifySection) -> DoctorCheck {
+    let injected = notify.path.is_some()
+        && match notify.inject.as_deref().unwrap_or("auto") {
+            "never" => false,
+            "force" => true,
+            _ => notify.command.is_none(),
+        };
+    let forwarder = resolve_forwarder_path()
+        .ok()
+        .and_then(|path| resolve_executable(&path));
+
+    match (forwarder, injected) {
+        (Some(path), _) => {
+            DoctorCheck::new("forwarder", CheckStatus::Ok, path.display().to_string())
+        }
+        (None, true) => DoctorCheck::new(
+            "forwarder",
+            CheckStatus::Fail,
+            "acplb-notify-forwarder not found next to the binary or on PATH",
+        ),
+        (None, false) => DoctorCheck::new(
+            "forwarder",
+            CheckStatus::Skip,
+            "not found; only needed when the notify path is injected",
+        ),
+    }
+}
+
+fn check_notify_path(notify: &NotifySection) -> DoctorCheck {
+    let Some(path) = notify.path.as_deref() else {
+        return DoctorCheck::new("notify", CheckStatus::Skip, "no notify path configured");
+    };
+
+    match notify.kind.as_deref().unwrap_or("file") {
+        "fifo" => match std::fs::metadata(path) {
+            Ok(meta) if meta.file_type().is_fifo() => DoctorCheck::new(
+                "notify",
+                CheckStatus::Ok,
+                format!("fifo {}", path.display()),
+            ),
+            Ok(_) => DoctorCheck::new(
+                "notify",
+                CheckStatus::Fail,
+                format!("{} exists but is not a FIFO", path.display()),
+            ),
+            Err(err) => DoctorCheck::new(
+                "notify",
+                CheckStatus::Fail,
+                format!("fifo {}: {} (create it with mkfifo)", path.display(), err),
+            ),
+        },
+        _ => {
+            if path.exists() && !path.is_file() {
+                return DoctorCheck::new(
+                    "notify",
+                    CheckStatus::Fail,
+                    format!("{} exists but is not a regular file", path.display()),
+                );
+            }
+            let parent = path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            if parent.is_dir() {
+                DoctorCheck::new(
+                    "notify",
+                    CheckStatus::Ok,
+                    format!("file {}", path.display()),
+                )
+            } else {
+                DoctorCheck::new(
+                    "notify",
+                    CheckStatus::Fail,
+                    format!("directory {} does not exist", parent.display()),
+                )
+            }
+        }
+    }
+}
+
+/// Resolve a command the way a spawn would: paths as-is, bare names via PATH.
+fn resolve_executable(command: &str) -> Option<PathBuf> {
+    if command.contains(std::path::MAIN_SEPARATOR) {
+        let path = PathBuf::from(command);
+        path.is_file().then_some(path)
+    } else {
+        which::which(command).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notify(path: Option<PathBuf>, kind: Option<&str>) -> NotifySection {
+        NotifySection {
+            path,
+            kind: kind.map(str::to_string),
+            ..NotifySection::default()
+        }
+    }
+
+    #[test]
+    fn notify_path_checks() {
+        // ast-grep-ignore: rust-no-unwrap
+        let dir = tempfile::tempdir().unwrap();
+
+        let skipped = check_notify_path(&notify(None, None));
+        assert_eq!(skipped.status, CheckStatus::Skip);
+
+        let file = check_notify_path(&notify(Some(dir.path().join("notify.jsonl")), None));
+        assert_eq!(file.status, CheckStatus::Ok);
+
+        let missing_dir = check_notify_path(&notify(
+            Some(dir.path().join("missing").join("notify.jsonl")),
+            Some("file"),
+        ));
+        assert_eq!(missing_dir.status, CheckStatus::Fail);
+
+        let not_fifo = check_notify_path(&notify(Some(dir.path().to_path_buf()), Some("fifo")));
+        assert_eq!(not_fifo.status, CheckStatus::Fail);
+    }
+
+    #[test]
+    fn resolve_executable_handles_paths_and_names() {
+        // ast-grep-ignore: rust-no-unwrap
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("codex");
+        // ast-grep-ignore: rust-no-unwrap
+        assert!(resolve_executable(missing.to_str().unwrap()).is_none());
+        assert!(resolve_executable("sh").is_some());
+        assert!(resolve_executable("definitely-not-a-real-codex-binary").is_none());
+    }
+}
@@ -1,7 +1,9 @@
 //! Library interface for codex-cli-acp
 
+pub mod cli;
 pub mod codex_agent;
 pub mod codex_proto;
+pub mod doctor;
 pub mod notify_source;
 pub mod tool_calls;
 pub mod validation;
@@ -0,0 +1,227 @@
+//! Session-scoped Codex process tests.
+//!
+//! A scripted stand-in for `codex proto` answers each `user_input` submission
+//! with an agent message and a `task_complete` carrying the submission id. The
+//! turn counter lives in the script, so a second prompt only sees "turn 2" if
+//! the same process served both turns. A "clean the build ..." prompt first asks
+//! for approval to delete it; the script records its arguments and the answer
+//! it got in the session cwd. A "notify first ..." prompt reports the turn
+//! complete through the notify file before its last agent message.
+
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use acp_lazy_core::config::ConfigLoader;
+use acp_lazy_core::permissions::AcpPermissionMode;
+use acp_lazy_core::runtime::{ClientHandle, ClientRequest, PromptQueuePolicy, RuntimeConfig};
+use agent_client_protocol::{
+    Agent, ContentBlock, NewSessionRequest, PermissionOptionKind, PromptRequest,
+    RequestPermissionOutcome, RequestPermissionResponse, SessionUpdate, StopReason,
+};
+use anyhow::Result;
+use codex_cli_acp::codex_agent::CodexAgent;
+use tokio::sync::mpsc;
+
+const FAKE_CODEX: &str = r#"#!/bin/sh
+printf '%s\n' "$*" > codex-args.txt
+turn=0
+while IFS= read -r line; do
+  id=$(printf '%s' "$line" | sed -n 's/^{"id":"\([^"]*\)".*/\1/p')
+  case "$line" in
+    *'"text":"clean the build'*)
+      printf '{"id":"%s","msg":{"type":"exec_approval_request","call_id":"exec_1","command":["rm","-rf","build"],"cwd":"."}}\n' "$id"
+      IFS= read -r reply
+      printf '%s\n' "$reply" > codex-approval.txt
+      printf '{"id":"%s","msg":{"type":"task_complete"}}\n' "$id"
+      ;;
+    *'"text":"notify first'*)
+      printf '{"type":"agent-turn-complete"}\n' >> notify.jsonl
+      sleep 0.3
+      printf '{"id":"%s","msg":{"type":"agent_message","message":"late tail"}}\n' "$id"
+      printf '{"id":"%s","msg":{"type":"task_complete"}}\n' "$id"
+      ;;
+    *'"type":"user_input"'*)
+      turn=$((turn + 1))
+      printf '{"id":"%s","msg":{"type":"task_started"}}\n' "$id"
+      printf '{"id":"%s","msg":{"type":"agent_message","message":"turn %s"}}\n' "$id" "$turn"
+      printf '{"id":"%s","msg":{"type":"task_complete"}}\n' "$id"
+      ;;
+  esac
+done
+"#;
+
+fn write_fake_codex(dir: &Path) -> Result<String> {
+    let path = dir.join("fake-codex");
+    std::fs::write(&path, FAKE_CODEX)?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+fn agent_message_texts(
+    rx: &mut mpsc::UnboundedReceiver<agent_client_protocol::SessionNotification>,
+) -> Vec<String> {
+    let mut texts = Vec::new();
+    while let Ok(notification) = rx.try_recv() {
+        if let SessionUpdate::AgentMessageChunk {
+            content: ContentBlock::Text(text),
+        } = notification.update
+        {
+            texts.push(text.text);
+        }
+    }
+    texts
+}
+
+#[tokio::test]
+async fn prompts_share_one_codex_process_per_session() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let fake_codex = write_fake_codex(temp_dir.path())?;
+    std::env::set_var("CODEX_CMD", &fake_codex);
+    // The fake binary needs no login; satisfy the pre-spawn credential check.
+    std::env::set_var("OPENAI_API_KEY", "sk-test");
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let config = RuntimeConfig {
+        idle_timeout_ms: 10_000,
+        polling_interval_ms: 50,
+        evidence_path: None,
+        state_dir: None,
+        session_ttl_ms: None,
+        max_sessions: None,
+        prompt_queue: PromptQueuePolicy::Serialize,
+        default_mode: AcpPermissionMode::Default,
+    };
+    let agent = CodexAgent::with_config(config, Some(tx));
+
+    let session = agent
+        .new_session(NewSessionRequest {
+            cwd: temp_dir.path().to_path_buf(),
+            mcp_servers: Vec::new(),
+            meta: None,
+        })
+        .await?;
+
+    for expected in ["turn 1", "turn 2"] {
+        let response = agent
+            .prompt(PromptRequest {
+                session_id: session.session_id.clone(),
+                prompt: vec![ContentBlock::from("hello")],
+                meta: None,
+            })
+            .await?;
+
+        assert_eq!(response.stop_reason, StopReason::EndTurn);
+        assert_eq!(agent_message_texts(&mut rx), vec![expected.to_string()]);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn default_mode_answers_codex_approvals_through_the_client() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let fake_codex = write_fake_codex(temp_dir.path())?;
+    std::env::set_var("CODEX_CMD", &fake_codex);
+    std::env::set_var("OPENAI_API_KEY", "sk-test");
+
+    // Approve the first permission request with the client's "allow once" option.
+    let (client, mut requests) = ClientHandle::channel();
+    let client_task = tokio::spawn(async move {
+        let Some(ClientRequest::RequestPermission { request, reply }) = requests.recv().await
+        else {
+            return None;
+        };
+        let outcome = match request
+            .options
+            .iter()
+            .find(|option| option.kind == PermissionOptionKind::AllowOnce)
+        {
+            Some(option) => RequestPermissionOutcome::Selected {
+                option_id: option.id.clone(),
+            },
+            None => RequestPermissionOutcome::Cancelled,
+        };
+        reply
+            .send(Ok(RequestPermissionResponse {
+                outcome,
+                meta: None,
+            }))
+            .ok();
+        Some(request.tool_call.id.0.to_string())
+    });
+
+    let agent = CodexAgent::new_with_client(None, Some(client));
+    let session = agent
+        .new_session(NewSessionRequest {
+            cwd: temp_dir.path().to_path_buf(),
+            mcp_servers: Vec::new(),
+            meta: None,
+        })
+        .await?;
+
+    let response = agent
+        .prompt(PromptRequest {
+            session_id: session.session_id.clone(),
+            prompt: vec![ContentBlock::from("clean the build directory")],
+            meta: None,
+        })
+        .await?;
+    assert_eq!(response.stop_reason, StopReason::EndTurn);
+
+    let args = std::fs::read_to_string(temp_dir.path().join("codex-args.txt"))?;
+    assert!(args.contains("approval_policy=on-request"), "{}", args);
+
+    let reply: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(
+        temp_dir.path().join("codex-approval.txt"),
+    )?)?;
+    assert_eq!(reply["op"]["type"], "exec_approval");
+    assert_eq!(reply["op"]["decision"], "approved");
+
+    assert_eq!(client_task.await?.as_deref(), Some("exec_1"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn notify_completion_still_forwards_the_turn_tail() -> Result<()> {
+    let temp_dir = tempfile::tempdir()?;
+    let fake_codex = write_fake_codex(temp_dir.path())?;
+    std::env::set_var("CODEX_CMD", &fake_codex);
+    std::env::set_var("OPENAI_API_KEY", "sk-test");
+
+    let notify_path = temp_dir.path().join("notify.jsonl");
+    std::fs::write(&notify_path, "")?;
+    let user_config = temp_dir.path().join("config.toml");
+    std::fs::write(
+        &user_config,
+        format!(
+            "[runtime]\npolling_interval_ms = 20\n\n[notify]\npath = {:?}\nkind = \"file\"\ninject = \"never\"\n",
+            notify_path.display().to_string()
+        ),
+    )?;
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let agent = CodexAgent::with_config_loader(
+        ConfigLoader::new().with_user_config(user_config),
+        Some(tx),
+        None,
+    )?;
+    let session = agent
+        .new_session(NewSessionRequest {
+            cwd: temp_dir.path().to_path_buf(),
+            mcp_servers: Vec::new(),
+            meta: None,
+        })
+        .await?;
+
+    let response = agent
+        .prompt(PromptRequest {
+            session_id: session.session_id.clone(),
+            prompt: vec![ContentBlock::from("notify first, then finish")],
+            meta: None,
+        })
+        .await?;
+
+    assert_eq!(response.stop_reason, StopReason::EndTurn);
+    assert_eq!(agent_message_texts(&mut rx), vec!["late tail".to_string()]);
+    Ok(())
+}
@@ -130,7 +130,11 @@ fn test_handshake() {
         assert!(result.get("agentCapabilities").is_some());
         assert_eq!(
             result["agentCapabilities"]["promptCapabilities"]["image"],
-            false
+            true
+        );
+        assert_eq!(
+            result["agentCapabilities"]["promptCapabilities"]["embeddedContext"],
+            true
         );
         // No top-level capabilities anymore
         assert!(result.get("capabilities").is_none());
@@ -178,7 +178,7 @@ async fn rich_session_updates_cover_user_thought_plan_and_mode() {
         .await
         .unwrap();
 
-    // current mode update derived from session configuration
+    // session configuration only records the model; it is not a mode update
     harness
         .ingest_raw(
             r#"{"type":"session_configured","session_id":"session-42","model":"codex-pro","history_log_id":0,"history_entry_count":0,"rollout_path":"/tmp/log"}"#,
@@ -187,6 +187,8 @@ async fn rich_session_updates_cover_user_thought_plan_and_mode() {
         // ast-grep-ignore: rust-no-unwrap
         .unwrap();
 
+    assert_eq!(harness.configured_model(), Some("codex-pro"));
+
     let updates = harness.drain_json();
 
     // ast-grep-ignore: rust-no-unwrap
@@ -258,13 +260,6 @@ async fn rich_session_updates_cover_user_thought_plan_and_mode() {
       ],
       "sessionUpdate": "available_commands_update"
     }
-  },
-  {
-    "sessionId": "rich-session",
-    "update": {
-      "currentModeId": "codex-pro",
-      "sessionUpdate": "current_mode_update"
-    }
   }
 ]
 "###);
@@ -338,3 +333,49 @@ async fn deduplicated_agent_chunks_should_parse_with_official_schema() {
 }
 "###);
 }
+
+#[tokio::test]
+async fn token_count_events_accumulate_turn_usage() {
+    let mut harness = SnapshotHarness::new("usage-session");
+
+    let lines = [
+        r#"{"id":"sub-1","msg":{"type":"task_started","model_context_window":200000}}"#,
+        r#"{"id":"sub-1","msg":{"type":"token_count","info":{"total_token_usage":{"input_tokens":1000,"cached_input_tokens":200,"output_tokens":100,"reasoning_output_tokens":40,"total_tokens":1100},"last_token_usage":{"input_tokens":1000,"cached_input_tokens":200,"output_tokens":100,"reasoning_output_tokens":40,"total_tokens":1100},"model_context_window":200000}}}"#,
+        r#"{"id":"sub-1","msg":{"type":"task_complete","last_agent_message":null}}"#,
+        r#"{"id":"sub-2","msg":{"type":"task_started","model_context_window":200000}}"#,
+        r#"{"id":"sub-2","msg":{"type":"token_count","info":{"total_token_usage":{"input_tokens":2500,"cached_input_tokens":1100,"output_tokens":160,"reasoning_output_tokens":50,"total_tokens":2660},"last_token_usage":{"input_tokens":1500,"cached_input_tokens":900,"output_tokens":60,"reasoning_output_tokens":10,"total_tokens":1560},"model_context_window":200000}}}"#,
+    ];
+    for line in lines.iter().take(3) {
+        // ast-grep-ignore: rust-no-unwrap
+        harness.ingest_raw(line).await.unwrap();
+    }
+    // ast-grep-ignore: rust-no-unwrap
+    let first = harness.take_completed_turn().unwrap();
+    assert_eq!(first.submission_id, "sub-1");
+
+    for line in lines.iter().skip(3) {
+        // ast-grep-ignore: rust-no-unwrap
+        harness.ingest_raw(line).await.unwrap();
+    }
+    harness
+        .ingest_raw(r#"{"id":"sub-2","msg":{"type":"task_complete","last_agent_message":null}}"#)
+        .await
+        // ast-grep-ignore: rust-no-unwrap
+        .unwrap();
+    // ast-grep-ignore: rust-no-unwrap
+    let second = harness.take_completed_turn().unwrap();
+
+    assert_json_snapshot!(second.usage, @r###"
+{
+  "inputTokens": 1500,
+  "cachedInputTokens": 900,
+  "outputTokens": 60,
+  "reasoningOutputTokens": 10,
+  "totalTokens": 1560,
+  "sessionTotalTokens": 2660,
+  "contextTokens": 1550,
+  "modelContextWindow": 200000
+}
+"###);
+    assert!(harness.drain_json().is_empty());
+}
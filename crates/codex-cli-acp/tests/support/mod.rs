@@ -1,6 +1,6 @@
 use agent_client_protocol::{SessionId, SessionNotification};
 use anyhow::Result;
-use codex_cli_acp::codex_proto::{CodexEvent, CodexStreamManager};
+use codex_cli_acp::codex_proto::{CodexEvent, CodexStreamManager, CodexTurnCompletion};
 use serde_json::{self, Value};
 use std::sync::Arc;
 use tokio::sync::mpsc;
@@ -33,6 +33,18 @@ impl SnapshotHarness {
         self.manager.process_line(raw).await
     }
 
+    /// Model recorded from the latest `session_configured` event.
+    #[allow(dead_code)]
+    pub fn configured_model(&self) -> Option<&str> {
+        self.manager.configured_model()
+    }
+
+    /// Turn completion recorded from the latest `task_complete` event.
+    #[allow(dead_code)]
+    pub fn take_completed_turn(&mut self) -> Option<CodexTurnCompletion> {
+        self.manager.take_completed_turn()
+    }
+
     /// Drain all pending session updates as JSON values ready for snapshot
     /// comparison. The harness intentionally ignores serialization errors so
     /// a single malformed update does not poison the channel.
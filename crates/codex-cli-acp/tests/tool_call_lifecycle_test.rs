@@ -7,10 +7,15 @@
 #[path = "support/mod.rs"]
 mod support;
 
+use acp_lazy_core::runtime::{ClientHandle, ClientRequest, ClientTerminals};
 use agent_client_protocol::{
-    ContentBlock, SessionId, SessionNotification, SessionUpdate, StopReason, ToolCallStatus,
+    ContentBlock, CreateTerminalResponse, ReleaseTerminalResponse, SessionId, SessionNotification,
+    SessionUpdate, StopReason, TerminalExitStatus, TerminalId, ToolCallContent, ToolCallStatus,
+    ToolKind, WaitForTerminalExitResponse,
+};
+use codex_cli_acp::codex_proto::{
+    CodexApprovalKind, CodexEvent, CodexReviewDecision, CodexStreamManager, ToolCallItem,
 };
-use codex_cli_acp::codex_proto::{CodexEvent, ToolCallItem};
 use codex_cli_acp::notify_source::NotifyEvent;
 use insta::assert_json_snapshot;
 use serde_json::json;
@@ -19,7 +24,7 @@ use std::sync::Arc;
 use std::time::Duration;
 use support::SnapshotHarness;
 use tokio::sync::{mpsc, Notify};
-use tokio::time::{sleep, Instant};
+use tokio::time::{sleep, timeout, Instant};
 
 fn parse_notification(value: &serde_json::Value) -> SessionNotification {
     // ast-grep-ignore: rust-no-unwrap
@@ -340,6 +345,322 @@ async fn tool_call_error_maps_to_failed_status_update() {
 "###);
 }
 
+#[tokio::test]
+async fn exec_command_events_map_to_execute_lifecycle() {
+    let mut harness = SnapshotHarness::new("exec-session");
+
+    // ast-grep-ignore: rust-no-unwrap
+    harness
+        .ingest_raw(
+            r#"{"id":"1","msg":{"type":"exec_command_begin","call_id":"exec_1","command":["bash","-lc","ls"],"cwd":"/workspace"}}"#,
+        )
+        .await
+        .unwrap();
+    // "aGVsbG8K" is base64 for "hello\n".
+    // ast-grep-ignore: rust-no-unwrap
+    harness
+        .ingest_raw(
+            r#"{"id":"1","msg":{"type":"exec_command_output_delta","call_id":"exec_1","stream":"stdout","chunk":"aGVsbG8K"}}"#,
+        )
+        .await
+        .unwrap();
+    // ast-grep-ignore: rust-no-unwrap
+    harness
+        .ingest_raw(
+            r#"{"id":"1","msg":{"type":"exec_command_output_delta","call_id":"exec_1","stream":"stderr","chunk":"d2Fybgo="}}"#,
+        )
+        .await
+        .unwrap();
+    // ast-grep-ignore: rust-no-unwrap
+    harness
+        .ingest_raw(
+            r#"{"id":"1","msg":{"type":"exec_command_end","call_id":"exec_1","stdout":"hello\n","stderr":"","exit_code":0,"duration":{"secs":1,"nanos":500000000}}}"#,
+        )
+        .await
+        .unwrap();
+
+    let updates = harness.drain_json();
+    assert_eq!(updates.len(), 4);
+
+    let begin = parse_notification(&updates[0]);
+    match &begin.update {
+        SessionUpdate::ToolCall(call) => {
+            assert_eq!(call.id.0.as_ref(), "exec_1");
+            assert_eq!(call.kind, ToolKind::Execute);
+            assert_eq!(call.status, ToolCallStatus::InProgress);
+            assert_eq!(call.title, "shell: bash -lc ls (in /workspace)");
+            assert_eq!(call.locations.len(), 1);
+        }
+        other => panic!("expected ToolCall variant, got {:?}", other),
+    }
+
+    assert_json_snapshot!(updates[2], @r###"
+{
+  "sessionId": "exec-session",
+  "update": {
+    "content": [
+      {
+        "content": {
+          "text": "hello\n",
+          "type": "text"
+        },
+        "type": "content"
+      },
+      {
+        "content": {
+          "text": "[stderr]:\nwarn\n",
+          "type": "text"
+        },
+        "type": "content"
+      }
+    ],
+    "sessionUpdate": "tool_call_update",
+    "toolCallId": "exec_1"
+  }
+}
+"###);
+
+    let end = parse_notification(&updates[3]);
+    match &end.update {
+        SessionUpdate::ToolCallUpdate(update) => {
+            assert_eq!(update.fields.status, Some(ToolCallStatus::Completed));
+            // ast-grep-ignore: rust-no-unwrap
+            let raw_output = update.fields.raw_output.as_ref().unwrap();
+            assert_eq!(raw_output["exit_code"], json!(0));
+            assert_eq!(raw_output["duration_ms"], json!(1500));
+            assert_eq!(raw_output["stdout"], json!("hello\n"));
+            assert_eq!(raw_output["stderr"], json!("warn\n"));
+        }
+        other => panic!("expected ToolCallUpdate variant, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn exec_command_nonzero_exit_maps_to_failed_status() {
+    let mut harness = SnapshotHarness::new("exec-fail-session");
+
+    // ast-grep-ignore: rust-no-unwrap
+    harness
+        .ingest_raw(
+            r#"{"type":"exec_command_begin","call_id":"exec_2","command":["false"],"cwd":"/tmp"}"#,
+        )
+        .await
+        .unwrap();
+    // ast-grep-ignore: rust-no-unwrap
+    harness
+        .ingest_raw(
+            r#"{"type":"exec_command_end","call_id":"exec_2","stdout":"","stderr":"boom","exit_code":2,"duration":"150ms"}"#,
+        )
+        .await
+        .unwrap();
+
+    let updates = harness.drain_json();
+    assert_eq!(updates.len(), 2);
+
+    let end = parse_notification(&updates[1]);
+    match &end.update {
+        SessionUpdate::ToolCallUpdate(update) => {
+            assert_eq!(update.fields.status, Some(ToolCallStatus::Failed));
+            // ast-grep-ignore: rust-no-unwrap
+            let content = update.fields.content.as_ref().unwrap();
+            assert_eq!(content.len(), 2);
+            // ast-grep-ignore: rust-no-unwrap
+            let raw_output = update.fields.raw_output.as_ref().unwrap();
+            assert_eq!(raw_output["exit_code"], json!(2));
+            assert_eq!(raw_output["duration_ms"], json!(150));
+        }
+        other => panic!("expected ToolCallUpdate variant, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn exec_command_without_exit_code_is_not_reported_as_success() {
+    let mut harness = SnapshotHarness::new("exec-unknown-session");
+
+    // A chunk that is not base64 is dropped instead of shown verbatim.
+    // ast-grep-ignore: rust-no-unwrap
+    harness
+        .ingest_raw(
+            r#"{"type":"exec_command_output_delta","call_id":"exec_3","stream":"stdout","chunk":"not base64!"}"#,
+        )
+        .await
+        .unwrap();
+    // ast-grep-ignore: rust-no-unwrap
+    harness
+        .ingest_raw(r#"{"type":"exec_command_end","call_id":"exec_3","stdout":"partial"}"#)
+        .await
+        .unwrap();
+
+    let updates = harness.drain_json();
+    assert_eq!(updates.len(), 2, "{:#?}", updates);
+
+    let end = parse_notification(&updates[1]);
+    match &end.update {
+        SessionUpdate::ToolCallUpdate(update) => {
+            assert_eq!(update.fields.status, Some(ToolCallStatus::Failed));
+            // ast-grep-ignore: rust-no-unwrap
+            let content = update.fields.content.as_ref().unwrap();
+            assert_eq!(
+                content.last(),
+                Some(&ToolCallContent::from("[exit code: unknown]"))
+            );
+            // ast-grep-ignore: rust-no-unwrap
+            let raw_output = update.fields.raw_output.as_ref().unwrap();
+            assert_eq!(raw_output["exit_code"], json!(null));
+        }
+        other => panic!("expected ToolCallUpdate variant, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn exec_output_is_replayed_into_client_terminal_when_supported() {
+    let (client, mut requests) = ClientHandle::channel();
+    let created = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let released = Arc::new(AtomicBool::new(false));
+    let (created_log, released_flag) = (created.clone(), released.clone());
+    tokio::spawn(async move {
+        while let Some(request) = requests.recv().await {
+            match request {
+                ClientRequest::CreateTerminal { request, reply } => {
+                    // ast-grep-ignore: rust-no-unwrap
+                    created_log.lock().unwrap().push(request);
+                    let _ = reply.send(Ok(CreateTerminalResponse {
+                        terminal_id: TerminalId(Arc::from("term-1")),
+                        meta: None,
+                    }));
+                }
+                ClientRequest::WaitForTerminalExit { reply, .. } => {
+                    let _ = reply.send(Ok(WaitForTerminalExitResponse {
+                        exit_status: TerminalExitStatus {
+                            exit_code: Some(3),
+                            signal: None,
+                            meta: None,
+                        },
+                        meta: None,
+                    }));
+                }
+                ClientRequest::ReleaseTerminal { reply, .. } => {
+                    released_flag.store(true, Ordering::SeqCst);
+                    let _ = reply.send(Ok(ReleaseTerminalResponse { meta: None }));
+                }
+                other => panic!("unexpected client request: {:?}", other),
+            }
+        }
+    });
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut manager = CodexStreamManager::new(SessionId(Arc::from("terminal-session")), tx)
+        .with_terminals(ClientTerminals::new(Some(client), true));
+    // ast-grep-ignore: rust-no-unwrap
+    manager
+        .process_line(
+            r#"{"type":"exec_command_begin","call_id":"exec_t","command":["make"],"cwd":"/repo"}"#,
+        )
+        .await
+        .unwrap();
+    // ast-grep-ignore: rust-no-unwrap
+    manager
+        .process_line(
+            r#"{"type":"exec_command_end","call_id":"exec_t","stdout":"building\n","stderr":"error: x","exit_code":3}"#,
+        )
+        .await
+        .unwrap();
+
+    // ast-grep-ignore: rust-no-unwrap
+    let _begin = rx.recv().await.unwrap();
+    // The completion carries the text preview; it does not wait for the terminal.
+    // ast-grep-ignore: rust-no-unwrap
+    let end = rx.recv().await.unwrap();
+    match end.update {
+        SessionUpdate::ToolCallUpdate(update) => {
+            assert_eq!(update.fields.status, Some(ToolCallStatus::Failed));
+            // ast-grep-ignore: rust-no-unwrap
+            let content = update.fields.content.unwrap();
+            assert_eq!(
+                content.last(),
+                Some(&ToolCallContent::from("[exit code: 3]"))
+            );
+            // ast-grep-ignore: rust-no-unwrap
+            let raw_output = update.fields.raw_output.unwrap();
+            assert_eq!(raw_output["stdout"], json!("building\n"));
+        }
+        other => panic!("expected ToolCallUpdate variant, got {:?}", other),
+    }
+
+    // A follow-up update embeds the terminal once it has printed the output.
+    // ast-grep-ignore: rust-no-unwrap
+    let mirrored = rx.recv().await.unwrap();
+    match mirrored.update {
+        SessionUpdate::ToolCallUpdate(update) => {
+            assert_eq!(update.id.0.as_ref(), "exec_t");
+            assert_eq!(update.fields.status, None);
+            assert_eq!(
+                update.fields.content,
+                Some(vec![ToolCallContent::Terminal {
+                    terminal_id: TerminalId(Arc::from("term-1")),
+                }])
+            );
+        }
+        other => panic!("expected ToolCallUpdate variant, got {:?}", other),
+    }
+
+    // The terminal replays the captured output instead of re-running `make`.
+    // ast-grep-ignore: rust-no-unwrap
+    let requests = created.lock().unwrap().clone();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].command, "sh");
+    assert_eq!(requests[0].args[3..], ["building\n", "error: x", "3"]);
+
+    let deadline = Instant::now() + Duration::from_secs(1);
+    while !released.load(Ordering::SeqCst) && Instant::now() < deadline {
+        sleep(Duration::from_millis(5)).await;
+    }
+    assert!(released.load(Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn unresponsive_client_terminal_does_not_stall_the_stream() {
+    // The client never answers `terminal/create`.
+    let (client, _requests) = ClientHandle::channel();
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut manager = CodexStreamManager::new(SessionId(Arc::from("stalled-session")), tx)
+        .with_terminals(ClientTerminals::new(Some(client), true));
+
+    let lines = [
+        r#"{"type":"exec_command_begin","call_id":"exec_s","command":["ls"],"cwd":"/repo"}"#,
+        r#"{"type":"exec_command_end","call_id":"exec_s","stdout":"a.txt\n","exit_code":0}"#,
+        r#"{"type":"agent_message","message":"done"}"#,
+    ];
+    for line in lines {
+        // ast-grep-ignore: rust-no-unwrap
+        timeout(Duration::from_secs(1), manager.process_line(line))
+            .await
+            .expect("the stdout reader should not wait for the client terminal")
+            .unwrap();
+    }
+
+    // ast-grep-ignore: rust-no-unwrap
+    let _begin = rx.recv().await.unwrap();
+    // ast-grep-ignore: rust-no-unwrap
+    let end = rx.recv().await.unwrap();
+    match end.update {
+        SessionUpdate::ToolCallUpdate(update) => {
+            assert_eq!(update.fields.status, Some(ToolCallStatus::Completed));
+            assert_eq!(
+                update.fields.content,
+                Some(vec![ToolCallContent::from("a.txt\n")])
+            );
+        }
+        other => panic!("expected ToolCallUpdate variant, got {:?}", other),
+    }
+    // ast-grep-ignore: rust-no-unwrap
+    let message = rx.recv().await.unwrap();
+    assert!(matches!(
+        message.update,
+        SessionUpdate::AgentMessageChunk { .. }
+    ));
+}
+
 #[tokio::test]
 async fn notify_completion_should_emit_official_stop_reason() {
     let cancel_notify = Arc::new(Notify::new());
@@ -402,3 +723,500 @@ async fn idle_timeout_should_emit_official_stop_reason() {
 
     assert_eq!(stop_reason, StopReason::EndTurn);
 }
+
+#[tokio::test]
+async fn approval_requests_are_forwarded_with_codex_response_ops() {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let (approval_tx, mut approval_rx) = mpsc::unbounded_channel();
+    let mut manager = CodexStreamManager::new(SessionId(Arc::from("approval-session")), tx)
+        .with_approvals(approval_tx);
+
+    // ast-grep-ignore: rust-no-unwrap
+    manager
+        .process_line(
+            r#"{"id":"sub-7","msg":{"type":"exec_approval_request","call_id":"exec_9","command":["rm","-rf","build"],"cwd":"/workspace","reason":"clean build output"}}"#,
+        )
+        .await
+        .unwrap();
+    // ast-grep-ignore: rust-no-unwrap
+    manager
+        .process_line(
+            r#"{"type":"apply_patch_approval_request","call_id":"patch_3","changes":{"src/lib.rs":{"update":{"unified_diff":"@@ -1 +1 @@\n-a\n+b\n"}}}}"#,
+        )
+        .await
+        .unwrap();
+
+    // Approval requests do not emit session updates on their own.
+    assert!(rx.try_recv().is_err());
+
+    // ast-grep-ignore: rust-no-unwrap
+    let exec = approval_rx.try_recv().unwrap();
+    assert_eq!(exec.kind, CodexApprovalKind::Exec);
+    assert_eq!(exec.tool_call.id.0.as_ref(), "exec_9");
+    assert_eq!(exec.tool_call.fields.kind, Some(ToolKind::Execute));
+    assert_eq!(
+        exec.tool_call.fields.title.as_deref(),
+        Some("shell: rm -rf build (in /workspace)")
+    );
+    assert_eq!(
+        exec.response_submission("approval-1", CodexReviewDecision::ApprovedForSession),
+        json!({
+            "id": "approval-1",
+            "op": {"type": "exec_approval", "id": "sub-7", "decision": "approved_for_session"}
+        })
+    );
+
+    // ast-grep-ignore: rust-no-unwrap
+    let patch = approval_rx.try_recv().unwrap();
+    assert_eq!(patch.kind, CodexApprovalKind::Patch);
+    assert_eq!(patch.tool_call.fields.kind, Some(ToolKind::Edit));
+    assert_eq!(
+        patch.tool_call.fields.title.as_deref(),
+        Some("apply_patch: src/lib.rs")
+    );
+    assert_eq!(
+        patch.response_submission("approval-2", CodexReviewDecision::Denied),
+        json!({
+            "id": "approval-2",
+            "op": {"type": "patch_approval", "id": "patch_3", "decision": "denied"}
+        })
+    );
+}
+
+#[tokio::test]
+async fn patch_apply_events_map_to_diff_tool_calls() {
+    let mut harness = SnapshotHarness::new("patch-session");
+
+    // ast-grep-ignore: rust-no-unwrap
+    harness
+        .ingest_raw(
+            r#"{"id":"1","msg":{"type":"patch_apply_begin","call_id":"patch_1","auto_approved":true,"changes":{"/repo/src/lib.rs":{"update":{"unified_diff":"@@ -1,2 +1,2 @@\n fn main() {}\n-let a = 1;\n+let a = 2;\n","move_path":null}},"/repo/NEW.md":{"type":"add","content":"hello\n"}}}}"#,
+        )
+        .await
+        .unwrap();
+    // ast-grep-ignore: rust-no-unwrap
+    harness
+        .ingest_raw(
+            r#"{"id":"1","msg":{"type":"patch_apply_end","call_id":"patch_1","stdout":"Success","stderr":"","success":true}}"#,
+        )
+        .await
+        .unwrap();
+
+    let updates = harness.drain_json();
+    assert_eq!(updates.len(), 2);
+
+    assert_json_snapshot!(updates[0], @r###"
+{
+  "sessionId": "patch-session",
+  "update": {
+    "content": [
+      {
+        "newText": "hello\n",
+        "oldText": null,
+        "path": "/repo/NEW.md",
+        "type": "diff"
+      },
+      {
+        "newText": "fn main() {}\nlet a = 2;\n",
+        "oldText": "fn main() {}\nlet a = 1;\n",
+        "path": "/repo/src/lib.rs",
+        "type": "diff"
+      }
+    ],
+    "kind": "edit",
+    "locations": [
+      {
+        "path": "/repo/NEW.md"
+      },
+      {
+        "path": "/repo/src/lib.rs"
+      }
+    ],
+    "rawInput": {
+      "auto_approved": true,
+      "changes": {
+        "/repo/NEW.md": {
+          "content": "hello\n",
+          "type": "add"
+        },
+        "/repo/src/lib.rs": {
+          "update": {
+            "move_path": null,
+            "unified_diff": "@@ -1,2 +1,2 @@\n fn main() {}\n-let a = 1;\n+let a = 2;\n"
+          }
+        }
+      }
+    },
+    "sessionUpdate": "tool_call",
+    "status": "in_progress",
+    "title": "apply_patch: 2 files",
+    "toolCallId": "patch_1"
+  }
+}
+"###);
+
+    let end = parse_notification(&updates[1]);
+    match &end.update {
+        SessionUpdate::ToolCallUpdate(update) => {
+            assert_eq!(update.fields.status, Some(ToolCallStatus::Completed));
+            assert!(update.fields.content.is_none());
+        }
+        other => panic!("expected ToolCallUpdate variant, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn patch_apply_failure_and_delete_kind() {
+    let mut harness = SnapshotHarness::new("patch-fail-session");
+
+    // ast-grep-ignore: rust-no-unwrap
+    harness
+        .ingest_raw(
+            r#"{"type":"patch_apply_begin","call_id":"patch_2","changes":{"/repo/old.txt":{"delete":{"content":"bye\n"}}}}"#,
+        )
+        .await
+        .unwrap();
+    // ast-grep-ignore: rust-no-unwrap
+    harness
+        .ingest_raw(
+            r#"{"type":"patch_apply_end","call_id":"patch_2","stdout":"","stderr":"permission denied","success":false}"#,
+        )
+        .await
+        .unwrap();
+
+    let updates = harness.drain_json();
+    assert_eq!(updates.len(), 2);
+
+    let begin = parse_notification(&updates[0]);
+    match &begin.update {
+        SessionUpdate::ToolCall(call) => {
+            assert_eq!(call.kind, ToolKind::Delete);
+            assert_eq!(call.title, "apply_patch: /repo/old.txt");
+            assert_eq!(call.locations.len(), 1);
+        }
+        other => panic!("expected ToolCall variant, got {:?}", other),
+    }
+
+    let end = parse_notification(&updates[1]);
+    match &end.update {
+        SessionUpdate::ToolCallUpdate(update) => {
+            assert_eq!(update.fields.status, Some(ToolCallStatus::Failed));
+            // ast-grep-ignore: rust-no-unwrap
+            let content = update.fields.content.as_ref().unwrap();
+            assert_eq!(content.len(), 2);
+        }
+        other => panic!("expected ToolCallUpdate variant, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn patch_updates_emit_one_diff_per_hunk() {
+    let mut harness = SnapshotHarness::new("patch-hunks-session");
+
+    // ast-grep-ignore: rust-no-unwrap
+    harness
+        .ingest_raw(
+            r#"{"type":"patch_apply_begin","call_id":"patch_4","changes":{"/repo/src/lib.rs":{"update":{"unified_diff":"--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,2 +1,2 @@\n-use a;\n+use b;\n fn main() {}\n@@ -40 +40 @@\n-    old()\n+    new()\n"}}}}"#,
+        )
+        .await
+        .unwrap();
+
+    let updates = harness.drain_json();
+    assert_eq!(updates.len(), 1);
+    assert_eq!(
+        updates[0]["update"]["content"],
+        json!([
+            {
+                "type": "diff",
+                "path": "/repo/src/lib.rs",
+                "oldText": "use a;\nfn main() {}\n",
+                "newText": "use b;\nfn main() {}\n"
+            },
+            {
+                "type": "diff",
+                "path": "/repo/src/lib.rs",
+                "oldText": "    old()\n",
+                "newText": "    new()\n"
+            }
+        ])
+    );
+}
+
+#[tokio::test]
+async fn dash_lines_inside_hunks_are_not_file_headers() {
+    let mut harness = SnapshotHarness::new("patch-dashes-session");
+
+    // `--- x` removes the line `-- x`; `+++ y` adds the line `++ y`.
+    // ast-grep-ignore: rust-no-unwrap
+    harness
+        .ingest_raw(
+            r#"{"type":"patch_apply_begin","call_id":"patch_5","changes":{"/repo/query.sql":{"update":{"unified_diff":"@@ -1,2 +1,2 @@\n--- legacy filter\n+++ counter\n SELECT 1;\n"}}}}"#,
+        )
+        .await
+        .unwrap();
+
+    let updates = harness.drain_json();
+    assert_eq!(updates.len(), 1);
+    assert_eq!(
+        updates[0]["update"]["content"],
+        json!([
+            {
+                "type": "diff",
+                "path": "/repo/query.sql",
+                "oldText": "-- legacy filter\nSELECT 1;\n",
+                "newText": "++ counter\nSELECT 1;\n"
+            }
+        ])
+    );
+}
+
+#[tokio::test]
+async fn mcp_tool_call_lifecycle_carries_call_tool_result() {
+    let mut harness = SnapshotHarness::new("mcp-session");
+
+    // ast-grep-ignore: rust-no-unwrap
+    harness
+        .ingest_raw(
+            r#"{"type":"mcp_tool_call_begin","call_id":"mcp_1","invocation":{"server":"docs","tool":"search_docs","arguments":{"query":"tokio"}}}"#,
+        )
+        .await
+        .unwrap();
+    // ast-grep-ignore: rust-no-unwrap
+    harness
+        .ingest_raw(
+            r#"{"type":"mcp_tool_call_end","call_id":"mcp_1","invocation":{"server":"docs","tool":"search_docs","arguments":{"query":"tokio"}},"duration":{"secs":1,"nanos":500000000},"result":{"Ok":{"content":[{"type":"text","text":"Found 2 pages"},{"type":"image","data":"QUJD","mimeType":"image/png"}],"isError":false}}}"#,
+        )
+        .await
+        .unwrap();
+
+    let updates = harness.drain_json();
+
+    assert_json_snapshot!(updates, @r###"
+[
+  {
+    "sessionId": "mcp-session",
+    "update": {
+      "kind": "search",
+      "rawInput": {
+        "query": "tokio"
+      },
+      "sessionUpdate": "tool_call",
+      "status": "in_progress",
+      "title": "docs.search_docs",
+      "toolCallId": "mcp_1"
+    }
+  },
+  {
+    "sessionId": "mcp-session",
+    "update": {
+      "content": [
+        {
+          "content": {
+            "text": "Found 2 pages",
+            "type": "text"
+          },
+          "type": "content"
+        },
+        {
+          "content": {
+            "data": "QUJD",
+            "mimeType": "image/png",
+            "type": "image"
+          },
+          "type": "content"
+        }
+      ],
+      "rawOutput": {
+        "duration_ms": 1500,
+        "result": {
+          "Ok": {
+            "content": [
+              {
+                "text": "Found 2 pages",
+                "type": "text"
+              },
+              {
+                "data": "QUJD",
+                "mimeType": "image/png",
+                "type": "image"
+              }
+            ],
+            "isError": false
+          }
+        }
+      },
+      "sessionUpdate": "tool_call_update",
+      "status": "completed",
+      "toolCallId": "mcp_1"
+    }
+  }
+]
+"###);
+}
+
+#[tokio::test]
+async fn mcp_tool_call_errors_fail_the_tool_call() {
+    let mut harness = SnapshotHarness::new("mcp-error-session");
+
+    // End without a begin: the tool call is synthesized from the invocation.
+    // ast-grep-ignore: rust-no-unwrap
+    harness
+        .ingest_raw(
+            r#"{"type":"mcp_tool_call_end","call_id":"mcp_2","invocation":{"server":"repo","tool":"read_file","arguments":null},"duration":"12ms","result":{"Err":"tool timed out"}}"#,
+        )
+        .await
+        .unwrap();
+
+    let updates = harness.drain_json();
+    assert_eq!(updates.len(), 2);
+
+    let begin = parse_notification(&updates[0]);
+    match &begin.update {
+        SessionUpdate::ToolCall(call) => {
+            assert_eq!(call.title, "repo.read_file");
+            assert_eq!(call.kind, ToolKind::Read);
+        }
+        other => panic!("expected ToolCall variant, got {:?}", other),
+    }
+
+    let end = parse_notification(&updates[1]);
+    match &end.update {
+        SessionUpdate::ToolCallUpdate(update) => {
+            assert_eq!(update.fields.status, Some(ToolCallStatus::Failed));
+            // ast-grep-ignore: rust-no-unwrap
+            let content = update.fields.content.as_ref().unwrap();
+            assert_eq!(content.len(), 1);
+        }
+        other => panic!("expected ToolCallUpdate variant, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn web_search_lifecycle_links_result_urls() {
+    let mut harness = SnapshotHarness::new("search-session");
+
+    // ast-grep-ignore: rust-no-unwrap
+    harness
+        .ingest_raw(r#"{"type":"web_search_begin","call_id":"ws_1"}"#)
+        .await
+        .unwrap();
+    // ast-grep-ignore: rust-no-unwrap
+    harness
+        .ingest_raw(
+            r#"{"type":"web_search_end","call_id":"ws_1","query":"tokio select biased","results":[{"url":"https://docs.rs/tokio/latest/tokio/macro.select.html","title":"select in tokio","snippet":"Waits on multiple concurrent branches"},"not a url"]}"#,
+        )
+        .await
+        .unwrap();
+
+    let updates = harness.drain_json();
+
+    assert_json_snapshot!(updates, @r###"
+[
+  {
+    "sessionId": "search-session",
+    "update": {
+      "kind": "fetch",
+      "rawInput": {
+        "query": null
+      },
+      "sessionUpdate": "tool_call",
+      "status": "in_progress",
+      "title": "Web search",
+      "toolCallId": "ws_1"
+    }
+  },
+  {
+    "sessionId": "search-session",
+    "update": {
+      "content": [
+        {
+          "content": {
+            "description": "Waits on multiple concurrent branches",
+            "name": "select in tokio",
+            "title": "select in tokio",
+            "type": "resource_link",
+            "uri": "https://docs.rs/tokio/latest/tokio/macro.select.html"
+          },
+          "type": "content"
+        }
+      ],
+      "rawInput": {
+        "query": "tokio select biased"
+      },
+      "rawOutput": {
+        "query": "tokio select biased",
+        "results": [
+          {
+            "snippet": "Waits on multiple concurrent branches",
+            "title": "select in tokio",
+            "url": "https://docs.rs/tokio/latest/tokio/macro.select.html"
+          },
+          "not a url"
+        ]
+      },
+      "sessionUpdate": "tool_call_update",
+      "status": "completed",
+      "title": "tokio select biased",
+      "toolCallId": "ws_1"
+    }
+  }
+]
+"###);
+}
+
+#[tokio::test]
+async fn turn_diff_is_summarised_when_the_turn_completes() {
+    let mut harness = SnapshotHarness::new("turn-diff-session");
+
+    let unified_diff = "diff --git a/src/lib.rs b/src/lib.rs\nindex 1111111..2222222 100644\n--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,2 +1,2 @@\n fn main() {}\n-// old\n+// new\ndiff --git a/notes.md b/notes.md\nnew file mode 100644\n--- /dev/null\n+++ b/notes.md\n@@ -0,0 +1 @@\n+hello\n";
+    let lines = [
+        json!({"id": "sub-1", "msg": {"type": "task_started"}}),
+        json!({"id": "sub-1", "msg": {"type": "turn_diff", "unified_diff": unified_diff}}),
+        json!({"id": "sub-1", "msg": {"type": "task_complete", "last_agent_message": null}}),
+    ];
+    for line in lines {
+        // ast-grep-ignore: rust-no-unwrap
+        harness.ingest_raw(&line.to_string()).await.unwrap();
+    }
+
+    let updates = harness.drain_json();
+    assert_eq!(updates.len(), 1);
+    let mut summary = updates[0].clone();
+    summary["update"]["rawOutput"] = json!("<unified diff>");
+
+    assert_json_snapshot!(summary, @r###"
+{
+  "sessionId": "turn-diff-session",
+  "update": {
+    "content": [
+      {
+        "newText": "fn main() {}\n// new\n",
+        "oldText": "fn main() {}\n// old\n",
+        "path": "src/lib.rs",
+        "type": "diff"
+      },
+      {
+        "newText": "hello\n",
+        "oldText": null,
+        "path": "notes.md",
+        "type": "diff"
+      }
+    ],
+    "kind": "edit",
+    "locations": [
+      {
+        "path": "src/lib.rs"
+      },
+      {
+        "path": "notes.md"
+      }
+    ],
+    "rawOutput": "<unified diff>",
+    "sessionUpdate": "tool_call",
+    "status": "completed",
+    "title": "Turn diff: 2 files",
+    "toolCallId": "turn_diff-sub-1"
+  }
+}
+"###);
+}
@@ -0,0 +1,101 @@
+use acp_lazy_core::config::{ConfigError, ConfigLoader};
+use acp_lazy_core::runtime::{
+    Conversation, ProviderModel, RuntimeServer, SessionState, TurnAdapter, TurnCli, TurnCommand,
+    TurnContext, TurnMapper,
+};
+use agent_client_protocol::{AgentCapabilities, Error, McpCapabilities, SessionId};
+use tracing::warn;
+
+use crate::gemini_proto::GeminiStreamMapper;
+use crate::permissions::map_acp_to_gemini;
+
+/// Models offered through `session/set_model`; the first entry is the default.
+const GEMINI_MODELS: &[(&str, &str)] = &[
+    ("gemini-2.5-pro", "Gemini 2.5 Pro"),
+    ("gemini-2.5-flash", "Gemini 2.5 Flash"),
+    ("gemini-2.5-flash-lite", "Gemini 2.5 Flash-Lite"),
+];
+
+/// Gemini CLI run as one `gemini` process per prompt.
+///
+/// Each turn runs with `--output-format stream-json` and resumes the
+/// previous Gemini session when there is one.
+pub struct GeminiCli;
+
+impl TurnCli for GeminiCli {
+    fn name(&self) -> &str {
+        "Gemini"
+    }
+
+    fn agent_capabilities(&self) -> AgentCapabilities {
+        AgentCapabilities {
+            load_session: false,
+            prompt_capabilities: agent_client_protocol::PromptCapabilities {
+                image: false,
+                audio: false,
+                embedded_context: true,
+                meta: None,
+            },
+            mcp_capabilities: McpCapabilities {
+                http: false,
+                sse: false,
+                meta: None,
+            },
+            meta: None,
+        }
+    }
+
+    fn available_models(&self) -> Vec<ProviderModel> {
+        GEMINI_MODELS
+            .iter()
+            .map(|(id, name)| ProviderModel {
+                id: id.to_string(),
+                name: name.to_string(),
+                description: None,
+            })
+            .collect()
+    }
+
+    fn turn_command(&self, turn: TurnContext<'_>) -> Result<TurnCommand, Error> {
+        if !turn.session.mcp_servers.is_empty() {
+            // Gemini only reads MCP servers from its settings.json.
+            warn!(
+                "Ignoring {} client MCP server(s) for Gemini session {}",
+                turn.session.mcp_servers.len(),
+                turn.session.session_id.0
+            );
+        }
+        let mut args = turn_args(turn.session, turn.conversation);
+        args.extend(turn.bridge.gemini.args.iter().flatten().cloned());
+        // Without `--prompt`, Gemini reads the prompt from stdin.
+        Ok(TurnCommand {
+            program: turn.bridge.gemini_command().to_string(),
+            args,
+            stdin: turn.prompt,
+        })
+    }
+
+    fn mapper(&self, session_id: SessionId) -> Box<dyn TurnMapper + '_> {
+        Box::new(GeminiStreamMapper::new(session_id))
+    }
+}
+
+/// Runtime serving the Gemini CLI over ACP.
+pub fn runtime(config_loader: ConfigLoader) -> Result<RuntimeServer, ConfigError> {
+    TurnAdapter::runtime(GeminiCli, config_loader)
+}
+
+/// Arguments for one turn, before the `[gemini].args` from config.
+fn turn_args(session: &SessionState, conversation: &Conversation) -> Vec<String> {
+    let mut args = vec!["--output-format".to_string(), "stream-json".to_string()];
+    args.extend(map_acp_to_gemini(session.permission_mode));
+    if let Some(model) = session.model.as_deref() {
+        args.push("--model".into());
+        args.push(model.to_string());
+    }
+    if let Some(id) = conversation.resume_id.as_deref() {
+        args.push("--resume".into());
+        args.push(id.to_string());
+    }
+    args
+}
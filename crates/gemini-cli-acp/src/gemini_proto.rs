@@ -0,0 +1,473 @@
+//! Gemini CLI `stream-json` event handling.
+//!
+//! `gemini --output-format stream-json` prints one JSON object per line.
+//! This module maps them onto ACP `SessionNotification`s:
+//!
+//! - `init` records the Gemini session id and model.
+//! - Assistant `message` events become agent message chunks; user messages
+//!   echo the prompt and are dropped.
+//! - `tool_use` starts a tool call and the matching `tool_result` completes
+//!   or fails it. `write_file` and `replace` carry `ToolCallContent::Diff`
+//!   entries, and `write_todos` is shown as an ACP plan.
+//! - `error` events are logged; `result` ends the turn with its stop reason
+//!   and stats.
+//!
+//! Unknown event types are ignored so newer CLI versions keep working.
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use acp_lazy_core::runtime::TurnMapper;
+use agent_client_protocol::{
+    ContentBlock, Diff, Error, Plan, PlanEntry, PlanEntryPriority, PlanEntryStatus, PromptResponse,
+    SessionId, SessionNotification, SessionUpdate, StopReason, ToolCall, ToolCallContent,
+    ToolCallId, ToolCallLocation, ToolCallStatus, ToolCallUpdate, ToolCallUpdateFields, ToolKind,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::{debug, warn};
+
+/// Gemini's task-list tool, presented as an ACP plan.
+const WRITE_TODOS_TOOL: &str = "write_todos";
+
+/// Error type Gemini reports when a run hits its turn limit.
+const TURN_LIMIT_ERROR: &str = "FatalTurnLimitedError";
+
+/// One line of `stream-json` output.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GeminiEvent {
+    Init(GeminiInitEvent),
+    Message(GeminiMessageEvent),
+    ToolUse(GeminiToolUseEvent),
+    ToolResult(GeminiToolResultEvent),
+    Error(GeminiErrorEvent),
+    Result(GeminiResultEvent),
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GeminiInitEvent {
+    #[serde(default)]
+    pub session_id: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GeminiMessageEvent {
+    #[serde(default)]
+    pub role: String,
+    #[serde(default)]
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GeminiToolUseEvent {
+    pub tool_name: String,
+    pub tool_id: String,
+    #[serde(default)]
+    pub parameters: Value,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GeminiToolResultEvent {
+    pub tool_id: String,
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub output: Option<String>,
+    #[serde(default)]
+    pub error: Option<GeminiError>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GeminiError {
+    #[serde(rename = "type", default)]
+    pub kind: String,
+    #[serde(default)]
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GeminiErrorEvent {
+    #[serde(default)]
+    pub severity: String,
+    #[serde(default)]
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GeminiResultEvent {
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub error: Option<GeminiError>,
+    #[serde(default)]
+    pub stats: Option<Value>,
+}
+
+impl GeminiResultEvent {
+    /// ACP stop reason for the turn, or the error Gemini reported.
+    pub fn stop_reason(&self) -> Result<StopReason, Error> {
+        if self.status == "success" {
+            return Ok(StopReason::EndTurn);
+        }
+        match &self.error {
+            Some(error) if error.kind == TURN_LIMIT_ERROR => Ok(StopReason::MaxTurnRequests),
+            Some(error) => Err(Error::internal_error().with_data(error.message.clone())),
+            None => {
+                Err(Error::internal_error()
+                    .with_data(format!("Gemini turn failed ({})", self.status)))
+            }
+        }
+    }
+
+    /// `PromptResponse` meta carrying the turn stats as usage.
+    pub fn meta(&self) -> Option<Value> {
+        self.stats.as_ref().map(|stats| json!({ "usage": stats }))
+    }
+}
+
+/// Stateful mapper from Gemini events to ACP notifications for one turn.
+pub struct GeminiStreamMapper {
+    session_id: SessionId,
+    gemini_session_id: Option<String>,
+    model: Option<String>,
+    /// Tool ids whose call carries a diff; ACP replaces content wholesale.
+    diff_tools: HashSet<String>,
+    plan_tools: HashSet<String>,
+    known_tools: HashMap<String, String>,
+    result: Option<GeminiResultEvent>,
+}
+
+impl GeminiStreamMapper {
+    pub fn new(session_id: SessionId) -> Self {
+        Self {
+            session_id,
+            gemini_session_id: None,
+            model: None,
+            diff_tools: HashSet::new(),
+            plan_tools: HashSet::new(),
+            known_tools: HashMap::new(),
+            result: None,
+        }
+    }
+
+    pub fn process_event(&mut self, event: GeminiEvent) -> Vec<SessionNotification> {
+        let update = match event {
+            GeminiEvent::Init(event) => {
+                if event.session_id.is_some() {
+                    self.gemini_session_id = event.session_id;
+                }
+                if event.model.is_some() {
+                    self.model = event.model;
+                }
+                None
+            }
+            GeminiEvent::Message(event) => (event.role == "assistant" && !event.content.is_empty())
+                .then(|| SessionUpdate::AgentMessageChunk {
+                    content: ContentBlock::from(event.content),
+                }),
+            GeminiEvent::ToolUse(event) => Some(self.tool_use_update(event)),
+            GeminiEvent::ToolResult(event) => self.tool_result_update(event),
+            GeminiEvent::Error(event) => {
+                if event.severity == "warning" {
+                    debug!("Gemini warning: {}", event.message);
+                } else {
+                    warn!("Gemini error: {}", event.message);
+                }
+                None
+            }
+            GeminiEvent::Result(event) => {
+                self.result = Some(event);
+                None
+            }
+            GeminiEvent::Unknown => None,
+        };
+
+        update
+            .into_iter()
+            .map(|update| SessionNotification {
+                session_id: self.session_id.clone(),
+                update,
+                meta: None,
+            })
+            .collect()
+    }
+
+    fn tool_use_update(&mut self, event: GeminiToolUseEvent) -> SessionUpdate {
+        if event.tool_name == WRITE_TODOS_TOOL {
+            self.plan_tools.insert(event.tool_id);
+            return SessionUpdate::Plan(plan_from_todos(&event.parameters));
+        }
+        let tool_call = tool_call_from_use(&event.tool_id, &event.tool_name, event.parameters);
+        if tool_call
+            .content
+            .iter()
+            .any(|content| matches!(content, ToolCallContent::Diff { .. }))
+        {
+            self.diff_tools.insert(event.tool_id.clone());
+        }
+        self.known_tools.insert(event.tool_id, event.tool_name);
+        SessionUpdate::ToolCall(tool_call)
+    }
+
+    fn tool_result_update(&mut self, event: GeminiToolResultEvent) -> Option<SessionUpdate> {
+        if self.plan_tools.remove(&event.tool_id) {
+            return None;
+        }
+        if self.known_tools.remove(&event.tool_id).is_none() {
+            debug!("tool_result for unknown tool use {}", event.tool_id);
+        }
+        let has_diff = self.diff_tools.remove(&event.tool_id);
+
+        let failed = event.status != "success";
+        let text = if failed {
+            event
+                .error
+                .as_ref()
+                .map(|error| error.message.clone())
+                .or(event.output.clone())
+                .unwrap_or_default()
+        } else {
+            event.output.clone().unwrap_or_default()
+        };
+        let content = if (has_diff && !failed) || text.is_empty() {
+            None
+        } else {
+            Some(vec![ToolCallContent::from(text)])
+        };
+
+        Some(SessionUpdate::ToolCallUpdate(ToolCallUpdate {
+            id: ToolCallId(Arc::from(event.tool_id.as_str())),
+            fields: ToolCallUpdateFields {
+                status: Some(if failed {
+                    ToolCallStatus::Failed
+                } else {
+                    ToolCallStatus::Completed
+                }),
+                content,
+                raw_output: event.output.map(Value::String),
+                ..Default::default()
+            },
+            meta: None,
+        }))
+    }
+}
+
+impl TurnMapper for GeminiStreamMapper {
+    fn process_line(&mut self, line: &str) -> Vec<SessionNotification> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Vec::new();
+        }
+        match serde_json::from_str::<GeminiEvent>(line) {
+            Ok(event) => self.process_event(event),
+            Err(err) => {
+                warn!("Ignoring malformed Gemini output line: {} ({})", line, err);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Gemini's own session id, used with `--resume` on the next turn.
+    fn resume_id(&self) -> Option<&str> {
+        self.gemini_session_id.as_deref()
+    }
+
+    /// Model reported by the `init` event.
+    fn model(&self) -> Option<&str> {
+        self.model.as_deref()
+    }
+
+    /// Stop reason and stats from the `result` event.
+    fn response(&self) -> Option<Result<PromptResponse, Error>> {
+        self.result.as_ref().map(|result| {
+            Ok(PromptResponse {
+                stop_reason: result.stop_reason()?,
+                meta: result.meta(),
+            })
+        })
+    }
+}
+
+fn str_field<'a>(input: &'a Value, key: &str) -> Option<&'a str> {
+    input.get(key).and_then(Value::as_str)
+}
+
+fn location(path: &str) -> ToolCallLocation {
+    ToolCallLocation {
+        path: PathBuf::from(path),
+        line: None,
+        meta: None,
+    }
+}
+
+/// Build the ACP tool call for a Gemini `tool_use` event.
+pub fn tool_call_from_use(id: &str, name: &str, parameters: Value) -> ToolCall {
+    let mut content = Vec::new();
+    let mut locations = Vec::new();
+    let file_path = str_field(&parameters, "file_path")
+        .or_else(|| str_field(&parameters, "absolute_path"))
+        .unwrap_or_default();
+
+    let (kind, title) = match name {
+        "run_shell_command" => {
+            let command = str_field(&parameters, "command").unwrap_or_default();
+            (ToolKind::Execute, format!("Run {}", command))
+        }
+        "read_file" => {
+            if !file_path.is_empty() {
+                locations.push(location(file_path));
+            }
+            (ToolKind::Read, format!("Read {}", file_path))
+        }
+        "read_many_files" => {
+            let paths: Vec<&str> = parameters
+                .get("paths")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .collect();
+            (ToolKind::Read, format!("Read {}", paths.join(", ")))
+        }
+        "write_file" | "replace" => {
+            if !file_path.is_empty() {
+                locations.push(location(file_path));
+                let (old_text, new_text) = if name == "write_file" {
+                    (None, str_field(&parameters, "content"))
+                } else {
+                    (
+                        str_field(&parameters, "old_string"),
+                        str_field(&parameters, "new_string"),
+                    )
+                };
+                content.push(ToolCallContent::from(Diff {
+                    path: PathBuf::from(file_path),
+                    old_text: old_text.map(str::to_string),
+                    new_text: new_text.unwrap_or_default().to_string(),
+                    meta: None,
+                }));
+            }
+            (ToolKind::Edit, format!("Edit {}", file_path))
+        }
+        "glob" | "search_file_content" => {
+            let pattern = str_field(&parameters, "pattern").unwrap_or_default();
+            (ToolKind::Search, format!("Search {}", pattern))
+        }
+        "list_directory" => {
+            let path = str_field(&parameters, "path").unwrap_or_default();
+            (ToolKind::Search, format!("List {}", path))
+        }
+        "web_fetch" => {
+            let prompt = str_field(&parameters, "prompt").unwrap_or_default();
+            (ToolKind::Fetch, format!("Fetch {}", prompt))
+        }
+        "google_web_search" => {
+            let query = str_field(&parameters, "query").unwrap_or_default();
+            (ToolKind::Fetch, format!("Search the web for {}", query))
+        }
+        _ => (ToolKind::Other, name.to_string()),
+    };
+
+    ToolCall {
+        id: ToolCallId(Arc::from(id)),
+        title: title.trim_end().to_string(),
+        kind,
+        status: ToolCallStatus::InProgress,
+        content,
+        locations,
+        raw_input: Some(parameters),
+        raw_output: None,
+        meta: None,
+    }
+}
+
+/// Convert `write_todos` input (`{"todos": [{description, status}]}`) to a
+/// plan; cancelled todos are left out.
+pub fn plan_from_todos(parameters: &Value) -> Plan {
+    let todos = parameters.get("todos").and_then(Value::as_array);
+    let entries = todos
+        .into_iter()
+        .flatten()
+        .filter_map(|todo| {
+            let content = str_field(todo, "description")?.to_string();
+            let status = match str_field(todo, "status") {
+                Some("cancelled") => return None,
+                Some("in_progress") => PlanEntryStatus::InProgress,
+                Some("completed") => PlanEntryStatus::Completed,
+                _ => PlanEntryStatus::Pending,
+            };
+            Some(PlanEntry {
+                content,
+                priority: PlanEntryPriority::Medium,
+                status,
+                meta: None,
+            })
+        })
+        .collect();
+    Plan {
+        entries,
+        meta: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(status: &str, error: Option<(&str, &str)>) -> GeminiResultEvent {
+        GeminiResultEvent {
+            status: status.to_string(),
+            error: error.map(|(kind, message)| GeminiError {
+                kind: kind.to_string(),
+                message: message.to_string(),
+            }),
+            stats: None,
+        }
+    }
+
+    #[test]
+    fn result_status_maps_to_stop_reasons() {
+        assert_eq!(
+            result("success", None).stop_reason(),
+            Ok(StopReason::EndTurn)
+        );
+        assert_eq!(
+            result("error", Some((TURN_LIMIT_ERROR, "limit"))).stop_reason(),
+            Ok(StopReason::MaxTurnRequests)
+        );
+        // ast-grep-ignore: rust-no-unwrap
+        let err = result("error", Some(("ApiError", "quota exceeded")))
+            .stop_reason()
+            .unwrap_err();
+        assert_eq!(err.data, Some(json!("quota exceeded")));
+    }
+
+    #[test]
+    fn cancelled_todos_are_left_out_of_plans() {
+        let plan = plan_from_todos(&json!({
+            "todos": [
+                {"description": "Read the code", "status": "completed"},
+                {"description": "Drop this", "status": "cancelled"},
+                {"description": "Write the fix", "status": "in_progress"},
+            ]
+        }));
+        let entries: Vec<_> = plan
+            .entries
+            .iter()
+            .map(|entry| (entry.content.as_str(), entry.status.clone()))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                ("Read the code", PlanEntryStatus::Completed),
+                ("Write the fix", PlanEntryStatus::InProgress),
+            ]
+        );
+    }
+}
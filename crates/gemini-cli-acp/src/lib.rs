@@ -0,0 +1,5 @@
+//! Library interface for gemini-cli-acp
+
+pub mod gemini_agent;
+pub mod gemini_proto;
+pub mod permissions;
@@ -0,0 +1,11 @@
+use acp_lazy_core::config::ConfigLoader;
+use acp_lazy_core::logging;
+use acp_lazy_core::runtime::serve_stdio;
+use anyhow::Result;
+use gemini_cli_acp::gemini_agent;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+    logging::init();
+    serve_stdio(gemini_agent::runtime(ConfigLoader::new())?).await
+}
@@ -0,0 +1,58 @@
+//! Permission mapping between ACP modes and Gemini CLI flags.
+//!
+//! In non-interactive runs Gemini CLI leaves out tools that would need a
+//! confirmation, so `--approval-mode` decides what the turn may do.
+//! Auto-approving everything is only sandboxed in bypass mode; YOLO runs
+//! unsandboxed.
+
+use acp_lazy_core::permissions::AcpPermissionMode;
+
+/// Gemini `--approval-mode` value for an ACP mode.
+pub fn gemini_approval_mode(mode: AcpPermissionMode) -> &'static str {
+    match mode {
+        AcpPermissionMode::Default | AcpPermissionMode::Plan => "default",
+        AcpPermissionMode::AcceptEdits => "auto_edit",
+        AcpPermissionMode::BypassPermissions | AcpPermissionMode::Yolo => "yolo",
+    }
+}
+
+/// Map an ACP permission mode to Gemini CLI arguments.
+pub fn map_acp_to_gemini(mode: AcpPermissionMode) -> Vec<String> {
+    let mut args = vec![
+        "--approval-mode".to_string(),
+        gemini_approval_mode(mode).to_string(),
+    ];
+    if mode == AcpPermissionMode::BypassPermissions {
+        args.push("--sandbox".to_string());
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modes_map_to_approval_and_sandbox_flags() {
+        assert_eq!(
+            map_acp_to_gemini(AcpPermissionMode::Default),
+            vec!["--approval-mode", "default"]
+        );
+        assert_eq!(
+            map_acp_to_gemini(AcpPermissionMode::Plan),
+            vec!["--approval-mode", "default"]
+        );
+        assert_eq!(
+            map_acp_to_gemini(AcpPermissionMode::AcceptEdits),
+            vec!["--approval-mode", "auto_edit"]
+        );
+        assert_eq!(
+            map_acp_to_gemini(AcpPermissionMode::BypassPermissions),
+            vec!["--approval-mode", "yolo", "--sandbox"]
+        );
+        assert_eq!(
+            map_acp_to_gemini(AcpPermissionMode::Yolo),
+            vec!["--approval-mode", "yolo"]
+        );
+    }
+}
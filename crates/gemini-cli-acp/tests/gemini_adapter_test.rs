@@ -0,0 +1,115 @@
+//! Gemini adapter tests against a scripted stand-in for the Gemini CLI.
+//!
+//! The shared `fake-cli` replays a recorded `stream-json` transcript from
+//! `tests/fixtures/gemini/`; see `support/cli_adapter.rs`.
+
+#[path = "../../acp-lazy-core/tests/support/cli_adapter.rs"]
+mod support;
+
+use acp_lazy_core::config::{BridgeConfig, GeminiSection};
+use agent_client_protocol::{ContentBlock, SessionUpdate, StopReason, ToolCallContent};
+use anyhow::Result;
+use gemini_cli_acp::gemini_agent;
+use support::{assert_tool_use_turn, fake_cli, fixtures_dir, FakeCli, TestSession};
+
+/// Session whose Gemini command replays `transcript`.
+async fn fake_gemini(cli: &FakeCli, transcript: &str) -> Result<TestSession> {
+    let overrides = BridgeConfig {
+        gemini: GeminiSection {
+            command: Some(fake_cli().display().to_string()),
+            args: Some(cli.args(&fixtures_dir().join("gemini").join(transcript))),
+        },
+        ..BridgeConfig::default()
+    };
+    cli.session(gemini_agent::runtime(cli.config_loader(overrides)?)?)
+        .await
+}
+
+#[tokio::test]
+async fn assistant_messages_stream_as_chunks() -> Result<()> {
+    let cli = FakeCli::new()?;
+    let mut session = fake_gemini(&cli, "hello.jsonl").await?;
+
+    let response = session.prompt("Say hello").await?;
+
+    assert_eq!(response.stop_reason, StopReason::EndTurn);
+    // ast-grep-ignore: rust-no-unwrap
+    let meta = response.meta.unwrap();
+    assert_eq!(meta["usage"]["output_tokens"], 5);
+
+    // The user message echoing the prompt is not forwarded.
+    let updates = session.drain();
+    let chunks: Vec<_> = updates
+        .iter()
+        .map(|update| match update {
+            SessionUpdate::AgentMessageChunk {
+                content: ContentBlock::Text(text),
+            } => text.text.as_str(),
+            other => panic!("expected a message chunk, got {:?}", other),
+        })
+        .collect();
+    assert_eq!(chunks, vec!["Hello ", "from Gemini!"]);
+
+    let recorded = cli.recorded()?;
+    assert!(recorded.contains("--output-format stream-json"));
+    assert!(recorded.contains("--approval-mode default"));
+    assert!(!recorded.contains("--sandbox"));
+    assert!(!recorded.contains("--resume"));
+    assert!(recorded.contains("PROMPT: Say hello"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn later_turns_resume_the_gemini_session_with_the_current_mode() -> Result<()> {
+    let cli = FakeCli::new()?;
+    let session = fake_gemini(&cli, "hello.jsonl").await?;
+
+    session.prompt("first").await?;
+    session.set_mode("bypassPermissions").await?;
+    session.prompt("second").await?;
+
+    let recorded = cli.recorded()?;
+    assert!(recorded.contains("--approval-mode yolo --sandbox"));
+    assert!(recorded.contains("--resume gemini-session-1"));
+    assert!(recorded.contains("PROMPT: second"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn tool_use_maps_to_tool_calls_and_plans() -> Result<()> {
+    let cli = FakeCli::new()?;
+    let mut session = fake_gemini(&cli, "tool_use.jsonl").await?;
+
+    let response = session.prompt("Fix it").await?;
+    assert_eq!(response.stop_reason, StopReason::EndTurn);
+
+    let updates = session.drain();
+    assert_tool_use_turn(&updates, "run_shell_command-2");
+    // Gemini reports the failure text on the tool result.
+    let SessionUpdate::ToolCallUpdate(read_failed) = &updates[6] else {
+        panic!("expected a tool call update, got {:?}", updates[6]);
+    };
+    assert_eq!(
+        read_failed.fields.content,
+        Some(vec![ToolCallContent::from(
+            "File not found: /repo/missing.rs"
+        )])
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn error_results_fail_the_prompt() -> Result<()> {
+    let cli = FakeCli::new()?;
+    let session = fake_gemini(&cli, "error.jsonl").await?;
+
+    let err = session
+        .prompt("hello")
+        .await
+        .expect_err("error result should fail the prompt");
+    assert_eq!(
+        err.data,
+        Some(serde_json::json!("Quota exceeded for quota metric"))
+    );
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! Command-line interface for the `jsonl-cli-acp` binary.
+
+use std::path::PathBuf;
+
+use acp_lazy_core::config::{BridgeConfig, ConfigLoader, RuntimeSection};
+use acp_lazy_core::logging::LogFormat;
+use acp_lazy_core::permissions::AcpPermissionMode;
+use clap::{Parser, ValueEnum};
+
+/// ACP agent server for any CLI that prints line-delimited JSON events.
+#[derive(Debug, Parser)]
+#[command(name = "jsonl-cli-acp", version)]
+pub struct Cli {
+    /// Adapter spec (TOML) describing the command and its event mapping.
+    #[arg(long, value_name = "PATH")]
+    pub adapter: PathBuf,
+
+    /// Configuration file used instead of ~/.config/acplazybridge/config.toml.
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Log line format.
+    #[arg(long, value_enum, default_value_t = LogFormatArg::Text)]
+    pub log_format: LogFormatArg,
+
+    /// Append logs to this file instead of stderr.
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Permission mode for new sessions (default, plan, acceptEdits, bypassPermissions, yolo).
+    #[arg(long, value_name = "MODE", value_parser = parse_mode)]
+    pub default_mode: Option<AcpPermissionMode>,
+
+    /// Idle timeout for a turn, in milliseconds.
+    #[arg(long, value_name = "MS")]
+    pub idle_timeout: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormatArg {
+    Text,
+    Json,
+}
+
+impl From<LogFormatArg> for LogFormat {
+    fn from(format: LogFormatArg) -> Self {
+        match format {
+            LogFormatArg::Text => LogFormat::Text,
+            LogFormatArg::Json => LogFormat::Json,
+        }
+    }
+}
+
+impl Cli {
+    /// Configuration layer built from the flags.
+    pub fn config_overrides(&self) -> BridgeConfig {
+        BridgeConfig {
+            runtime: RuntimeSection {
+                idle_timeout_ms: self.idle_timeout,
+                default_mode: self.default_mode,
+                ..RuntimeSection::default()
+            },
+            ..BridgeConfig::default()
+        }
+    }
+
+    /// Loader applying `--config` and the flag overrides.
+    pub fn config_loader(&self) -> ConfigLoader {
+        let loader = ConfigLoader::new().with_overrides(self.config_overrides());
+        match &self.config {
+            Some(path) => loader.with_user_config(path),
+            None => loader,
+        }
+    }
+}
+
+fn parse_mode(value: &str) -> Result<AcpPermissionMode, String> {
+    value
+        .parse()
+        .map_err(|()| format!("unknown permission mode `{}`", value))
+}
@@ -0,0 +1,100 @@
+use std::process::ExitStatus;
+
+use acp_lazy_core::config::{ConfigError, ConfigLoader};
+use acp_lazy_core::runtime::{
+    RuntimeServer, TurnAdapter, TurnCli, TurnCommand, TurnContext, TurnMapper,
+};
+use agent_client_protocol::{
+    AgentCapabilities, Error, McpCapabilities, PromptResponse, SessionId, StopReason,
+};
+use tracing::warn;
+
+use crate::mapper::JsonlStreamMapper;
+use crate::spec::{AdapterSpec, TurnVars};
+
+/// The spec's command, run once per prompt.
+///
+/// The CLI gets the prompt as described by `[prompt]`, its stdout events are
+/// mapped by `[events]` and the turn is over when the process exits. A
+/// `session` rule lets later turns resume the CLI's own session.
+pub struct JsonlCli {
+    spec: AdapterSpec,
+}
+
+impl JsonlCli {
+    pub fn new(spec: AdapterSpec) -> Self {
+        Self { spec }
+    }
+}
+
+impl TurnCli for JsonlCli {
+    fn name(&self) -> &str {
+        &self.spec.name
+    }
+
+    fn agent_capabilities(&self) -> AgentCapabilities {
+        AgentCapabilities {
+            load_session: false,
+            prompt_capabilities: agent_client_protocol::PromptCapabilities {
+                image: false,
+                audio: false,
+                embedded_context: true,
+                meta: None,
+            },
+            mcp_capabilities: McpCapabilities {
+                http: false,
+                sse: false,
+                meta: None,
+            },
+            meta: None,
+        }
+    }
+
+    fn turn_command(&self, turn: TurnContext<'_>) -> Result<TurnCommand, Error> {
+        let session = turn.session;
+        if !session.mcp_servers.is_empty() {
+            warn!(
+                "Ignoring {} client MCP server(s) for {} session {}",
+                session.mcp_servers.len(),
+                self.spec.name,
+                session.session_id.0
+            );
+        }
+        let vars = TurnVars {
+            prompt: turn.prompt,
+            cwd: session.working_dir.display().to_string(),
+            session_id: session.session_id.0.to_string(),
+            resume_id: turn.conversation.resume_id.clone(),
+        };
+        Ok(TurnCommand {
+            program: self.spec.command.clone(),
+            args: self.spec.turn_args(&vars, session.permission_mode),
+            stdin: self.spec.stdin_input(&vars),
+        })
+    }
+
+    fn mapper(&self, session_id: SessionId) -> Box<dyn TurnMapper + '_> {
+        Box::new(JsonlStreamMapper::new(&self.spec, session_id))
+    }
+
+    /// Without a `turn_end` rule the exit status decides.
+    fn exited_without_result(&self, status: ExitStatus) -> Result<PromptResponse, Error> {
+        if status.success() {
+            Ok(PromptResponse {
+                stop_reason: StopReason::EndTurn,
+                meta: None,
+            })
+        } else {
+            Err(Error::internal_error()
+                .with_data(format!("{} exited with {}", self.spec.name, status)))
+        }
+    }
+}
+
+/// Runtime serving the CLI described by `spec` over ACP.
+pub fn runtime(
+    spec: AdapterSpec,
+    config_loader: ConfigLoader,
+) -> Result<RuntimeServer, ConfigError> {
+    TurnAdapter::runtime(JsonlCli::new(spec), config_loader)
+}
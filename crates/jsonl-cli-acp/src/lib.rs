@@ -0,0 +1,6 @@
+//! Library interface for jsonl-cli-acp
+
+pub mod cli;
+pub mod jsonl_agent;
+pub mod mapper;
+pub mod spec;
@@ -0,0 +1,18 @@
+use acp_lazy_core::logging;
+use acp_lazy_core::runtime::serve_stdio;
+use anyhow::{Context, Result};
+use clap::Parser;
+use jsonl_cli_acp::cli::Cli;
+use jsonl_cli_acp::jsonl_agent;
+use jsonl_cli_acp::spec::AdapterSpec;
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    logging::init_with(cli.log_format.into(), cli.log_file.as_deref())
+        .context("failed to open log file")?;
+    let spec = AdapterSpec::from_file(&cli.adapter)?;
+
+    serve_stdio(jsonl_agent::runtime(spec, cli.config_loader())?).await
+}
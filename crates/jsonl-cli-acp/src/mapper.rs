@@ -0,0 +1,362 @@
+//! Maps stdout events onto ACP session updates using the spec's rules.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use acp_lazy_core::runtime::TurnMapper;
+use agent_client_protocol::{
+    ContentBlock, Error, Plan, PlanEntry, PlanEntryPriority, PlanEntryStatus, PromptResponse,
+    SessionId, SessionNotification, SessionUpdate, StopReason, ToolCall, ToolCallContent,
+    ToolCallId, ToolCallLocation, ToolCallStatus, ToolCallUpdate, ToolCallUpdateFields, ToolKind,
+};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use tracing::{debug, warn};
+
+use crate::spec::{AdapterSpec, EventRule, UpdateKind};
+
+/// The event matched by a `turn_end` rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TurnEndEvent {
+    pub stop_reason: Option<String>,
+    pub error: Option<String>,
+    pub usage: Option<Value>,
+}
+
+impl TurnEndEvent {
+    /// ACP stop reason for the turn, or the error the CLI reported.
+    ///
+    /// Unrecognised stop reasons end the turn normally.
+    pub fn stop_reason(&self) -> Result<StopReason, Error> {
+        if let Some(error) = &self.error {
+            return Err(Error::internal_error().with_data(error.clone()));
+        }
+        Ok(self
+            .stop_reason
+            .as_deref()
+            .and_then(parse_enum)
+            .unwrap_or(StopReason::EndTurn))
+    }
+
+    /// `PromptResponse` meta carrying the reported usage.
+    pub fn meta(&self) -> Option<Value> {
+        self.usage.as_ref().map(|usage| json!({ "usage": usage }))
+    }
+}
+
+/// Stateful mapper from CLI events to ACP notifications for one turn.
+pub struct JsonlStreamMapper<'a> {
+    spec: &'a AdapterSpec,
+    session_id: SessionId,
+    resume_id: Option<String>,
+    turn_end: Option<TurnEndEvent>,
+}
+
+impl<'a> JsonlStreamMapper<'a> {
+    pub fn new(spec: &'a AdapterSpec, session_id: SessionId) -> Self {
+        Self {
+            spec,
+            session_id,
+            resume_id: None,
+            turn_end: None,
+        }
+    }
+
+    /// The `turn_end` event, once one was seen.
+    pub fn turn_end(&self) -> Option<&TurnEndEvent> {
+        self.turn_end.as_ref()
+    }
+
+    pub fn process_event(&mut self, event: &Value) -> Vec<SessionNotification> {
+        let Some(event_type) = text_at(event, &self.spec.events.type_pointer) else {
+            debug!("{} event without a type: {}", self.spec.name, event);
+            return Vec::new();
+        };
+        let spec = self.spec;
+        let updates: Vec<SessionUpdate> = spec
+            .events
+            .rules
+            .iter()
+            .filter(|rule| rule.on == event_type && matches_when(rule, event))
+            .filter_map(|rule| self.apply(rule, event))
+            .collect();
+        updates
+            .into_iter()
+            .map(|update| SessionNotification {
+                session_id: self.session_id.clone(),
+                update,
+                meta: None,
+            })
+            .collect()
+    }
+
+    fn apply(&mut self, rule: &EventRule, event: &Value) -> Option<SessionUpdate> {
+        match rule.update {
+            UpdateKind::MessageChunk => {
+                field(event, &rule.text).map(|text| SessionUpdate::AgentMessageChunk {
+                    content: ContentBlock::from(text),
+                })
+            }
+            UpdateKind::ThoughtChunk => {
+                field(event, &rule.text).map(|text| SessionUpdate::AgentThoughtChunk {
+                    content: ContentBlock::from(text),
+                })
+            }
+            UpdateKind::ToolCall => self.tool_call(rule, event).map(SessionUpdate::ToolCall),
+            UpdateKind::ToolCallUpdate => self
+                .tool_call_update(rule, event)
+                .map(SessionUpdate::ToolCallUpdate),
+            UpdateKind::Plan => Some(SessionUpdate::Plan(self.plan(rule, event))),
+            UpdateKind::TurnEnd => {
+                self.turn_end = Some(TurnEndEvent {
+                    stop_reason: value(event, &rule.stop_reason),
+                    error: field(event, &rule.error),
+                    usage: raw(event, &rule.usage).cloned(),
+                });
+                None
+            }
+            UpdateKind::Session => {
+                if let Some(id) = field(event, &rule.id) {
+                    self.resume_id = Some(id);
+                }
+                None
+            }
+        }
+    }
+
+    fn tool_call(&self, rule: &EventRule, event: &Value) -> Option<ToolCall> {
+        let id = field(event, &rule.id)?;
+        let status = value(event, &rule.status).and_then(|status| self.status(&status));
+        Some(ToolCall {
+            id: ToolCallId(Arc::from(id)),
+            title: value(event, &rule.title).unwrap_or_default(),
+            kind: value(event, &rule.kind)
+                .and_then(|kind| parse_enum(&kind))
+                .unwrap_or(ToolKind::Other),
+            status: status.unwrap_or(ToolCallStatus::InProgress),
+            content: field(event, &rule.output)
+                .map(|output| vec![ToolCallContent::from(output)])
+                .unwrap_or_default(),
+            locations: field(event, &rule.path)
+                .map(|path| {
+                    vec![ToolCallLocation {
+                        path: PathBuf::from(path),
+                        line: None,
+                        meta: None,
+                    }]
+                })
+                .unwrap_or_default(),
+            raw_input: raw(event, &rule.input).cloned(),
+            raw_output: None,
+            meta: None,
+        })
+    }
+
+    fn tool_call_update(&self, rule: &EventRule, event: &Value) -> Option<ToolCallUpdate> {
+        let id = field(event, &rule.id)?;
+        Some(ToolCallUpdate {
+            id: ToolCallId(Arc::from(id)),
+            fields: ToolCallUpdateFields {
+                status: value(event, &rule.status).and_then(|status| self.status(&status)),
+                title: value(event, &rule.title),
+                content: field(event, &rule.output)
+                    .map(|output| vec![ToolCallContent::from(output)]),
+                raw_output: raw(event, &rule.output).cloned(),
+                ..Default::default()
+            },
+            meta: None,
+        })
+    }
+
+    fn plan(&self, rule: &EventRule, event: &Value) -> Plan {
+        let entries = raw(event, &rule.entries)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                Some(PlanEntry {
+                    content: field(entry, &rule.content)?,
+                    priority: PlanEntryPriority::Medium,
+                    status: value(entry, &rule.status)
+                        .and_then(|status| parse_enum(self.spec.acp_status(&status)))
+                        .unwrap_or(PlanEntryStatus::Pending),
+                    meta: None,
+                })
+            })
+            .collect();
+        Plan {
+            entries,
+            meta: None,
+        }
+    }
+
+    fn status(&self, status: &str) -> Option<ToolCallStatus> {
+        parse_enum(self.spec.acp_status(status))
+    }
+}
+
+impl TurnMapper for JsonlStreamMapper<'_> {
+    /// Map one stdout line; non-JSON lines are logged and skipped.
+    fn process_line(&mut self, line: &str) -> Vec<SessionNotification> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Vec::new();
+        }
+        match serde_json::from_str::<Value>(line) {
+            Ok(event) => self.process_event(&event),
+            Err(err) => {
+                warn!(
+                    "Ignoring non-JSON {} output line: {} ({})",
+                    self.spec.name, line, err
+                );
+                Vec::new()
+            }
+        }
+    }
+
+    /// Session id captured by a `session` rule.
+    fn resume_id(&self) -> Option<&str> {
+        self.resume_id.as_deref()
+    }
+
+    fn response(&self) -> Option<Result<PromptResponse, Error>> {
+        self.turn_end.as_ref().map(|turn_end| {
+            Ok(PromptResponse {
+                stop_reason: turn_end.stop_reason()?,
+                meta: turn_end.meta(),
+            })
+        })
+    }
+}
+
+fn matches_when(rule: &EventRule, event: &Value) -> bool {
+    rule.when
+        .iter()
+        .all(|(pointer, expected)| text_at(event, pointer).as_deref() == Some(expected.as_str()))
+}
+
+/// Non-null value at `pointer`.
+fn raw<'v>(event: &'v Value, pointer: &Option<String>) -> Option<&'v Value> {
+    event
+        .pointer(pointer.as_deref()?)
+        .filter(|value| !value.is_null())
+}
+
+/// Value at `pointer` as text; non-string values are rendered as JSON.
+fn field(event: &Value, pointer: &Option<String>) -> Option<String> {
+    raw(event, pointer).map(to_text)
+}
+
+/// A pointer (leading `/`) or a literal.
+fn value(event: &Value, spec: &Option<String>) -> Option<String> {
+    match spec.as_deref()? {
+        pointer if pointer.starts_with('/') => field(event, spec),
+        literal => Some(literal.to_string()),
+    }
+}
+
+fn text_at(event: &Value, pointer: &str) -> Option<String> {
+    event.pointer(pointer).map(to_text)
+}
+
+fn to_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Parse a snake_case ACP enum value such as a tool kind or stop reason.
+fn parse_enum<T: DeserializeOwned>(value: &str) -> Option<T> {
+    serde_json::from_value(Value::String(value.to_string())).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn rules_map_events_to_updates() {
+        // ast-grep-ignore: rust-no-unwrap
+        let spec = AdapterSpec::from_toml_str(
+            r#"
+            command = "acme"
+
+            [statuses]
+            ok = "completed"
+
+            [[events.rules]]
+            on = "message"
+            when = { "/role" = "assistant" }
+            update = "message_chunk"
+            text = "/text"
+
+            [[events.rules]]
+            on = "tool"
+            update = "tool_call"
+            id = "/id"
+            title = "/name"
+            kind = "/kind"
+            path = "/args/file"
+
+            [[events.rules]]
+            on = "tool_done"
+            update = "tool_call_update"
+            id = "/id"
+            status = "/status"
+            output = "/output"
+
+            [[events.rules]]
+            on = "done"
+            update = "turn_end"
+            stop_reason = "max_tokens"
+            usage = "/usage"
+            "#,
+            Path::new("spec.toml"),
+        )
+        .unwrap();
+        let mut mapper = JsonlStreamMapper::new(&spec, SessionId(Arc::from("s-1")));
+
+        assert!(mapper
+            .process_line(r#"{"type":"message","role":"user","text":"hi"}"#)
+            .is_empty());
+        assert!(mapper.process_line("not json").is_empty());
+
+        let updates: Vec<SessionUpdate> = [
+            r#"{"type":"message","role":"assistant","text":"hello"}"#,
+            r#"{"type":"tool","id":"t1","name":"Read","kind":"read","args":{"file":"/a.rs"}}"#,
+            r#"{"type":"tool_done","id":"t1","status":"ok","output":{"lines":3}}"#,
+            r#"{"type":"done","usage":{"tokens":9}}"#,
+        ]
+        .into_iter()
+        .flat_map(|line| mapper.process_line(line))
+        .map(|notification| notification.update)
+        .collect();
+
+        assert_eq!(updates.len(), 3, "{:#?}", updates);
+        assert!(matches!(
+            &updates[0],
+            SessionUpdate::AgentMessageChunk { content: ContentBlock::Text(text) }
+                if text.text == "hello"
+        ));
+        let SessionUpdate::ToolCall(call) = &updates[1] else {
+            panic!("expected a tool call, got {:?}", updates[1]);
+        };
+        assert_eq!(call.kind, ToolKind::Read);
+        assert_eq!(call.locations[0].path, PathBuf::from("/a.rs"));
+        let SessionUpdate::ToolCallUpdate(done) = &updates[2] else {
+            panic!("expected a tool call update, got {:?}", updates[2]);
+        };
+        assert_eq!(done.fields.status, Some(ToolCallStatus::Completed));
+        assert_eq!(
+            done.fields.content,
+            Some(vec![ToolCallContent::from("{\"lines\":3}")])
+        );
+
+        // ast-grep-ignore: rust-no-unwrap
+        let end = mapper.turn_end().unwrap();
+        assert_eq!(end.stop_reason(), Ok(StopReason::MaxTokens));
+        assert_eq!(end.meta(), Some(json!({"usage": {"tokens": 9}})));
+    }
+}
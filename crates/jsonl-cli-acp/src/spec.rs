@@ -0,0 +1,517 @@
+//! Adapter spec: how to run a line-delimited JSON CLI and read its output.
+//!
+//! The spec is a TOML file passed with `--adapter`. It names the command,
+//! how the prompt reaches it and which stdout events become which ACP
+//! session updates. Rule fields are JSON pointers into the event; `title`,
+//! `kind`, `status` and `stop_reason` also accept a literal when the value
+//! does not start with `/`. Every rule whose `on` matches the event type
+//! (and whose `when` pointers hold the given values) applies, in order.
+//!
+//! `{{prompt}}`, `{{cwd}}`, `{{session_id}}` and `{{resume_id}}` are
+//! substituted in `args`, `resume_args` and the JSON prompt template.
+//!
+//! ```toml
+//! name = "acme"
+//! command = "acme-agent"
+//! args = ["run", "--json"]
+//! resume_args = ["--continue", "{{resume_id}}"]
+//!
+//! [prompt]
+//! format = "json"   # "text" (default), "json" or "argument"
+//! template = { type = "user", text = "{{prompt}}" }
+//!
+//! [modes]
+//! acceptEdits = ["--allow-edits"]
+//!
+//! [statuses]
+//! ok = "completed"
+//! error = "failed"
+//!
+//! [events]
+//! type = "/type"
+//!
+//! [[events.rules]]
+//! on = "started"
+//! update = "session"
+//! id = "/session"
+//!
+//! [[events.rules]]
+//! on = "message"
+//! when = { "/role" = "assistant" }
+//! update = "message_chunk"
+//! text = "/text"
+//!
+//! [[events.rules]]
+//! on = "tool"
+//! update = "tool_call"
+//! id = "/id"
+//! title = "/name"
+//! kind = "execute"
+//!
+//! [[events.rules]]
+//! on = "tool_done"
+//! update = "tool_call_update"
+//! id = "/id"
+//! status = "/status"
+//! output = "/output"
+//!
+//! [[events.rules]]
+//! on = "done"
+//! update = "turn_end"
+//! error = "/error"
+//! usage = "/usage"
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use acp_lazy_core::config::ConfigError;
+use acp_lazy_core::permissions::AcpPermissionMode;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// ACP tool call and plan statuses `[statuses]` may map onto.
+const ACP_STATUSES: [&str; 4] = ["pending", "in_progress", "completed", "failed"];
+
+/// A line-delimited JSON CLI exposed over ACP.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AdapterSpec {
+    /// Name used in logs and error messages.
+    #[serde(default = "default_name")]
+    pub name: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Appended once a `session` rule captured an id to resume.
+    #[serde(default)]
+    pub resume_args: Vec<String>,
+    #[serde(default)]
+    pub prompt: PromptSpec,
+    /// Extra arguments per ACP permission mode id.
+    #[serde(default)]
+    pub modes: BTreeMap<String, Vec<String>>,
+    /// CLI status values mapped to ACP statuses.
+    #[serde(default)]
+    pub statuses: BTreeMap<String, String>,
+    pub events: EventsSpec,
+}
+
+fn default_name() -> String {
+    "jsonl".to_string()
+}
+
+/// `[prompt]`: how the prompt reaches the CLI.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PromptSpec {
+    #[serde(default)]
+    pub format: PromptFormat,
+    /// JSON value written as one line on stdin for `format = "json"`.
+    pub template: Option<Value>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptFormat {
+    /// The prompt text on stdin.
+    #[default]
+    Text,
+    /// `template` as one JSON line on stdin.
+    Json,
+    /// `{{prompt}}` in `args`; stdin stays empty.
+    Argument,
+}
+
+/// `[events]`: where the event type lives and how events map to updates.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EventsSpec {
+    #[serde(rename = "type", default = "default_type_pointer")]
+    pub type_pointer: String,
+    pub rules: Vec<EventRule>,
+}
+
+fn default_type_pointer() -> String {
+    "/type".to_string()
+}
+
+/// One `[[events.rules]]` entry.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EventRule {
+    /// Event type this rule applies to.
+    pub on: String,
+    /// Pointers that must hold the given values.
+    #[serde(default)]
+    pub when: BTreeMap<String, String>,
+    pub update: UpdateKind,
+    pub text: Option<String>,
+    pub id: Option<String>,
+    pub title: Option<String>,
+    pub kind: Option<String>,
+    pub status: Option<String>,
+    pub input: Option<String>,
+    pub output: Option<String>,
+    pub path: Option<String>,
+    /// Array of plan entries; `content` and `status` point into each entry.
+    pub entries: Option<String>,
+    pub content: Option<String>,
+    pub stop_reason: Option<String>,
+    /// A non-null value here fails the prompt with it as the message.
+    pub error: Option<String>,
+    pub usage: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateKind {
+    MessageChunk,
+    ThoughtChunk,
+    ToolCall,
+    ToolCallUpdate,
+    Plan,
+    TurnEnd,
+    /// Captures the CLI's session id for `resume_args`.
+    Session,
+}
+
+/// Values substituted into arguments and the prompt template for one turn.
+#[derive(Debug, Clone, Default)]
+pub struct TurnVars {
+    pub prompt: String,
+    pub cwd: String,
+    pub session_id: String,
+    pub resume_id: Option<String>,
+}
+
+impl TurnVars {
+    fn value(&self, name: &str) -> Option<&str> {
+        match name {
+            "prompt" => Some(&self.prompt),
+            "cwd" => Some(&self.cwd),
+            "session_id" => Some(&self.session_id),
+            "resume_id" => Some(self.resume_id.as_deref().unwrap_or_default()),
+            _ => None,
+        }
+    }
+
+    /// Replace placeholders in one pass, so `{{...}}` inside a substituted
+    /// value (say, in the prompt) is left alone. Unknown names stay as written.
+    fn substitute(&self, template: &str) -> String {
+        let mut out = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find("{{") {
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            match after
+                .find("}}")
+                .and_then(|end| Some((end, self.value(&after[..end])?)))
+            {
+                Some((end, value)) => {
+                    out.push_str(value);
+                    rest = &after[end + 2..];
+                }
+                None => {
+                    out.push_str("{{");
+                    rest = after;
+                }
+            }
+        }
+        out.push_str(rest);
+        out
+    }
+
+    fn substitute_json(&self, template: &Value) -> Value {
+        match template {
+            Value::String(text) => Value::String(self.substitute(text)),
+            Value::Array(items) => items.iter().map(|v| self.substitute_json(v)).collect(),
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(key, value)| (key.clone(), self.substitute_json(value)))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+}
+
+impl AdapterSpec {
+    /// Parse and validate a spec; `origin` names it in errors.
+    pub fn from_toml_str(contents: &str, origin: &Path) -> Result<Self, ConfigError> {
+        let spec: Self = toml::from_str(contents).map_err(|source| ConfigError::Parse {
+            path: origin.to_path_buf(),
+            source,
+        })?;
+        spec.validate(&origin.display().to_string())?;
+        Ok(spec)
+    }
+
+    /// Read a spec file.
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let contents = std::fs::read_to_string(path).map_err(|source| ConfigError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::from_toml_str(&contents, path)
+    }
+
+    fn validate(&self, origin: &str) -> Result<(), ConfigError> {
+        let invalid = |message: String| ConfigError::Invalid {
+            origin: origin.to_string(),
+            message,
+        };
+
+        if self.command.trim().is_empty() {
+            return Err(invalid("command must not be empty".into()));
+        }
+        match self.prompt.format {
+            PromptFormat::Json if self.prompt.template.is_none() => {
+                return Err(invalid(
+                    "prompt.template is required when prompt.format = \"json\"".into(),
+                ));
+            }
+            PromptFormat::Argument if !self.args.iter().any(|arg| arg.contains("{{prompt}}")) => {
+                return Err(invalid(
+                    "args must contain {{prompt}} when prompt.format = \"argument\"".into(),
+                ));
+            }
+            _ => {}
+        }
+        for mode in self.modes.keys() {
+            if mode.parse::<AcpPermissionMode>().is_err() {
+                return Err(invalid(format!("unknown permission mode `{}`", mode)));
+            }
+        }
+        for (value, status) in &self.statuses {
+            if !ACP_STATUSES.contains(&status.as_str()) {
+                return Err(invalid(format!(
+                    "statuses.{} must be one of {}",
+                    value,
+                    ACP_STATUSES.join(", ")
+                )));
+            }
+        }
+        check_pointer("events.type", &self.events.type_pointer).map_err(invalid)?;
+        if self.events.rules.is_empty() {
+            return Err(invalid("events.rules must not be empty".into()));
+        }
+        for (index, rule) in self.events.rules.iter().enumerate() {
+            rule.validate(&format!("events.rules[{}]", index))
+                .map_err(invalid)?;
+        }
+        Ok(())
+    }
+
+    /// Command-line arguments for one turn.
+    pub fn turn_args(&self, vars: &TurnVars, mode: AcpPermissionMode) -> Vec<String> {
+        let mut args: Vec<String> = self.args.iter().map(|arg| vars.substitute(arg)).collect();
+        if let Some(mode_args) = self.modes.iter().find_map(|(id, args)| {
+            (id.parse::<AcpPermissionMode>().ok() == Some(mode)).then_some(args)
+        }) {
+            args.extend(mode_args.iter().cloned());
+        }
+        if vars.resume_id.is_some() {
+            args.extend(self.resume_args.iter().map(|arg| vars.substitute(arg)));
+        }
+        args
+    }
+
+    /// What to write on stdin before closing it.
+    pub fn stdin_input(&self, vars: &TurnVars) -> String {
+        match (self.prompt.format, &self.prompt.template) {
+            (PromptFormat::Text, _) => vars.prompt.clone(),
+            (PromptFormat::Json, Some(template)) => {
+                format!("{}\n", vars.substitute_json(template))
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// Apply `[statuses]` to a status value reported by the CLI.
+    pub fn acp_status<'a>(&'a self, value: &'a str) -> &'a str {
+        self.statuses.get(value).map_or(value, String::as_str)
+    }
+}
+
+impl EventRule {
+    fn validate(&self, section: &str) -> Result<(), String> {
+        let required: &[(&str, &Option<String>)] = match self.update {
+            UpdateKind::MessageChunk | UpdateKind::ThoughtChunk => &[("text", &self.text)],
+            UpdateKind::ToolCall => &[("id", &self.id), ("title", &self.title)],
+            UpdateKind::ToolCallUpdate | UpdateKind::Session => &[("id", &self.id)],
+            UpdateKind::Plan => &[("entries", &self.entries), ("content", &self.content)],
+            UpdateKind::TurnEnd => &[],
+        };
+        for (field, value) in required {
+            if value.is_none() {
+                return Err(format!("{}.{} is required for this update", section, field));
+            }
+        }
+
+        let pointers = [
+            ("text", &self.text),
+            ("id", &self.id),
+            ("input", &self.input),
+            ("output", &self.output),
+            ("path", &self.path),
+            ("entries", &self.entries),
+            ("content", &self.content),
+            ("error", &self.error),
+            ("usage", &self.usage),
+        ];
+        for (field, value) in pointers {
+            if let Some(pointer) = value {
+                check_pointer(&format!("{}.{}", section, field), pointer)?;
+            }
+        }
+        for pointer in self.when.keys() {
+            check_pointer(&format!("{}.when", section), pointer)?;
+        }
+        Ok(())
+    }
+}
+
+fn check_pointer(name: &str, pointer: &str) -> Result<(), String> {
+    if pointer.is_empty() || pointer.starts_with('/') {
+        Ok(())
+    } else {
+        Err(format!(
+            "{} must be a JSON pointer starting with `/`, got `{}`",
+            name, pointer
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(contents: &str) -> Result<AdapterSpec, ConfigError> {
+        AdapterSpec::from_toml_str(contents, Path::new("spec.toml"))
+    }
+
+    const MINIMAL: &str = r#"
+        command = "acme"
+        args = ["--cwd", "{{cwd}}"]
+        resume_args = ["--continue", "{{resume_id}}"]
+
+        [modes]
+        accept-edits = ["--allow-edits"]
+
+        [[events.rules]]
+        on = "delta"
+        update = "message_chunk"
+        text = "/text"
+    "#;
+
+    #[test]
+    fn turn_args_substitute_vars_and_add_mode_and_resume_args() {
+        // ast-grep-ignore: rust-no-unwrap
+        let spec = parse(MINIMAL).unwrap();
+        let mut vars = TurnVars {
+            prompt: "hi".into(),
+            cwd: "/repo".into(),
+            session_id: "s-1".into(),
+            resume_id: None,
+        };
+        assert_eq!(
+            spec.turn_args(&vars, AcpPermissionMode::Default),
+            vec!["--cwd", "/repo"]
+        );
+        assert_eq!(spec.stdin_input(&vars), "hi");
+
+        vars.resume_id = Some("acme-7".into());
+        assert_eq!(
+            spec.turn_args(&vars, AcpPermissionMode::AcceptEdits),
+            vec!["--cwd", "/repo", "--allow-edits", "--continue", "acme-7"]
+        );
+    }
+
+    #[test]
+    fn placeholders_in_values_are_not_substituted_again() {
+        let vars = TurnVars {
+            prompt: "explain {{cwd}} and {{session_id}}".into(),
+            cwd: "/repo".into(),
+            session_id: "s-1".into(),
+            resume_id: None,
+        };
+        assert_eq!(
+            vars.substitute("{{cwd}}: {{prompt}} {{unknown}} {{"),
+            "/repo: explain {{cwd}} and {{session_id}} {{unknown}} {{"
+        );
+    }
+
+    #[test]
+    fn json_prompts_render_the_template() {
+        // ast-grep-ignore: rust-no-unwrap
+        let spec = parse(
+            r#"
+            command = "acme"
+
+            [prompt]
+            format = "json"
+            template = { type = "user", text = "{{prompt}}", tags = ["{{session_id}}"] }
+
+            [[events.rules]]
+            on = "done"
+            update = "turn_end"
+        "#,
+        )
+        .unwrap();
+        let vars = TurnVars {
+            prompt: "say \"hi\"".into(),
+            session_id: "s-1".into(),
+            ..TurnVars::default()
+        };
+        let input = spec.stdin_input(&vars);
+        assert!(input.ends_with('\n'));
+        // ast-grep-ignore: rust-no-unwrap
+        let line: Value = serde_json::from_str(&input).unwrap();
+        assert_eq!(
+            line,
+            serde_json::json!({"type": "user", "text": "say \"hi\"", "tags": ["s-1"]})
+        );
+    }
+
+    #[test]
+    fn invalid_specs_are_rejected() {
+        for (contents, message) in [
+            (
+                "command = \"\"\n[[events.rules]]\non = \"x\"\nupdate = \"turn_end\"",
+                "command must not be empty",
+            ),
+            (
+                "command = \"acme\"\n[events]\nrules = []",
+                "events.rules must not be empty",
+            ),
+            (
+                "command = \"acme\"\n[[events.rules]]\non = \"x\"\nupdate = \"tool_call\"\nid = \"/id\"",
+                "events.rules[0].title is required",
+            ),
+            (
+                "command = \"acme\"\n[[events.rules]]\non = \"x\"\nupdate = \"message_chunk\"\ntext = \"text\"",
+                "events.rules[0].text must be a JSON pointer",
+            ),
+            (
+                "command = \"acme\"\n[prompt]\nformat = \"argument\"\n[[events.rules]]\non = \"x\"\nupdate = \"turn_end\"",
+                "args must contain {{prompt}}",
+            ),
+            (
+                "command = \"acme\"\n[modes]\nturbo = []\n[[events.rules]]\non = \"x\"\nupdate = \"turn_end\"",
+                "unknown permission mode `turbo`",
+            ),
+            (
+                "command = \"acme\"\n[statuses]\nok = \"done\"\n[[events.rules]]\non = \"x\"\nupdate = \"turn_end\"",
+                "statuses.ok must be one of",
+            ),
+        ] {
+            match parse(contents) {
+                Err(ConfigError::Invalid { message: err, .. }) => {
+                    assert!(err.contains(message), "{} does not mention {}", err, message)
+                }
+                other => panic!("expected {} for {:?}, got {:?}", message, contents, other),
+            }
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Generic adapter tests against a scripted line-delimited JSON CLI.
+//!
+//! `tests/fixtures/acme.toml` maps the events that the shared `fake-cli`
+//! replays from `tests/fixtures/jsonl/`; see `support/cli_adapter.rs`.
+
+#[path = "../../acp-lazy-core/tests/support/cli_adapter.rs"]
+mod support;
+
+use std::path::Path;
+
+use acp_lazy_core::config::BridgeConfig;
+use agent_client_protocol::{
+    ContentBlock, PlanEntryStatus, SessionUpdate, StopReason, ToolCallContent, ToolCallStatus,
+    ToolKind,
+};
+use anyhow::Result;
+use jsonl_cli_acp::jsonl_agent;
+use jsonl_cli_acp::spec::AdapterSpec;
+use support::{fake_cli, fixtures_dir, FakeCli, TestSession};
+
+/// Session running `spec` with no config overrides.
+async fn start(cli: &FakeCli, spec: AdapterSpec) -> Result<TestSession> {
+    let loader = cli.config_loader(BridgeConfig::default())?;
+    cli.session(jsonl_agent::runtime(spec, loader)?).await
+}
+
+/// Session running `acme.toml` with `transcript` replayed by `fake-cli`.
+async fn fake_acme(cli: &FakeCli, transcript: &str) -> Result<TestSession> {
+    let spec_path = fixtures_dir().join("acme.toml");
+    let fake_args: Vec<String> = cli
+        .args(&fixtures_dir().join("jsonl").join(transcript))
+        .iter()
+        .map(|arg| format!("{:?}", arg))
+        .collect();
+    let contents = std::fs::read_to_string(&spec_path)?
+        .replace("@FAKE_CLI@", &fake_cli().display().to_string())
+        .replace("@FAKE_ARGS@", &fake_args.join(", "));
+    start(cli, AdapterSpec::from_toml_str(&contents, &spec_path)?).await
+}
+
+#[tokio::test]
+async fn events_map_to_session_updates() -> Result<()> {
+    let cli = FakeCli::new()?;
+    let mut session = fake_acme(&cli, "turn.jsonl").await?;
+
+    let response = session.prompt("List it").await?;
+
+    assert_eq!(response.stop_reason, StopReason::EndTurn);
+    assert_eq!(
+        response.meta,
+        Some(serde_json::json!({"usage": {"tokens": 42}}))
+    );
+
+    let updates = session.drain();
+    assert_eq!(updates.len(), 5, "{:#?}", updates);
+    assert!(matches!(
+        &updates[0],
+        SessionUpdate::AgentThoughtChunk { content: ContentBlock::Text(text) }
+            if text.text == "Looking at the tree."
+    ));
+
+    let SessionUpdate::Plan(plan) = &updates[1] else {
+        panic!("expected a plan, got {:?}", updates[1]);
+    };
+    let entries: Vec<_> = plan
+        .entries
+        .iter()
+        .map(|entry| (entry.content.as_str(), entry.status.clone()))
+        .collect();
+    assert_eq!(
+        entries,
+        vec![
+            ("List sources", PlanEntryStatus::InProgress),
+            ("Report", PlanEntryStatus::Pending),
+        ]
+    );
+
+    let SessionUpdate::ToolCall(call) = &updates[2] else {
+        panic!("expected a tool call, got {:?}", updates[2]);
+    };
+    assert_eq!(call.id.0.as_ref(), "call-1");
+    assert_eq!(call.title, "shell");
+    assert_eq!(call.kind, ToolKind::Execute);
+    assert_eq!(call.status, ToolCallStatus::InProgress);
+    assert_eq!(call.raw_input, Some(serde_json::json!({"cmd": "ls src"})));
+
+    let SessionUpdate::ToolCallUpdate(done) = &updates[3] else {
+        panic!("expected a tool call update, got {:?}", updates[3]);
+    };
+    assert_eq!(done.id.0.as_ref(), "call-1");
+    assert_eq!(done.fields.status, Some(ToolCallStatus::Completed));
+    assert_eq!(
+        done.fields.content,
+        Some(vec![ToolCallContent::from("main.rs")])
+    );
+
+    // The user-role `say` event does not match the `when` filter.
+    assert!(matches!(
+        &updates[4],
+        SessionUpdate::AgentMessageChunk { content: ContentBlock::Text(text) }
+            if text.text == "Found main.rs."
+    ));
+
+    let recorded = cli.recorded()?;
+    assert!(recorded.contains(&format!("--cwd {}", cli.dir().display())));
+    assert!(!recorded.contains("--resume"));
+    assert!(!recorded.contains("--allow-edits"));
+    assert!(recorded.contains(r#"PROMPT: {"op":"ask","text":"List it"}"#));
+    Ok(())
+}
+
+#[tokio::test]
+async fn later_turns_resume_the_captured_session_with_mode_args() -> Result<()> {
+    let cli = FakeCli::new()?;
+    let session = fake_acme(&cli, "turn.jsonl").await?;
+
+    session.prompt("first").await?;
+    session.set_mode("acceptEdits").await?;
+    session.prompt("second").await?;
+
+    let recorded = cli.recorded()?;
+    assert!(recorded.contains("--allow-edits --resume acme-session-1"));
+    assert!(recorded.contains(r#""text":"second""#));
+    Ok(())
+}
+
+#[tokio::test]
+async fn turn_end_errors_fail_the_prompt() -> Result<()> {
+    let cli = FakeCli::new()?;
+    let session = fake_acme(&cli, "failed.jsonl").await?;
+
+    let err = session
+        .prompt("hello")
+        .await
+        .expect_err("turn_end error should fail the prompt");
+    assert_eq!(err.data, Some(serde_json::json!("model overloaded")));
+    Ok(())
+}
+
+#[tokio::test]
+async fn exit_status_decides_turns_without_turn_end() -> Result<()> {
+    let cli = FakeCli::new()?;
+
+    for (script, ok) in [("cat >/dev/null", true), ("cat >/dev/null; exit 3", false)] {
+        let spec = AdapterSpec::from_toml_str(
+            &format!(
+                "command = \"sh\"\nargs = [\"-c\", {:?}]\n\n[[events.rules]]\non = \"x\"\nupdate = \"message_chunk\"\ntext = \"/text\"\n",
+                script
+            ),
+            Path::new("inline.toml"),
+        )?;
+        let session = start(&cli, spec).await?;
+
+        let result = session.prompt("hi").await;
+        match result {
+            Ok(response) => {
+                assert!(ok, "{} should fail the prompt", script);
+                assert_eq!(response.stop_reason, StopReason::EndTurn);
+            }
+            Err(err) => {
+                assert!(!ok, "{} failed: {:?}", script, err);
+                assert!(format!("{:?}", err.data).contains("jsonl exited with"));
+            }
+        }
+    }
+    Ok(())
+}